@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use crate::{block::BlockType, chunk_master::ChunkMaster, math::{Coord3, Vec3}, protocol::MOVE_SEQUENCE_RATE};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveCheck {
+    Accepted,
+    // starszy niz ruch juz przyjety, np. po przestawieniu pakietow
+    Stale,
+    // klient musi wrocic do pozycji z serwera
+    Rejected
+}
+
+// to, co serwer wie o graczu, rozstrzyga o jego ruchach i zmianach blokow
+pub struct PlayerAuthority{
+    position: Vec3,
+    last_move: u32,
+    // najwyzsza przyjeta sekwencja edycji i ostatnia sekwencja dla kazdego bloku z okna EDIT_WINDOW
+    last_edit: u32,
+    edits: HashMap<Coord3, u32>
+}
+impl PlayerAuthority {
+    // szybciej niz spadanie z predkoscia graniczna
+    pub const MAX_SPEED: f32 = 64.0;
+    // po dluzszej przerwie w ruchach gracz nie dostaje wiecej zapasu
+    const MAX_SEQUENCE_GAP: u32 = 60;
+    // dalej od gracza nie mozna zmieniac blokow
+    pub const MAX_EDIT_DISTANCE: f32 = 8.0;
+    // starsze edycje sa odrzucane bez sprawdzania, wiec mapa nie rosnie bez konca
+    const EDIT_WINDOW: u32 = 256;
+
+    pub fn new(position: Vec3) -> Self{
+        PlayerAuthority{position, last_move: 0, last_edit: 0, edits: HashMap::new()}
+    }
+    pub fn get_position(&self) -> Vec3{
+        self.position
+    }
+    pub fn check_move(&mut self, sequence: u32, position: Vec3) -> MoveCheck{
+        if sequence <= self.last_move{
+            return MoveCheck::Stale;
+        }
+        let gap = (sequence-self.last_move).min(PlayerAuthority::MAX_SEQUENCE_GAP);
+        self.last_move = sequence;
+        let allowed = PlayerAuthority::MAX_SPEED*gap as f32/MOVE_SEQUENCE_RATE as f32;
+        if (position-self.position).magnitude() > allowed{
+            return MoveCheck::Rejected;
+        }
+        self.position = position;
+        MoveCheck::Accepted
+    }
+    // true, gdy blok sie zmienil; w kazdym przypadku gracz dostaje prawdziwy stan bloku
+    // przestawione edycje roznych blokow przechodza, odrzucane sa powtorzone i starsze dla tego samego bloku
+    pub fn apply_edit(&mut self, world: &impl ChunkMaster, sequence: u32, position: Coord3, block: BlockType) -> bool{
+        if sequence.saturating_add(PlayerAuthority::EDIT_WINDOW) <= self.last_edit
+            || self.edits.get(&position).is_some_and(|last| sequence <= *last){
+            return false;
+        }
+        self.edits.insert(position, sequence);
+        if sequence > self.last_edit{
+            self.last_edit = sequence;
+            let oldest = sequence.saturating_sub(PlayerAuthority::EDIT_WINDOW);
+            self.edits.retain(|_, last| *last > oldest);
+        }
+        let center = Vec3::new(position.x as f32, position.y as f32, position.z as f32);
+        if (center-self.position).magnitude() > PlayerAuthority::MAX_EDIT_DISTANCE{
+            return false;
+        }
+        world.get_voxel(position) != block && world.set_voxel(position, block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_master::MemoryWorld;
+
+    use super::*;
+
+    #[test]
+    fn moves_faster_than_allowed_are_rejected(){
+        let mut authority = PlayerAuthority::new(Vec3::ZERO);
+        assert_eq!(authority.check_move(1, Vec3::new(0.5, 0.0, 0.0)), MoveCheck::Accepted);
+        assert_eq!(authority.check_move(2, Vec3::new(20.0, 0.0, 0.0)), MoveCheck::Rejected);
+        assert_eq!(authority.get_position(), Vec3::new(0.5, 0.0, 0.0));
+        // po przerwie wolno przejsc dalej
+        assert_eq!(authority.check_move(32, Vec3::new(20.0, 0.0, 0.0)), MoveCheck::Accepted);
+        assert_eq!(authority.check_move(30, Vec3::ZERO), MoveCheck::Stale);
+    }
+
+    #[test]
+    fn stale_and_distant_edits_are_ignored(){
+        let world = MemoryWorld::with_chunks(Coord3::ZERO, Coord3::ZERO);
+        let mut authority = PlayerAuthority::new(Vec3::new(1.0, 1.0, 1.0));
+        let position = Coord3::new(1, 0, 1);
+        assert!(authority.apply_edit(&world, 2, position, BlockType::Stone));
+        assert!(!authority.apply_edit(&world, 1, position, BlockType::Air));
+        assert!(!authority.apply_edit(&world, 2, position, BlockType::Air));
+        assert_eq!(world.get_voxel(position), BlockType::Stone);
+        assert!(!authority.apply_edit(&world, 3, Coord3::new(40, 0, 1), BlockType::Stone));
+        // edycja spoza okna jest za stara nawet dla nowego bloku
+        assert!(authority.apply_edit(&world, 1000, Coord3::new(3, 0, 1), BlockType::Stone));
+        assert!(!authority.apply_edit(&world, 4, Coord3::new(2, 0, 1), BlockType::Stone));
+        assert_eq!(world.get_voxel(Coord3::new(2, 0, 1)), BlockType::Air);
+    }
+
+    #[test]
+    fn reordered_edits_of_different_blocks_both_land(){
+        let world = MemoryWorld::with_chunks(Coord3::ZERO, Coord3::ZERO);
+        let mut authority = PlayerAuthority::new(Vec3::new(1.0, 1.0, 1.0));
+        assert!(authority.apply_edit(&world, 2, Coord3::new(1, 0, 1), BlockType::Stone));
+        assert!(authority.apply_edit(&world, 1, Coord3::new(2, 0, 1), BlockType::Sand));
+        assert_eq!(world.get_voxel(Coord3::new(1, 0, 1)), BlockType::Stone);
+        assert_eq!(world.get_voxel(Coord3::new(2, 0, 1)), BlockType::Sand);
+        // powtorzony pakiet niczego nie zmienia
+        world.set_voxel(Coord3::new(2, 0, 1), BlockType::Air);
+        assert!(!authority.apply_edit(&world, 1, Coord3::new(2, 0, 1), BlockType::Sand));
+    }
+}
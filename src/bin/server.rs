@@ -0,0 +1,70 @@
+use std::{env, io::BufRead, path::PathBuf, process::ExitCode, sync::atomic::Ordering, thread};
+
+use my_opengl_game::{chunk_loader::ChunkLoader, server::{Server, ServerSettings}};
+
+const USAGE: &str = "usage: server [--port N] [--world DIR] [--seed N] [--view-distance N]";
+
+// serwer bez okna: --port N, --world katalog, --seed N, --view-distance N; komenda stop zapisuje swiat i konczy
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (settings, port) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut server = Server::bind(&format!("0.0.0.0:{port}"), settings).expect("cannot start server");
+    println!("server listening on {}, seed {}, type stop to save and quit", server.local_addr().unwrap(), server.get_seed());
+    let stop = server.get_stop_flag();
+    thread::spawn(move ||{
+        for line in std::io::stdin().lock().lines().map_while(Result::ok){
+            if line.trim() == "stop"{
+                stop.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    });
+    match server.run() {
+        Ok(()) => {
+            println!("server stopped");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            println!("server stopped: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(ServerSettings, u16), String>{
+    let value = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .map(|i| args.get(i+1).cloned().ok_or(format!("{name} needs a value")))
+        .transpose();
+
+    let mut settings = ServerSettings{
+        world_dir: Some(PathBuf::from(value("--world")?.unwrap_or("world".to_string()))),
+        ..Default::default()
+    };
+    if let Some(seed) = value("--seed")?{
+        settings.seed = parse(&seed, "--seed")?;
+    }
+    if let Some(distance) = value("--view-distance")?{
+        let distance: i32 = parse(&distance, "--view-distance")?;
+        if !(1..=ChunkLoader::MAX_LOAD_DISTANCE).contains(&distance){
+            return Err(format!("--view-distance must be between 1 and {}", ChunkLoader::MAX_LOAD_DISTANCE));
+        }
+        settings.view_distance = distance;
+    }
+    let port = match value("--port")? {
+        Some(port) => parse(&port, "--port")?,
+        None => Server::DEFAULT_PORT
+    };
+    Ok((settings, port))
+}
+
+fn parse<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String>{
+    value.parse().map_err(|_| format!("invalid value '{value}' for {name}"))
+}
@@ -0,0 +1,115 @@
+use std::{env, path::{Path, PathBuf}, process::ExitCode};
+
+use my_opengl_game::{chunk_master::MemoryWorld, export::{self, ExportFormat, Region}, persistence::WorldStorage, server::ServerSettings, world_generator::WorldGenerator, structure::{Rotation, Structure}, world_tool::{self, ChunkInfo}};
+
+const USAGE: &str = "usage: voxel-tool <command> [options]
+  pregen --world DIR --radius N [--seed N] [--center X,Y,Z]
+  info --world DIR X,Y,Z
+  export --world DIR --out FILE [--format xyz|obj|gltf|vox] [--min X,Y,Z --max X,Y,Z | --chunk X,Y,Z]
+  import --world DIR --file FILE.vox|FILE.xyz --origin X,Y,Z [--rotate 0|90|180|270] [--seed N]
+  diff WORLD_A WORLD_B";
+
+// narzedzie do swiatow na maszynach bez ekranu, nie uzywa OpenGL
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String>{
+    let (command, rest) = args.split_first().ok_or("missing command")?;
+    let value = |name: &str| rest.iter()
+        .position(|arg| arg == name)
+        .map(|i| rest.get(i+1).cloned().ok_or(format!("{name} needs a value")))
+        .transpose();
+    // argumenty bez nazwy, pomijajac wartosci opcji
+    let positional: Vec<&String> = rest.iter().enumerate()
+        .filter(|(i, arg)| !arg.starts_with("--") && (*i == 0 || !rest[i-1].starts_with("--")))
+        .map(|(_, arg)| arg)
+        .collect();
+    // ziarno dla nowego swiata, istniejacy swiat zostaje przy swoim
+    let seed_arg = || -> Result<u32, String>{
+        match value("--seed")? {
+            Some(seed) => parse(&seed, "--seed"),
+            None => Ok(ServerSettings::default().seed)
+        }
+    };
+    match command.as_str() {
+        "pregen" => {
+            let storage = open_world(&value("--world")?.ok_or("--world is required")?, true)?;
+            let radius: i32 = parse(&value("--radius")?.ok_or("--radius is required")?, "--radius")?;
+            let seed = storage.load_or_save_seed(seed_arg()?).map_err(|error| error.to_string())?;
+            let center = world_tool::parse_position(&value("--center")?.unwrap_or("0,0,0".to_string()))?;
+            let stats = world_tool::pregen(&storage, &WorldGenerator::new(seed), center, radius).map_err(|error| error.to_string())?;
+            println!("seed {seed}: generated {} chunks, {} already saved", stats.generated, stats.skipped);
+        }
+        "info" => {
+            let storage = open_world(&value("--world")?.ok_or("--world is required")?, false)?;
+            let position = world_tool::parse_position(positional.first().ok_or("missing chunk position")?)?;
+            let chunk = storage.load_chunk(position)
+                .map_err(|error| error.to_string())?
+                .ok_or(format!("chunk {position} is not saved"))?;
+            print!("{}", ChunkInfo::new(&chunk));
+        }
+        "export" => {
+            let storage = open_world(&value("--world")?.ok_or("--world is required")?, false)?;
+            let path = PathBuf::from(value("--out")?.ok_or("--out is required")?);
+            let format = match value("--format")? {
+                Some(name) => ExportFormat::parse(&name).ok_or(format!("unknown format '{name}'"))?,
+                None => ExportFormat::from_path(&path).ok_or("cannot tell the format from --out, use --format")?
+            };
+            // bez granic eksportowane sa wszystkie zapisane chunki
+            let region = match (value("--min")?, value("--max")?, value("--chunk")?) {
+                (Some(min), Some(max), None) => Region::new(world_tool::parse_position(&min)?, world_tool::parse_position(&max)?),
+                (None, None, Some(chunk)) => Region::from_chunk(world_tool::parse_position(&chunk)?),
+                (None, None, None) => world_tool::get_saved_region(&storage).map_err(|error| error.to_string())?.ok_or("the world has no saved chunks")?,
+                _ => return Err("use --min with --max, or --chunk".to_string())
+            };
+            let world: MemoryWorld = world_tool::load_region(&storage, &region).map_err(|error| error.to_string())?;
+            export::export_to_file(&world, &region, format, &path).map_err(|error| format!("{}: {error}", path.display()))?;
+            println!("exported {} .. {} to {} as {format:?}", region.min, region.max, path.display());
+        }
+        "import" => {
+            let storage = open_world(&value("--world")?.ok_or("--world is required")?, true)?;
+            let structure = Structure::load(Path::new(&value("--file")?.ok_or("--file is required")?))?;
+            let origin = world_tool::parse_position(&value("--origin")?.ok_or("--origin is required")?)?;
+            let rotation = match value("--rotate")? {
+                Some(rotation) => Rotation::parse(&rotation).ok_or(format!("invalid value '{rotation}' for --rotate"))?,
+                None => Rotation::None
+            };
+            let seed = storage.load_or_save_seed(seed_arg()?).map_err(|error| error.to_string())?;
+            let changed = world_tool::paste_structure(&storage, &WorldGenerator::new(seed), &structure, origin, rotation).map_err(|error| error.to_string())?;
+            println!("pasted {} blocks at {origin}, {changed} changed", structure.get_blocks().len());
+        }
+        "diff" => {
+            let [a, b] = positional[..] else {
+                return Err("diff needs two worlds".to_string());
+            };
+            let diff = world_tool::diff(&open_world(a, false)?, &open_world(b, false)?).map_err(|error| error.to_string())?;
+            print!("{diff}");
+            // jak diff: 1 gdy swiaty sie roznia
+            if !diff.is_empty(){
+                return Ok(ExitCode::from(1));
+            }
+        }
+        _ => return Err(format!("unknown command '{command}'"))
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// tylko pregen i import tworza nowy katalog, literowka w innych komendach jest bledem
+fn open_world(dir: &str, create: bool) -> Result<WorldStorage, String>{
+    if !create && !Path::new(dir).is_dir(){
+        return Err(format!("no world at {dir}"));
+    }
+    WorldStorage::open(dir).map_err(|error| format!("{dir}: {error}"))
+}
+
+fn parse<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String>{
+    value.parse().map_err(|_| format!("invalid value '{value}' for {name}"))
+}
@@ -25,6 +25,17 @@ impl From<u16> for BlockType {
 }
 
 impl BlockType {
+    pub const ALL: [BlockType; 6] = [
+        BlockType::Air, BlockType::Stone, BlockType::Dirt, BlockType::Grass, BlockType::Water, BlockType::Sand
+    ];
+    // bezpieczna wersja From<u16> dla danych z pliku albo z sieci
+    pub fn from_id(id: u16) -> Option<BlockType>{
+        BlockType::ALL.into_iter().find(|block| *block as u16 == id)
+    }
+    // nazwa jak w kodzie, bez rozrozniania wielkosci liter
+    pub fn parse(name: &str) -> Option<BlockType>{
+        BlockType::ALL.into_iter().find(|block| format!("{:?}", block).eq_ignore_ascii_case(name))
+    }
     pub fn get_color(self) -> Vector3<f32>{
         match self {
             BlockType::Dirt => Vector3::new(0.5, 0.25, 0.1), //133, 67, 18
@@ -42,4 +53,16 @@ impl BlockType {
             _ => false,
         }
     }
+    // czy sciana bloku od strony sasiada jest rysowana; jedna regula dla siatek chunkow, LOD i eksportu
+    pub fn is_face_visible(self, neighbor: BlockType) -> bool{
+        self != BlockType::Air && (neighbor == BlockType::Air || (self != BlockType::Water && neighbor == BlockType::Water))
+    }
+    // blok zatrzymujacy ruch gracza
+    pub fn is_solid(self) -> bool{
+        !self.is_transparent()
+    }
+    // blok spadajacy, gdy nic go nie podtrzymuje
+    pub fn is_falling(self) -> bool{
+        matches!(self, BlockType::Sand)
+    }
 }
\ No newline at end of file
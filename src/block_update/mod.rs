@@ -0,0 +1,144 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{block::BlockType, chunk_master::ChunkMaster, fluid, math::Coord3};
+
+// kolejka pozycji do sprawdzenia w kolejnym kroku symulacji, wspolna dla wszystkich mechanik
+// (woda, spadajace bloki), kazda pozycja jest w kolejce najwyzej raz
+pub struct BlockUpdateScheduler{
+    queue: VecDeque<Coord3>,
+    queued: HashSet<Coord3>,
+    max_updates_per_tick: usize
+}
+impl Default for BlockUpdateScheduler {
+    fn default() -> Self {
+        BlockUpdateScheduler::new(BlockUpdateScheduler::DEFAULT_UPDATES_PER_TICK)
+    }
+}
+impl BlockUpdateScheduler {
+    pub const DEFAULT_UPDATES_PER_TICK: usize = 512;
+
+    pub fn new(max_updates_per_tick: usize) -> Self{
+        BlockUpdateScheduler{
+            queue: VecDeque::new(),
+            queued: HashSet::new(),
+            max_updates_per_tick
+        }
+    }
+    pub fn get_pending(&self) -> usize{
+        self.queue.len()
+    }
+    pub fn schedule_single(&mut self, position: Coord3){
+        if self.queued.insert(position){
+            self.queue.push_back(position);
+        }
+    }
+    // po zmianie bloku sprawdzany jest on sam i jego sasiedzi
+    pub fn schedule(&mut self, position: Coord3){
+        self.schedule_single(position);
+        for offset in Coord3::neighbors_into_iter(){
+            self.schedule_single(position+offset);
+        }
+    }
+    // pozycje zaplanowane przed tym krokiem, najwyzej max_updates_per_tick
+    pub fn take_batch(&mut self) -> Vec<Coord3>{
+        let count = self.queue.len().min(self.max_updates_per_tick);
+        let batch: Vec<Coord3> = self.queue.drain(..count).collect();
+        for position in batch.iter(){
+            self.queued.remove(position);
+        }
+        batch
+    }
+    // jeden krok wszystkich mechanik, zwraca zmienione woksele
+    pub fn tick(&mut self, world: &impl ChunkMaster) -> Vec<Coord3>{
+        let mut changed = Vec::new();
+        for position in self.take_batch(){
+            update_falling_block(position, world, self, &mut changed);
+            fluid::update_fluid(position, world, self, &mut changed);
+        }
+        changed
+    }
+}
+
+// blok z grawitacja spada o jeden woksel na krok, az trafi na cos stalego, wode wypiera
+pub fn update_falling_block(position: Coord3, world: &impl ChunkMaster, scheduler: &mut BlockUpdateScheduler, changed: &mut Vec<Coord3>){
+    let block = world.get_voxel(position);
+    if !block.is_falling(){
+        return;
+    }
+    let below = position.lower();
+    if world.get_voxel(below).is_solid(){
+        return;
+    }
+    // ponizej niezaladowanego chunka blok zostaje na miejscu
+    if !world.set_voxel(below, block){
+        return;
+    }
+    world.set_voxel(position, BlockType::Air);
+    changed.push(position);
+    changed.push(below);
+    scheduler.schedule(position);
+    scheduler.schedule(below);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_master::MemoryWorld;
+
+    use super::*;
+
+    fn test_world() -> MemoryWorld{
+        let world = MemoryWorld::default();
+        world.fill(Coord3::ZERO, Coord3::new(9, 0, 9), BlockType::Stone);
+        world
+    }
+
+    fn run(scheduler: &mut BlockUpdateScheduler, world: &MemoryWorld, ticks: usize){
+        for _ in 0..ticks{
+            scheduler.tick(world);
+        }
+    }
+
+    #[test]
+    fn sand_falls_until_it_lands(){
+        let world = test_world();
+        world.set_voxel(Coord3::new(3, 8, 3), BlockType::Sand);
+        world.set_voxel(Coord3::new(3, 9, 3), BlockType::Sand);
+        world.set_voxel(Coord3::new(5, 8, 5), BlockType::Stone);
+        let mut scheduler = BlockUpdateScheduler::default();
+        scheduler.schedule(Coord3::new(3, 8, 3));
+        scheduler.schedule(Coord3::new(5, 8, 5));
+        run(&mut scheduler, &world, 20);
+        assert_eq!(world.get_voxel(Coord3::new(3, 1, 3)), BlockType::Sand);
+        assert_eq!(world.get_voxel(Coord3::new(3, 2, 3)), BlockType::Sand);
+        assert_eq!(world.get_voxel(Coord3::new(3, 8, 3)), BlockType::Air);
+        assert_eq!(world.get_voxel(Coord3::new(5, 8, 5)), BlockType::Stone);
+        assert_eq!(scheduler.get_pending(), 0);
+    }
+
+    #[test]
+    fn removing_support_wakes_sand_above(){
+        let world = test_world();
+        world.set_voxel(Coord3::new(2, 1, 2), BlockType::Dirt);
+        world.set_voxel(Coord3::new(2, 2, 2), BlockType::Sand);
+        let mut scheduler = BlockUpdateScheduler::default();
+        run(&mut scheduler, &world, 5);
+        assert_eq!(world.get_voxel(Coord3::new(2, 2, 2)), BlockType::Sand);
+
+        world.set_voxel(Coord3::new(2, 1, 2), BlockType::Air);
+        scheduler.schedule(Coord3::new(2, 1, 2));
+        run(&mut scheduler, &world, 5);
+        assert_eq!(world.get_voxel(Coord3::new(2, 1, 2)), BlockType::Sand);
+        assert_eq!(world.get_voxel(Coord3::new(2, 2, 2)), BlockType::Air);
+    }
+
+    #[test]
+    fn batches_are_bounded_and_deduplicated(){
+        let mut scheduler = BlockUpdateScheduler::new(4);
+        scheduler.schedule(Coord3::ZERO);
+        scheduler.schedule(Coord3::new(0, 1, 0));
+        // 7 + 5 nowych (ZERO i (0,1,0) juz sa w kolejce)
+        assert_eq!(scheduler.get_pending(), 12);
+        assert_eq!(scheduler.take_batch().len(), 4);
+        assert_eq!(scheduler.get_pending(), 8);
+    }
+}
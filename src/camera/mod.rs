@@ -1,6 +1,6 @@
 use nalgebra::{Matrix4, Perspective3, Point3, Vector3};
 
-use crate::Vec3;
+use crate::math::{Frustum, Vec3};
 
 pub struct Camera{
     position: Vec3,
@@ -17,6 +17,9 @@ impl Default for  Camera{
     }
 }
 impl Camera {
+    pub const FOVY: f32 = std::f32::consts::FRAC_PI_4; // 45 stopni w radianach
+    pub const NEAR: f32 = 0.1;                         // odległość do najbliższej płaszczyzny widzenia
+    pub const FAR: f32 = 3000.0;                        // odległość do najdalszej płaszczyzny widzenia
     pub fn new() -> Self{
         Camera{
             position: Vec3::new(0.0, 40.0, -3.25),
@@ -47,14 +50,16 @@ impl Camera {
         rotation.transpose() * translation 
     }
     pub fn get_projection_matrix(&self, ratio: f32) -> Matrix4<f32>{
-        let fovy = std::f32::consts::FRAC_PI_4; // 45 stopni w radianach
+        self.get_projection_matrix_range(ratio, Camera::NEAR, Camera::FAR)
+    }
+    pub fn get_projection_matrix_range(&self, ratio: f32, near: f32, far: f32) -> Matrix4<f32>{
         let aspect_ratio = ratio;// 16.0 / 9.0;          // typowy stosunek szerokości do wysokości ekranu
-        let near = 0.1;                         // odległość do najbliższej płaszczyzny widzenia
-        let far = 1000.0;                        // odległość do najdalszej płaszczyzny widzenia
-        
-        let perspective = Perspective3::new(aspect_ratio, fovy, near, far);
+        let perspective = Perspective3::new(aspect_ratio, Camera::FOVY, near, far);
         perspective.to_homogeneous()
     }
+    pub fn get_frustum(&self, ratio: f32) -> Frustum{
+        Frustum::from_matrix(&(self.get_projection_matrix(ratio)*self.get_view_matrix()))
+    }
     pub fn set_look_at(&mut self, look_at: Vec3){
         self.look_at = look_at;
     }
@@ -1,23 +1,33 @@
 
-use crate::{c3d3, chunk_master::ChunkMaster, math::Coord3, block::BlockType};
+use std::collections::HashMap;
+
+use crate::{c3d3, chunk_master::Neighbourhood, math::Coord3, block::BlockType};
 
 #[derive(Clone)]
 pub struct Chunk{
     chunk_position: Coord3,
     voxels_table: Box<[BlockType; Chunk::CHUNK_SIZE.pow(3)]>,
-    voxels: Vec<usize>
+    voxels: Vec<usize>,
+    // miejsce kazdego indeksu w voxels, zeby usuwanie nie szukalo liniowo;
+    // tworzone przy pierwszym usunieciu bloku, wygenerowane i nieruszane chunki go nie maja
+    slots: Option<Box<[u32]>>,
+    // poziomy plynacej wody, woda bez wpisu jest zrodlem
+    fluid_levels: HashMap<usize, u8>
 }
 impl Default for Chunk{
     fn default() -> Self {
         Chunk{
             chunk_position: Coord3::new(0, 0, 0),
             voxels_table: Box::new([BlockType::Air; Chunk::CHUNK_SIZE.pow(3)]),
-            voxels: Vec::new()
+            voxels: Vec::new(),
+            slots: None,
+            fluid_levels: HashMap::new()
         }
     }
 }
 impl Chunk {
     pub const CHUNK_SIZE: usize = 50;
+    pub const SOURCE_FLUID_LEVEL: u8 = 8;
     #[inline(always)]
     pub fn is_border(local_position: Coord3) -> bool{
         (local_position.x == 0) | (local_position.x == Chunk::CHUNK_SIZE as i32-1)
@@ -59,18 +69,55 @@ impl Chunk {
     pub fn get_voxel_from_index(&self, index: usize) -> BlockType{
         self.voxels_table[index]
     }
-    // TODO: dodac ustawianie na 0
     #[inline(always)]
     pub fn set_voxel(&mut self, local_coord: Coord3, value: BlockType){
-        if value == BlockType::Air{
-            panic!("Not implemented yet");
-        }
         let index = Chunk::get_index(local_coord);
-        if self.get_voxel(local_coord) == BlockType::Air{
+        let previous = self.voxels_table[index];
+        if previous == BlockType::Air && value != BlockType::Air{
+            if let Some(slots) = &mut self.slots{
+                slots[index] = self.voxels.len() as u32;
+            }
             self.voxels.push(index);
         }
+        else if previous != BlockType::Air && value == BlockType::Air{
+            let voxels = &self.voxels;
+            let slots = self.slots.get_or_insert_with(||{
+                let mut slots = vec![0; Chunk::CHUNK_SIZE.pow(3)].into_boxed_slice();
+                for (slot, voxel) in voxels.iter().enumerate(){
+                    slots[*voxel] = slot as u32;
+                }
+                slots
+            });
+            let slot = slots[index] as usize;
+            self.voxels.swap_remove(slot);
+            if let Some(moved) = self.voxels.get(slot){
+                slots[*moved] = slot as u32;
+            }
+        }
+        if value != BlockType::Water{
+            self.fluid_levels.remove(&index);
+        }
         self.voxels_table[index] = value;
     }
+    // 0 dla blokow, ktore nie sa woda
+    pub fn get_fluid_level(&self, local_position: Coord3) -> u8{
+        let index = Chunk::get_index(local_position);
+        if self.voxels_table[index] != BlockType::Water{
+            return 0;
+        }
+        self.fluid_levels.get(&index).copied().unwrap_or(Chunk::SOURCE_FLUID_LEVEL)
+    }
+    // ustawia wode o danym poziomie, od 1 do SOURCE_FLUID_LEVEL
+    pub fn set_fluid(&mut self, local_position: Coord3, level: u8){
+        self.set_voxel(local_position, BlockType::Water);
+        let index = Chunk::get_index(local_position);
+        if level >= Chunk::SOURCE_FLUID_LEVEL{
+            self.fluid_levels.remove(&index);
+        }
+        else{
+            self.fluid_levels.insert(index, level.max(1));
+        }
+    }
     #[inline(always)]
     pub fn get_chunk_position(&self) -> Coord3{
         self.chunk_position
@@ -83,7 +130,123 @@ impl Chunk {
     pub fn get_voxels(&self) -> &Vec<usize>{
         &self.voxels
     }
-    pub fn calculate_faces_table(&self, master: &impl ChunkMaster) -> Vec<u8>{
+    // zapis do bajtow: pary (id bloku u16, dlugosc serii u32), potem poziomy plynacej wody
+    pub fn encode(&self) -> Vec<u8>{
+        let mut bytes = Vec::new();
+        let mut runs: Vec<(BlockType, u32)> = Vec::new();
+        for block in self.voxels_table.iter(){
+            match runs.last_mut() {
+                Some((last, count)) if last == block => *count += 1,
+                _ => runs.push((*block, 1))
+            }
+        }
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (block, count) in runs{
+            bytes.extend_from_slice(&(block as u16).to_le_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+        let mut levels: Vec<(&usize, &u8)> = self.fluid_levels.iter().collect();
+        levels.sort();
+        bytes.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+        for (index, level) in levels{
+            bytes.extend_from_slice(&(*index as u32).to_le_bytes());
+            bytes.push(*level);
+        }
+        bytes
+    }
+    pub fn decode(chunk_position: Coord3, bytes: &[u8]) -> Result<Chunk, String>{
+        let mut reader = bytes;
+        let mut take = |count: usize| -> Result<&[u8], String>{
+            if reader.len() < count{
+                return Err("chunk data is truncated".to_string());
+            }
+            let (head, tail) = reader.split_at(count);
+            reader = tail;
+            Ok(head)
+        };
+        let mut chunk = Chunk{chunk_position, ..Default::default()};
+        let runs = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let mut index = 0;
+        for _ in 0..runs{
+            let id = u16::from_le_bytes(take(2)?.try_into().unwrap());
+            let count = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+            let block = BlockType::from_id(id).ok_or(format!("unknown block id {id}"))?;
+            if index+count > Chunk::CHUNK_SIZE.pow(3){
+                return Err("chunk data has too many voxels".to_string());
+            }
+            for i in index..index+count{
+                chunk.voxels_table[i] = block;
+                if block != BlockType::Air{
+                    chunk.voxels.push(i);
+                }
+            }
+            index += count;
+        }
+        if index != Chunk::CHUNK_SIZE.pow(3){
+            return Err(format!("chunk data has {index} voxels"));
+        }
+        let levels = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        for _ in 0..levels{
+            let index = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+            let level = take(1)?[0];
+            if chunk.voxels_table.get(index) != Some(&BlockType::Water) || level == 0 || level >= Chunk::SOURCE_FLUID_LEVEL{
+                return Err(format!("invalid fluid level {level} at {index}"));
+            }
+            chunk.fluid_levels.insert(index, level);
+        }
+        if !reader.is_empty(){
+            return Err(format!("{} bytes after chunk data", reader.len()));
+        }
+        Ok(chunk)
+    }
+    // sciany chunka, ktorych dotyka woksel (kolejnosc jak w Coord3::neighbors_into_iter)
+    #[inline(always)]
+    pub fn get_border_faces(local_position: Coord3) -> u8{
+        let last = Chunk::CHUNK_SIZE as i32-1;
+        let mut faces: u8 = 0;
+        if local_position.y == last {faces |= 1<<0;}
+        if local_position.y == 0 {faces |= 1<<1;}
+        if local_position.x == last {faces |= 1<<2;}
+        if local_position.x == 0 {faces |= 1<<3;}
+        if local_position.z == last {faces |= 1<<4;}
+        if local_position.z == 0 {faces |= 1<<5;}
+        faces
+    }
+    // flood fill po przezroczystych wokselach, zapamietuje ktore sciany chunka sa ze soba polaczone
+    pub fn calculate_visibility(&self) -> ChunkVisibility{
+        if self.is_empty(){
+            return ChunkVisibility::ALL;
+        }
+        let mut visibility = ChunkVisibility::NONE;
+        let mut visited = vec![false; Chunk::CHUNK_SIZE.pow(3)];
+        let mut stack: Vec<usize> = Vec::new();
+        for start in 0..Chunk::CHUNK_SIZE.pow(3){
+            if visited[start] || !self.voxels_table[start].is_transparent(){
+                continue;
+            }
+            let mut faces: u8 = 0;
+            visited[start] = true;
+            stack.push(start);
+            while let Some(index) = stack.pop(){
+                let local_position = Chunk::get_local_position_from_index(index);
+                faces |= Chunk::get_border_faces(local_position);
+                for offset in Coord3::neighbors_into_iter(){
+                    let next = local_position+offset;
+                    if Chunk::is_outer(next){
+                        continue;
+                    }
+                    let next_index = Chunk::get_index(next);
+                    if !visited[next_index] && self.voxels_table[next_index].is_transparent(){
+                        visited[next_index] = true;
+                        stack.push(next_index);
+                    }
+                }
+            }
+            visibility.connect_faces(faces);
+        }
+        visibility
+    }
+    pub fn calculate_faces_table(&self, neighbourhood: &Neighbourhood) -> Vec<u8>{
         let mut faces_table = [0 as u8; Chunk::CHUNK_SIZE.pow(3)];
         let neighbors: Vec<Coord3> = Coord3::neighbors_into_iter().collect();
         for indexref in self.voxels.iter(){
@@ -99,14 +262,13 @@ impl Chunk {
                     for i in 0..6{
                         let pos = local_position+neighbors[i];
                         let block_type = if Chunk::is_outer(pos){
-                            let world_position = self.get_world_position(pos);
-                            master.get_voxel(world_position)
+                            neighbourhood.get_voxel(pos)
                         }
                         else{
                             self.get_voxel(pos)
                         };
                         
-                        if !(block_type == BlockType::Air || (current_block != BlockType::Water && block_type == BlockType::Water)){
+                        if !current_block.is_face_visible(block_type){
                             mesh_type_mask |= 1<<i;
                         }
                     }
@@ -115,7 +277,7 @@ impl Chunk {
         faces_table.into()
     }
     //todo: rewrite
-    pub fn calculate_ambient_occlusion(&self, master: &impl ChunkMaster, faces_table: &Vec<u8>) -> Vec<u32>{
+    pub fn calculate_ambient_occlusion(&self, neighbourhood: &Neighbourhood, faces_table: &[u8]) -> Vec<u32>{
         let mut ao_mask_table = [0 as u32; Chunk::CHUNK_SIZE.pow(3)];
         for indexref in self.voxels.iter(){
                     let index: usize = *indexref;
@@ -159,9 +321,9 @@ impl Chunk {
                                 side2p = local_position+side2p;
                                 cornerp = local_position+cornerp;
 
-                                let side1_block = if Chunk::is_outer(side1p) {master.get_voxel(self.get_world_position(side1p))} else {self.get_voxel(side1p)};
-                                let side2_block = if Chunk::is_outer(side2p) {master.get_voxel(self.get_world_position(side2p))} else {self.get_voxel(side2p)};
-                                let corner_block = if Chunk::is_outer(cornerp) {master.get_voxel(self.get_world_position(cornerp))} else {self.get_voxel(cornerp)};
+                                let side1_block = if Chunk::is_outer(side1p) {neighbourhood.get_voxel(side1p)} else {self.get_voxel(side1p)};
+                                let side2_block = if Chunk::is_outer(side2p) {neighbourhood.get_voxel(side2p)} else {self.get_voxel(side2p)};
+                                let corner_block = if Chunk::is_outer(cornerp) {neighbourhood.get_voxel(cornerp)} else {self.get_voxel(cornerp)};
                                 
                                 let side1 = side1_block!=BlockType::Air&&side1_block!=BlockType::Water;
                                 let side2 = side2_block!=BlockType::Air&&side2_block!=BlockType::Water;
@@ -213,6 +375,34 @@ impl Chunk {
     }
 }
 
+// macierz 6x6 polaczen miedzy scianami chunka
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkVisibility{
+    connections: u64
+}
+impl ChunkVisibility {
+    pub const NONE: ChunkVisibility = ChunkVisibility{connections: 0};
+    pub const ALL: ChunkVisibility = ChunkVisibility{connections: (1<<36)-1};
+
+    pub fn connect(&mut self, face_a: usize, face_b: usize){
+        self.connections |= 1<<(face_a*6+face_b);
+        self.connections |= 1<<(face_b*6+face_a);
+    }
+    pub fn connect_faces(&mut self, faces: u8){
+        for a in 0..6{
+            for b in 0..6{
+                if faces & 1<<a != 0 && faces & 1<<b != 0{
+                    self.connect(a, b);
+                }
+            }
+        }
+    }
+    #[inline(always)]
+    pub fn is_connected(&self, face_a: usize, face_b: usize) -> bool{
+        self.connections & 1<<(face_a*6+face_b) != 0
+    }
+}
+
 pub struct ChunkCoordsIterator{
     cnt: usize
 }
@@ -232,4 +422,96 @@ impl Iterator for ChunkCoordsIterator {
         }
         None
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_slab_separates_top_from_bottom(){
+        let mut chunk = Chunk::default();
+        for x in 0..Chunk::CHUNK_SIZE as i32{
+            for z in 0..Chunk::CHUNK_SIZE as i32{
+                chunk.set_voxel(Coord3::new(x, 10, z), BlockType::Stone);
+            }
+        }
+        let visibility = chunk.calculate_visibility();
+        // 0: +y, 1: -y, 2: +x, 3: -x
+        assert!(!visibility.is_connected(0, 1));
+        assert!(visibility.is_connected(2, 3));
+        assert!(visibility.is_connected(0, 2));
+        assert!(visibility.is_connected(1, 3));
+    }
+
+    #[test]
+    fn setting_air_removes_voxel(){
+        let mut chunk = Chunk::default();
+        chunk.set_voxel(Coord3::new(1, 2, 3), BlockType::Stone);
+        chunk.set_voxel(Coord3::new(4, 5, 6), BlockType::Dirt);
+        chunk.set_voxel(Coord3::new(1, 2, 3), BlockType::Sand);
+        assert_eq!(chunk.get_voxels().len(), 2);
+
+        chunk.set_voxel(Coord3::new(1, 2, 3), BlockType::Air);
+        chunk.set_voxel(Coord3::new(7, 7, 7), BlockType::Air);
+        assert_eq!(chunk.get_voxels(), &vec![Chunk::get_index(Coord3::new(4, 5, 6))]);
+        assert_eq!(chunk.get_voxel(Coord3::new(1, 2, 3)), BlockType::Air);
+
+        chunk.set_voxel(Coord3::new(4, 5, 6), BlockType::Air);
+        assert!(chunk.is_empty());
+    }
+
+    #[test]
+    fn voxel_list_stays_exact_through_many_edits(){
+        let mut chunk = Chunk::default();
+        let size = Chunk::CHUNK_SIZE as i32;
+        for x in 0..size{
+            for z in 0..size{
+                chunk.set_voxel(Coord3::new(x, 0, z), BlockType::Stone);
+            }
+        }
+        // usuwanie na przemian z dodawaniem, tez ponownie tych samych blokow
+        for x in 0..size{
+            for z in (0..size).step_by(2){
+                chunk.set_voxel(Coord3::new(x, 0, z), BlockType::Air);
+                chunk.set_voxel(Coord3::new(x, 1, z), BlockType::Sand);
+            }
+        }
+        for x in (0..size).step_by(3){
+            chunk.set_voxel(Coord3::new(x, 0, 0), BlockType::Dirt);
+            chunk.set_voxel(Coord3::new(x, 1, 0), BlockType::Air);
+        }
+        let mut listed = chunk.get_voxels().clone();
+        listed.sort();
+        let expected: Vec<usize> = (0..Chunk::CHUNK_SIZE.pow(3)).filter(|index| chunk.get_voxel_from_index(*index) != BlockType::Air).collect();
+        assert_eq!(listed, expected);
+    }
+
+    #[test]
+    fn encoded_chunk_decodes_to_the_same_voxels(){
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(Coord3::new(1, -2, 3));
+        for x in 0..Chunk::CHUNK_SIZE as i32{
+            chunk.set_voxel(Coord3::new(x, 0, x%7), BlockType::Stone);
+        }
+        chunk.set_fluid(Coord3::new(3, 1, 3), 5);
+        chunk.set_fluid(Coord3::new(4, 1, 3), Chunk::SOURCE_FLUID_LEVEL);
+        let bytes = chunk.encode();
+        let decoded = Chunk::decode(Coord3::new(1, -2, 3), &bytes).unwrap();
+        assert_eq!(decoded.get_chunk_position(), Coord3::new(1, -2, 3));
+        assert_eq!(decoded.get_voxels().len(), chunk.get_voxels().len());
+        assert_eq!(decoded.get_voxel(Coord3::new(6, 0, 6)), BlockType::Stone);
+        assert_eq!(decoded.get_fluid_level(Coord3::new(3, 1, 3)), 5);
+        assert_eq!(decoded.get_fluid_level(Coord3::new(4, 1, 3)), Chunk::SOURCE_FLUID_LEVEL);
+        assert_eq!(decoded.encode(), bytes);
+
+        assert!(Chunk::decode(Coord3::ZERO, &bytes[..bytes.len()-1]).is_err());
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(Chunk::decode(Coord3::ZERO, &extra).is_err());
+    }
+
+    #[test]
+    fn empty_chunk_connects_everything(){
+        assert_eq!(Chunk::default().calculate_visibility(), ChunkVisibility::ALL);
+    }
+}
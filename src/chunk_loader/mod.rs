@@ -1,15 +1,18 @@
 use crate::{c3d3, math::*};
 use dashmap::DashSet;
 use std::{
-    sync::mpsc::{self, Receiver},
+    sync::{mpsc::{self, Receiver}, Arc},
     thread::{self, JoinHandle}
 }; 
 
 pub struct ChunkLoader{
     request_world_position: Coord3,
     world_position: Coord3,
-    loaded_set: DashSet<Coord3>,
+    // wspoldzielony z watkiem liczacym zmiany
+    loaded_set: Arc<DashSet<Coord3>>,
     load_distance: i32,
+    // odleglosc z ostatniego przeliczenia, jej zmiana tez wymaga nowego
+    committed_distance: i32,
     new_channel: (mpsc::Sender<Coord3>, mpsc::Receiver<Coord3>),
     old_channel: (mpsc::Sender<Coord3>, mpsc::Receiver<Coord3>)
 }
@@ -19,8 +22,9 @@ impl Default for ChunkLoader {
         ChunkLoader{
             request_world_position: Coord3::default(),
             world_position: Coord3::default(),
-            loaded_set: DashSet::new(),
+            loaded_set: Arc::new(DashSet::new()),
             load_distance: 10,
+            committed_distance: 10,
             new_channel: mpsc::channel(),
             old_channel: mpsc::channel()
         }
@@ -38,30 +42,34 @@ impl ChunkLoader{
         );
     }
 
+    pub fn get_load_distance(&self) -> i32{
+        self.load_distance
+    }
+
     pub fn set_world_positon(&mut self, new_position: Coord3){
         self.request_world_position = new_position;
     }
 
+    // chunki poza kula ida do get_coords_to_delete, nowe w kuli do get_coords_to_load
     pub fn commit_world_positon(&mut self) -> Vec<JoinHandle<()>>{
         let mut joins: Vec<JoinHandle<()>> = Vec::new(); 
-        if self.request_world_position != self.world_position || self.loaded_set.is_empty(){
+        if self.request_world_position != self.world_position || self.load_distance != self.committed_distance || self.loaded_set.is_empty(){
             self.world_position = self.request_world_position;
+            self.committed_distance = self.load_distance;
             
             let ld = self.load_distance;
             let center = self.world_position;
             let new_sender = self.new_channel.0.clone();
             let old_sender = self.old_channel.0.clone();
-            let loaded_set_clone = self.loaded_set.clone();
+            let loaded_set = Arc::clone(&self.loaded_set);
             joins.push(thread::spawn(move ||{
-                let mut to_remove: Vec<Coord3> = Vec::new();
-                for position in loaded_set_clone.iter(){
-                    if position.distance2(center) < ld.pow(2){
-                        to_remove.push(*position);
-                        old_sender.send(*position).unwrap();
-                    }
-                }
-                while !to_remove.is_empty(){
-                    loaded_set_clone.remove(to_remove.last().unwrap());
+                let to_remove: Vec<Coord3> = loaded_set.iter()
+                    .map(|position| *position)
+                    .filter(|position| position.distance2(center) > ld.pow(2))
+                    .collect();
+                for position in to_remove{
+                    loaded_set.remove(&position);
+                    old_sender.send(position).unwrap();
                 }
                 for xi in -ld..ld+1{
                     for yi in -ld..ld+1{
@@ -69,9 +77,8 @@ impl ChunkLoader{
                             let offset = c3d3!(xi, yi, zi);
                             if offset.magnitude2() <= ld.pow(2){
                                 let position = center+offset;
-                                if !loaded_set_clone.contains(&position){
+                                if loaded_set.insert(position){
                                     new_sender.send(position).unwrap();
-                                    loaded_set_clone.insert(position);
                                 }
                             }
                         }
@@ -83,7 +90,7 @@ impl ChunkLoader{
     }
     
     pub fn should_be_loaded(&self, value: Coord3) -> bool{
-        self.world_position.distance2(value) < self.load_distance.pow(2)
+        self.world_position.distance2(value) <= self.load_distance.pow(2)
     }
 
     pub fn get_coords_to_load(&mut self) -> &mut Receiver<Coord3>{
@@ -93,4 +100,39 @@ impl ChunkLoader{
     pub fn get_coords_to_delete(&mut self) -> &mut Receiver<Coord3>{
         &mut self.old_channel.1
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(loader: &mut ChunkLoader){
+        for join in loader.commit_world_positon(){
+            join.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn moving_and_changing_distance_loads_and_unloads_the_difference(){
+        let mut loader = ChunkLoader::default();
+        loader.set_load_distance(1);
+        commit(&mut loader);
+        assert_eq!(loader.get_coords_to_load().try_iter().count(), 7);
+        // bez zmian nic nie jest przeliczane
+        commit(&mut loader);
+        assert_eq!(loader.get_coords_to_load().try_iter().count(), 0);
+
+        loader.set_world_positon(c3d3!(1, 0, 0));
+        commit(&mut loader);
+        let mut unloaded: Vec<Coord3> = loader.get_coords_to_delete().try_iter().collect();
+        unloaded.sort_by_key(|position| (position.x, position.y, position.z));
+        assert_eq!(unloaded, vec![c3d3!(-1, 0, 0), c3d3!(0, -1, 0), c3d3!(0, 0, -1), c3d3!(0, 0, 1), c3d3!(0, 1, 0)]);
+        assert_eq!(loader.get_coords_to_load().try_iter().count(), 5);
+
+        loader.set_load_distance(2);
+        commit(&mut loader);
+        assert_eq!(loader.get_coords_to_load().try_iter().count(), 33-7);
+        assert!(loader.should_be_loaded(c3d3!(3, 0, 0)));
+        assert!(!loader.should_be_loaded(c3d3!(4, 0, 0)));
+    }
+}
@@ -1,10 +1,107 @@
-use dashmap::DashMap;
-use crate::{block::BlockType, chunk::Chunk, math::Coord3};
+use std::ops::Deref;
 
+use dashmap::{mapref::one::Ref, DashMap};
+use crate::{block::BlockType, chunk::Chunk, math::{raycast_voxels, Coord3, RayHit, Vec3}};
+
+// swiat z chunkow; podsystemy zaleza od tego traitu, a nie od DashMap
 pub trait ChunkMaster {
+    // pozyczony chunk, blokuje zmiany tego chunka dopoki zyje
+    type ChunkRef<'a>: Deref<Target = Chunk> where Self: 'a;
+
+    fn get_chunk(&self, chunk_position: Coord3) -> Option<Self::ChunkRef<'_>>;
+    fn chunk_exists(&self, chunk_position: Coord3) -> bool{
+        self.get_chunk(chunk_position).is_some()
+    }
+    // chunk o tej samej pozycji jest zastepowany
+    fn insert_chunk(&self, chunk: Chunk);
+    // zwraca usuniety chunk, np. do zapisu
+    fn remove_chunk(&self, chunk_position: Coord3) -> Option<Chunk>;
+    // bez okreslonej kolejnosci
+    fn get_chunk_positions(&self) -> Vec<Coord3>;
     fn get_voxel(&self, world_position: Coord3) -> BlockType;
+    // false gdy chunk nie jest zaladowany
+    fn set_voxel(&self, world_position: Coord3, block: BlockType) -> bool;
+    // poziom wody w wokselu, 0 gdy to nie woda
+    fn get_fluid_level(&self, world_position: Coord3) -> u8;
+    fn set_fluid(&self, world_position: Coord3, level: u8) -> bool;
+    // woda i powietrze nie zatrzymuja promienia
+    fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RayHit>{
+        raycast_voxels(origin, direction, max_distance, |position|{
+            let block = self.get_voxel(position);
+            block != BlockType::Air && block != BlockType::Water
+        })
+    }
+    fn get_neighbourhood(&self, chunk_position: Coord3) -> Neighbourhood where Self: Sized{
+        Neighbourhood::new(self, chunk_position)
+    }
+}
+
+// woksele sasiednich chunkow stykajace sie z chunkiem (takze po skosie, dla AO), kazdy z 26 sasiadow
+// jest czytany raz zamiast szukania w mapie dla kazdego woksela; wnetrza chunka tu nie ma
+pub struct Neighbourhood{
+    blocks: Vec<BlockType>
+}
+impl Neighbourhood {
+    const PADDED_SIZE: usize = Chunk::CHUNK_SIZE+2;
+
+    pub fn new(master: &impl ChunkMaster, chunk_position: Coord3) -> Self{
+        let size = Chunk::CHUNK_SIZE as i32;
+        let mut neighbourhood = Neighbourhood{blocks: vec![BlockType::Air; Neighbourhood::PADDED_SIZE.pow(3)]};
+        // z sasiada po ujemnej stronie potrzebna jest tylko ostatnia warstwa, z dodatniej pierwsza
+        let range = |offset: i32| match offset {
+            -1 => size-1..size,
+            0 => 0..size,
+            _ => 0..1
+        };
+        for offset in (-1..=1).flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| Coord3::new(x, y, z)))){
+            if offset == Coord3::ZERO{
+                continue;
+            }
+            let Some(chunk) = master.get_chunk(chunk_position+offset) else {
+                continue;
+            };
+            for x in range(offset.x){
+                for y in range(offset.y){
+                    for z in range(offset.z){
+                        let local = Coord3::new(x, y, z);
+                        neighbourhood.blocks[Neighbourhood::get_index(local+offset*size)] = chunk.get_voxel(local);
+                    }
+                }
+            }
+        }
+        neighbourhood
+    }
+    #[inline(always)]
+    fn get_index(local_position: Coord3) -> usize{
+        let size = Neighbourhood::PADDED_SIZE;
+        ((local_position.x+1) as usize*size+(local_position.y+1) as usize)*size+(local_position.z+1) as usize
+    }
+    // pozycja wzgledem chunka, od -1 do CHUNK_SIZE; brakujacy sasiad to powietrze
+    #[inline(always)]
+    pub fn get_voxel(&self, local_position: Coord3) -> BlockType{
+        debug_assert!(Chunk::is_outer(local_position));
+        self.blocks[Neighbourhood::get_index(local_position)]
+    }
 }
+
 impl ChunkMaster for DashMap<Coord3, Chunk> {
+    type ChunkRef<'a> = Ref<'a, Coord3, Chunk>;
+
+    fn get_chunk(&self, chunk_position: Coord3) -> Option<Self::ChunkRef<'_>>{
+        self.get(&chunk_position)
+    }
+    fn chunk_exists(&self, chunk_position: Coord3) -> bool{
+        self.contains_key(&chunk_position)
+    }
+    fn insert_chunk(&self, chunk: Chunk){
+        self.insert(chunk.get_chunk_position(), chunk);
+    }
+    fn remove_chunk(&self, chunk_position: Coord3) -> Option<Chunk>{
+        self.remove(&chunk_position).map(|(_, chunk)| chunk)
+    }
+    fn get_chunk_positions(&self) -> Vec<Coord3>{
+        self.iter().map(|chunk| *chunk.key()).collect()
+    }
     #[inline]
     fn get_voxel(&self, world_position: Coord3) -> BlockType{
         let chunk_position = world_position.div_euclid(Chunk::CHUNK_SIZE as i32);
@@ -14,4 +111,226 @@ impl ChunkMaster for DashMap<Coord3, Chunk> {
         }
         BlockType::Air
     }
+    fn set_voxel(&self, world_position: Coord3, block: BlockType) -> bool{
+        let chunk_position = world_position.div_euclid(Chunk::CHUNK_SIZE as i32);
+        let local_position = world_position.mod_euclid(Chunk::CHUNK_SIZE as i32);
+        if let Some(mut chunkerz) = self.get_mut(&chunk_position){
+            chunkerz.set_voxel(local_position, block);
+            return true;
+        }
+        false
+    }
+    fn get_fluid_level(&self, world_position: Coord3) -> u8{
+        let chunk_position = world_position.div_euclid(Chunk::CHUNK_SIZE as i32);
+        let local_position = world_position.mod_euclid(Chunk::CHUNK_SIZE as i32);
+        if let Some(chunkerz) = self.get(&chunk_position){
+            return chunkerz.get_fluid_level(local_position);
+        }
+        0
+    }
+    fn set_fluid(&self, world_position: Coord3, level: u8) -> bool{
+        let chunk_position = world_position.div_euclid(Chunk::CHUNK_SIZE as i32);
+        let local_position = world_position.mod_euclid(Chunk::CHUNK_SIZE as i32);
+        if let Some(mut chunkerz) = self.get_mut(&chunk_position){
+            chunkerz.set_fluid(local_position, level);
+            return true;
+        }
+        false
+    }
+}
+
+// chunki, ktorych siatka zalezy od woksela: jego chunk i sasiedzi (takze po skosie, przez AO),
+// o ktorych sciany woksel sie opiera
+pub fn get_affected_chunks(world_position: Coord3) -> Vec<Coord3>{
+    let size = Chunk::CHUNK_SIZE as i32;
+    let chunk_position = world_position.div_euclid(size);
+    let local = world_position.mod_euclid(size);
+    let offsets = |value: i32| -> Vec<i32>{
+        match value {
+            0 => vec![0, -1],
+            v if v == size-1 => vec![0, 1],
+            _ => vec![0]
+        }
+    };
+    let mut chunks = Vec::new();
+    for x in offsets(local.x){
+        for y in offsets(local.y){
+            for z in offsets(local.z){
+                chunks.push(chunk_position+Coord3::new(x, y, z));
+            }
+        }
+    }
+    chunks
+}
+
+// swiat w zwyklej HashMap, jednowatkowy: narzedzia, serwer testowy i testy podsystemow; pozyczony chunk blokuje zmiany calego swiata
+#[derive(Default)]
+pub struct MemoryWorld{
+    chunks: std::cell::RefCell<std::collections::HashMap<Coord3, Chunk>>
+}
+impl MemoryWorld {
+    // puste chunki od min do max wlacznie, w pozycjach chunkow
+    pub fn with_chunks(min: Coord3, max: Coord3) -> Self{
+        let world = MemoryWorld::default();
+        for x in min.x..=max.x{
+            for y in min.y..=max.y{
+                for z in min.z..=max.z{
+                    world.create_chunk(Coord3::new(x, y, z));
+                }
+            }
+        }
+        world
+    }
+    // swiat z podanymi blokami, brakujace chunki sa tworzone puste
+    pub fn with_blocks(blocks: impl IntoIterator<Item = (Coord3, BlockType)>) -> Self{
+        let world = MemoryWorld::default();
+        for (position, block) in blocks{
+            world.place(position, block);
+        }
+        world
+    }
+    // wypelnia prostopadloscian od min do max wlacznie, tez poza istniejacymi chunkami
+    pub fn fill(&self, min: Coord3, max: Coord3, block: BlockType){
+        for x in min.x..=max.x{
+            for y in min.y..=max.y{
+                for z in min.z..=max.z{
+                    self.place(Coord3::new(x, y, z), block);
+                }
+            }
+        }
+    }
+    pub fn place(&self, world_position: Coord3, block: BlockType){
+        self.create_chunk(world_position.div_euclid(Chunk::CHUNK_SIZE as i32));
+        self.set_voxel(world_position, block);
+    }
+    fn create_chunk(&self, chunk_position: Coord3){
+        self.chunks.borrow_mut().entry(chunk_position).or_insert_with(||{
+            let mut chunk = Chunk::default();
+            chunk.set_chunk_position(chunk_position);
+            chunk
+        });
+    }
+    fn update<R>(&self, world_position: Coord3, update: impl FnOnce(&mut Chunk, Coord3) -> R) -> Option<R>{
+        let chunk_position = world_position.div_euclid(Chunk::CHUNK_SIZE as i32);
+        let local_position = world_position.mod_euclid(Chunk::CHUNK_SIZE as i32);
+        self.chunks.borrow_mut().get_mut(&chunk_position).map(|chunk| update(chunk, local_position))
+    }
+}
+impl ChunkMaster for MemoryWorld {
+    type ChunkRef<'a> = std::cell::Ref<'a, Chunk>;
+
+    fn get_chunk(&self, chunk_position: Coord3) -> Option<Self::ChunkRef<'_>>{
+        std::cell::Ref::filter_map(self.chunks.borrow(), |chunks| chunks.get(&chunk_position)).ok()
+    }
+    fn insert_chunk(&self, chunk: Chunk){
+        self.chunks.borrow_mut().insert(chunk.get_chunk_position(), chunk);
+    }
+    fn remove_chunk(&self, chunk_position: Coord3) -> Option<Chunk>{
+        self.chunks.borrow_mut().remove(&chunk_position)
+    }
+    fn get_chunk_positions(&self) -> Vec<Coord3>{
+        self.chunks.borrow().keys().copied().collect()
+    }
+    fn get_voxel(&self, world_position: Coord3) -> BlockType{
+        let local_position = world_position.mod_euclid(Chunk::CHUNK_SIZE as i32);
+        self.get_chunk(world_position.div_euclid(Chunk::CHUNK_SIZE as i32))
+            .map_or(BlockType::Air, |chunk| chunk.get_voxel(local_position))
+    }
+    fn set_voxel(&self, world_position: Coord3, block: BlockType) -> bool{
+        self.update(world_position, |chunk, local_position| chunk.set_voxel(local_position, block)).is_some()
+    }
+    fn get_fluid_level(&self, world_position: Coord3) -> u8{
+        let local_position = world_position.mod_euclid(Chunk::CHUNK_SIZE as i32);
+        self.get_chunk(world_position.div_euclid(Chunk::CHUNK_SIZE as i32))
+            .map_or(0, |chunk| chunk.get_fluid_level(local_position))
+    }
+    fn set_fluid(&self, world_position: Coord3, level: u8) -> bool{
+        self.update(world_position, |chunk, local_position| chunk.set_fluid(local_position, level)).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_chunk(chunk_position: Coord3) -> Chunk{
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(chunk_position);
+        chunk
+    }
+
+    // oba swiaty musza sie zachowywac tak samo
+    fn check_world(world: &impl ChunkMaster){
+        let position = Coord3::new(-1, 60, 3);
+        assert!(!world.set_voxel(position, BlockType::Stone));
+        assert_eq!(world.get_voxel(position), BlockType::Air);
+        world.insert_chunk(empty_chunk(Coord3::new(-1, 1, 0)));
+        assert!(world.chunk_exists(Coord3::new(-1, 1, 0)));
+        assert!(!world.chunk_exists(Coord3::ZERO));
+        assert!(world.set_voxel(position, BlockType::Stone));
+        assert!(world.set_fluid(position+Coord3::new(0, 1, 0), 3));
+        assert_eq!(world.get_voxel(position), BlockType::Stone);
+        assert_eq!(world.get_fluid_level(position+Coord3::new(0, 1, 0)), 3);
+        assert_eq!(world.get_chunk(Coord3::new(-1, 1, 0)).unwrap().get_voxel(Coord3::new(49, 10, 3)), BlockType::Stone);
+        world.insert_chunk(empty_chunk(Coord3::new(-1, 1, 0)));
+        assert_eq!(world.get_voxel(position), BlockType::Air);
+        assert_eq!(world.get_chunk_positions(), vec![Coord3::new(-1, 1, 0)]);
+        assert_eq!(world.remove_chunk(Coord3::new(-1, 1, 0)).map(|chunk| chunk.get_chunk_position()), Some(Coord3::new(-1, 1, 0)));
+        assert!(world.remove_chunk(Coord3::new(-1, 1, 0)).is_none());
+        assert!(world.get_chunk_positions().is_empty());
+    }
+
+    #[test]
+    fn dashmap_and_memory_worlds_agree(){
+        check_world(&DashMap::<Coord3, Chunk>::new());
+        check_world(&MemoryWorld::default());
+    }
+
+    #[test]
+    fn memory_world_fixtures_create_missing_chunks(){
+        let world = MemoryWorld::with_chunks(Coord3::new(-1, 0, 0), Coord3::ZERO);
+        assert_eq!(world.get_chunk_positions().len(), 2);
+        world.fill(Coord3::new(-2, 0, 0), Coord3::new(50, 0, 0), BlockType::Stone);
+        assert_eq!(world.get_chunk_positions().len(), 3);
+        assert_eq!(world.get_voxel(Coord3::new(50, 0, 0)), BlockType::Stone);
+        assert_eq!(world.get_chunk(Coord3::new(1, 0, 0)).unwrap().get_chunk_position(), Coord3::new(1, 0, 0));
+        let world = MemoryWorld::with_blocks([(Coord3::new(0, -1, 0), BlockType::Sand)]);
+        assert_eq!(world.get_chunk_positions(), vec![Coord3::new(0, -1, 0)]);
+        assert_eq!(world.get_voxel(Coord3::new(0, -1, 0)), BlockType::Sand);
+    }
+
+    #[test]
+    fn neighbourhood_matches_world_lookups(){
+        let world = MemoryWorld::default();
+        let size = Chunk::CHUNK_SIZE as i32;
+        for x in -1..=1{
+            for y in -1..=1{
+                for z in -1..=1{
+                    if (x, y, z) != (1, 1, 1){
+                        world.insert_chunk(empty_chunk(Coord3::new(x, y, z)));
+                    }
+                }
+            }
+        }
+        // bloki na scianie, krawedzi i w rogu sasiadow oraz jeden dalej od chunka
+        for position in [Coord3::new(-1, 10, 20), Coord3::new(size, size, 7), Coord3::new(-1, -1, -1), Coord3::new(5, size, -1), Coord3::new(-2, 10, 20)]{
+            world.set_voxel(position, BlockType::Sand);
+        }
+        let neighbourhood = world.get_neighbourhood(Coord3::ZERO);
+        let mut sand = 0;
+        for x in -1..=size{
+            for y in -1..=size{
+                for z in -1..=size{
+                    let position = Coord3::new(x, y, z);
+                    if Chunk::is_outer(position){
+                        assert_eq!(neighbourhood.get_voxel(position), world.get_voxel(position));
+                        sand += (neighbourhood.get_voxel(position) == BlockType::Sand) as usize;
+                    }
+                }
+            }
+        }
+        assert_eq!(sand, 4);
+        // brakujacy chunk (1, 1, 1) jest czytany jako powietrze
+        assert_eq!(neighbourhood.get_voxel(Coord3::new(size, size, size)), BlockType::Air);
+    }
 }
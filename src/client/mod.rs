@@ -0,0 +1,131 @@
+use std::{collections::HashMap, io, net::TcpStream};
+
+use crate::{block::BlockType, chunk::Chunk, chunk_master::ChunkMaster, math::{Coord3, Vec3}, net::Connection, prediction::BlockPrediction, protocol::{Message, PROTOCOL_VERSION}};
+
+// co zmienilo sie w swiecie klienta podczas jednego poll
+#[derive(Default)]
+pub struct ClientUpdate{
+    pub chunks: Vec<Coord3>,
+    pub blocks: Vec<Coord3>,
+    pub entities: Vec<RemoteEntity>,
+    // ruchy odrzucone przez serwer: numer ruchu i pozycja, do ktorej gracz ma wrocic
+    pub corrections: Vec<(u32, Vec3)>,
+    // id autora i tekst
+    pub chat: Vec<(u32, String)>
+}
+
+// obiekt, ktory serwer kazal stworzyc
+pub struct RemoteEntity{
+    pub id: u32,
+    pub position: Vec3,
+    pub half_extents: Vec3
+}
+
+// polaczenie z serwerem, swiat klienta jest tylko kopia tego, co przyslal serwer
+pub struct Client{
+    connection: Connection,
+    player_id: u32,
+    spawn: Vec3,
+    players: HashMap<u32, Vec3>,
+    last_position: Option<Vec3>,
+    blocks: BlockPrediction
+}
+impl Client {
+    // ruchy mniejsze niz tyle nie sa wysylane
+    const MIN_MOVE: f32 = 0.01;
+
+    pub fn connect(address: &str) -> io::Result<Client>{
+        let mut connection = Connection::new(TcpStream::connect(address)?)?;
+        connection.send(&Message::handshake(0, Vec3::ZERO))?;
+        match connection.receive_blocking()? {
+            Message::Handshake{version, ..} if version != PROTOCOL_VERSION => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("server uses protocol version {version}, client uses {PROTOCOL_VERSION}")
+            )),
+            Message::Handshake{player_id, position, ..} => Ok(Client{
+                connection,
+                player_id,
+                spawn: position,
+                players: HashMap::new(),
+                last_position: None,
+                blocks: BlockPrediction::default()
+            }),
+            Message::Disconnect{reason, ..} => Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason)),
+            message => Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected handshake, got {message:?}")))
+        }
+    }
+    pub fn get_player_id(&self) -> u32{
+        self.player_id
+    }
+    pub fn get_spawn(&self) -> Vec3{
+        self.spawn
+    }
+    // pozycje pozostalych graczy
+    pub fn get_players(&self) -> &HashMap<u32, Vec3>{
+        &self.players
+    }
+    pub fn is_connected(&self) -> bool{
+        !self.connection.is_closed()
+    }
+    pub fn get_pending_edits(&self) -> usize{
+        self.blocks.get_pending_count()
+    }
+    // sequence to numer kroku symulacji, po ktorym gracz byl w tej pozycji
+    pub fn send_position(&mut self, sequence: u32, position: Vec3){
+        if self.last_position.is_some_and(|last| (last-position).magnitude() < Client::MIN_MOVE){
+            return;
+        }
+        self.last_position = Some(position);
+        let _ = self.connection.send(&Message::PlayerMove{player_id: self.player_id, sequence, position});
+    }
+    pub fn send_chat(&mut self, text: &str){
+        let _ = self.connection.send(&Message::Chat{player_id: self.player_id, text: text.to_string()});
+    }
+    // blok zmienia sie od razu, serwer potwierdzi zmiane albo ja cofnie; true, gdy swiat sie zmienil
+    pub fn request_block(&mut self, world: &impl ChunkMaster, position: Coord3, block: BlockType) -> bool{
+        let Some(sequence) = self.blocks.predict(world, position, block) else {
+            return false;
+        };
+        let _ = self.connection.send(&Message::BlockChange{sequence, position, block});
+        true
+    }
+    pub fn poll(&mut self, world: &impl ChunkMaster) -> ClientUpdate{
+        let mut update = ClientUpdate::default();
+        for message in self.connection.receive(){
+            match message {
+                Message::ChunkData{position, data} => match Chunk::decode(position, &data) {
+                    Ok(chunk) => {
+                        world.insert_chunk(chunk);
+                        update.chunks.push(position);
+                    }
+                    Err(error) => println!("bad chunk {position}: {error}")
+                },
+                Message::BlockChange{sequence, position, block} if self.blocks.apply(world, sequence, position, block) => {
+                    update.blocks.push(position);
+                }
+                Message::PlayerMove{player_id, sequence, position} if player_id == self.player_id => {
+                    update.corrections.push((sequence, position));
+                }
+                Message::PlayerMove{player_id, position, ..} => {
+                    self.players.insert(player_id, position);
+                }
+                Message::EntitySpawn{entity_id, position, half_extents} => {
+                    update.entities.push(RemoteEntity{id: entity_id, position, half_extents});
+                }
+                Message::Disconnect{player_id, ..} => {
+                    self.players.remove(&player_id);
+                }
+                Message::Chat{player_id, text} => {
+                    update.chat.push((player_id, text));
+                }
+                _ => {}
+            }
+        }
+        update
+    }
+}
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.connection.send(&Message::Disconnect{player_id: self.player_id, reason: "quit".to_string()});
+    }
+}
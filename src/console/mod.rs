@@ -0,0 +1,469 @@
+use std::{collections::{BTreeMap, VecDeque}, path::Path};
+
+use dashmap::DashMap;
+
+use crate::{block::BlockType, chunk::Chunk, chunk_master::ChunkMaster, game_loop::GameState, export::Region, math::{Coord3, Vec3}, structure::{Mirror, Rotation, Structure}, world_edit::{self, Shape}};
+
+// argumenty bez nazwy komendy, wynik trafia do konsoli
+pub type CommandHandler<W> = fn(&mut GameState<W>, &[&str]) -> Result<String, String>;
+
+struct Command<W: ChunkMaster>{
+    usage: &'static str,
+    handler: CommandHandler<W>
+}
+
+// komendy po nazwie, inne moduly moga dopisywac wlasne przez register
+pub struct CommandRegistry<W: ChunkMaster = DashMap<Coord3, Chunk>>{
+    commands: BTreeMap<String, Command<W>>
+}
+impl<W: ChunkMaster> Default for CommandRegistry<W> {
+    fn default() -> Self {
+        let mut registry = CommandRegistry::new();
+        registry.register("tp", "<x> <y> <z>", teleport);
+        registry.register("seed", "", seed);
+        registry.register("setblock", "<x> <y> <z> <block>", set_block);
+        registry.register("fill", "<x1> <y1> <z1> <x2> <y2> <z2> <block>", fill);
+        registry.register("replace", "<x1> <y1> <z1> <x2> <y2> <z2> <from> <to>", replace);
+        registry.register("sphere", "<x> <y> <z> <radius> <block>", sphere);
+        registry.register("cylinder", "<x> <y> <z> <radius> <height> <block>", cylinder);
+        registry.register("copy", "<x1> <y1> <z1> <x2> <y2> <z2>", copy);
+        registry.register("load", "<file>", load);
+        registry.register("rotate", "<90|180|270>", rotate);
+        registry.register("flip", "<x|y|z>", flip);
+        registry.register("paste", "[<x> <y> <z>]", paste);
+        registry.register("undo", "", undo);
+        registry.register("redo", "", redo);
+        registry.register("time", "[day|noon|night|midnight|0..1]", time);
+        registry.register("renderdistance", "<chunks>", render_distance);
+        registry.register("fly", "", fly);
+        registry
+    }
+}
+impl<W: ChunkMaster> CommandRegistry<W> {
+    pub fn new() -> Self{
+        CommandRegistry{commands: BTreeMap::new()}
+    }
+    // komenda o tej samej nazwie jest zastepowana
+    pub fn register(&mut self, name: &str, usage: &'static str, handler: CommandHandler<W>){
+        self.commands.insert(name.to_string(), Command{usage, handler});
+    }
+    pub fn get_names(&self) -> Vec<&str>{
+        self.commands.keys().map(|name| name.as_str()).collect()
+    }
+    // linia z '/' albo bez
+    pub fn execute(&self, game: &mut GameState<W>, line: &str) -> Result<String, String>{
+        let mut words = line.trim().trim_start_matches('/').split_whitespace();
+        let name = words.next().ok_or("empty command")?;
+        let args: Vec<&str> = words.collect();
+        if name == "help"{
+            let usages: Vec<String> = self.commands.iter()
+                .map(|(name, command)| format!("/{name} {}", command.usage).trim_end().to_string())
+                .collect();
+            return Ok(usages.join(", "));
+        }
+        let command = self.commands.get(name).ok_or(format!("unknown command /{name}, try /help"))?;
+        (command.handler)(game, &args).map_err(|error| format!("{error}, usage: /{name} {}", command.usage).trim_end().to_string())
+    }
+}
+
+fn expect_args(args: &[&str], count: usize) -> Result<(), String>{
+    if args.len() != count{
+        return Err(format!("expected {count} arguments, got {}", args.len()));
+    }
+    Ok(())
+}
+// liczba albo ~ i przesuniecie wzgledem gracza
+fn parse_coordinate(arg: &str, relative_to: f32) -> Result<f32, String>{
+    let (base, number) = match arg.strip_prefix('~') {
+        Some("") => return Ok(relative_to),
+        Some(offset) => (relative_to, offset),
+        None => (0.0, arg)
+    };
+    number.parse::<f32>().map(|value| base+value).map_err(|_| format!("'{arg}' is not a number"))
+}
+fn parse_position<W: ChunkMaster>(args: &[&str], game: &GameState<W>) -> Result<Vec3, String>{
+    let player = game.player.get_position();
+    Ok(Vec3::new(
+        parse_coordinate(args[0], player.x)?,
+        parse_coordinate(args[1], player.y)?,
+        parse_coordinate(args[2], player.z)?
+    ))
+}
+fn parse_block_position<W: ChunkMaster>(args: &[&str], game: &GameState<W>) -> Result<Coord3, String>{
+    let player = game.player.get_position().to_voxel_coord();
+    let coordinate = |arg: &str, relative_to: i32| parse_coordinate(arg, relative_to as f32).map(|value| value.round() as i32);
+    Ok(Coord3::new(coordinate(args[0], player.x)?, coordinate(args[1], player.y)?, coordinate(args[2], player.z)?))
+}
+fn parse_block(name: &str) -> Result<BlockType, String>{
+    BlockType::parse(name).ok_or(format!("unknown block '{name}'"))
+}
+// z serwerem swiat zmienia tylko serwer
+fn expect_local_world<W: ChunkMaster>(game: &GameState<W>) -> Result<(), String>{
+    if game.prediction.is_some(){
+        return Err("the world is controlled by the server".to_string());
+    }
+    Ok(())
+}
+
+fn teleport<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 3)?;
+    let position = parse_position(args, game)?;
+    game.player.set_position(position);
+    Ok(format!("teleported to {position}"))
+}
+fn seed<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 0)?;
+    game.seed.map(|seed| format!("seed: {seed}")).ok_or("the seed is known only to the server".to_string())
+}
+fn set_block<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 4)?;
+    expect_local_world(game)?;
+    let position = parse_block_position(args, game)?;
+    let block = parse_block(args[3])?;
+    if !game.set_block(position, block){
+        return Err(format!("chunk with {position} is not loaded"));
+    }
+    Ok(format!("{position} set to {block:?}"))
+}
+// wiekszych obszarow nie da sie zmienic jedna komenda
+const MAX_FILL: i64 = 32768;
+fn parse_region<W: ChunkMaster>(args: &[&str], game: &GameState<W>) -> Result<Region, String>{
+    let region = Region::new(parse_block_position(&args[0..3], game)?, parse_block_position(&args[3..6], game)?);
+    expect_volume(Shape::Cuboid(region).get_volume())?;
+    Ok(region)
+}
+fn parse_number(arg: &str) -> Result<i32, String>{
+    arg.parse().map_err(|_| format!("'{arg}' is not a number"))
+}
+fn expect_volume(volume: i64) -> Result<(), String>{
+    if volume > MAX_FILL{
+        return Err(format!("{volume} blocks is more than {MAX_FILL}"));
+    }
+    Ok(())
+}
+fn fill<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 7)?;
+    expect_local_world(game)?;
+    let shape = Shape::Cuboid(parse_region(args, game)?);
+    let block = parse_block(args[6])?;
+    let edit = world_edit::fill(&game.world, &shape, block);
+    Ok(format!("filled {} blocks", game.record_edit(edit)))
+}
+fn replace<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 8)?;
+    expect_local_world(game)?;
+    let shape = Shape::Cuboid(parse_region(args, game)?);
+    let (from, to) = (parse_block(args[6])?, parse_block(args[7])?);
+    let edit = world_edit::replace(&game.world, &shape, from, to);
+    Ok(format!("replaced {} blocks", game.record_edit(edit)))
+}
+fn sphere<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 5)?;
+    expect_local_world(game)?;
+    let shape = Shape::Sphere{center: parse_block_position(args, game)?, radius: parse_number(args[3])?.max(0)};
+    expect_volume(shape.get_volume())?;
+    let block = parse_block(args[4])?;
+    let edit = world_edit::fill(&game.world, &shape, block);
+    Ok(format!("filled {} blocks", game.record_edit(edit)))
+}
+fn cylinder<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 6)?;
+    expect_local_world(game)?;
+    let shape = Shape::Cylinder{center: parse_block_position(args, game)?, radius: parse_number(args[3])?.max(0), height: parse_number(args[4])?.max(1)};
+    expect_volume(shape.get_volume())?;
+    let block = parse_block(args[5])?;
+    let edit = world_edit::fill(&game.world, &shape, block);
+    Ok(format!("filled {} blocks", game.record_edit(edit)))
+}
+fn copy<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 6)?;
+    let region = parse_region(args, game)?;
+    let clipboard = world_edit::copy(&game.world, &region);
+    let size = clipboard.get_size();
+    game.clipboard = Some(clipboard);
+    Ok(format!("copied {}x{}x{} blocks", size.x, size.y, size.z))
+}
+// struktura z pliku .vox albo schematu trafia do schowka
+fn load<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 1)?;
+    let structure = Structure::load(Path::new(args[0]))?;
+    let count = structure.get_blocks().len();
+    game.clipboard = Some(structure);
+    Ok(format!("loaded {count} blocks from {}", args[0]))
+}
+fn get_clipboard<W: ChunkMaster>(game: &GameState<W>) -> Result<&Structure, String>{
+    game.clipboard.as_ref().ok_or("the clipboard is empty, use /copy or /load".to_string())
+}
+fn rotate<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 1)?;
+    let rotation = Rotation::parse(args[0]).ok_or(format!("'{}' is not a rotation", args[0]))?;
+    game.clipboard = Some(get_clipboard(game)?.rotated(rotation));
+    Ok(format!("clipboard rotated by {rotation:?}"))
+}
+fn flip<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 1)?;
+    let mirror = Mirror::parse(args[0]).ok_or(format!("'{}' is not an axis", args[0]))?;
+    game.clipboard = Some(get_clipboard(game)?.mirrored(mirror));
+    Ok(format!("clipboard flipped along {mirror:?}"))
+}
+// domyslnie pod nogami gracza
+fn paste<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_local_world(game)?;
+    let origin = match args.len() {
+        0 => parse_block_position(&["~", "~", "~"], game)?,
+        _ => {
+            expect_args(args, 3)?;
+            parse_block_position(args, game)?
+        }
+    };
+    let clipboard = get_clipboard(game)?;
+    let size = clipboard.get_size();
+    expect_volume(size.x as i64*size.y as i64*size.z as i64)?;
+    let edit = world_edit::paste(&game.world, clipboard, origin);
+    Ok(format!("pasted at {origin}, {} blocks changed", game.record_edit(edit)))
+}
+fn undo<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 0)?;
+    expect_local_world(game)?;
+    let count = game.undo_edit().ok_or("nothing to undo")?;
+    Ok(format!("undone {count} blocks"))
+}
+fn redo<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 0)?;
+    expect_local_world(game)?;
+    let count = game.redo_edit().ok_or("nothing to redo")?;
+    Ok(format!("redone {count} blocks"))
+}
+fn time<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    let Some(value) = args.first() else {
+        return Ok(format!("time: {:.3}", game.time_of_day));
+    };
+    expect_args(args, 1)?;
+    let time = match *value {
+        "day" => 0.3,
+        "noon" => 0.5,
+        "night" => 0.8,
+        "midnight" => 0.0,
+        number => number.parse::<f32>().ok().filter(|time| (0.0..1.0).contains(time)).ok_or(format!("'{number}' is not a time"))?
+    };
+    game.time_of_day = time;
+    Ok(format!("time set to {time:.3}"))
+}
+fn render_distance<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 1)?;
+    let distance: i32 = args[0].parse().map_err(|_| format!("'{}' is not a number", args[0]))?;
+    // przy swiecie z serwera zmienia sie tylko rysowanie, chunki wysyla serwer
+    Ok(format!("render distance: {} chunks", game.set_render_distance(distance)))
+}
+fn fly<W: ChunkMaster>(game: &mut GameState<W>, args: &[&str]) -> Result<String, String>{
+    expect_args(args, 0)?;
+    game.player.toggle_flying();
+    Ok(format!("flying: {}", game.player.is_flying()))
+}
+
+// konsola w oknie i na stdin w trybie headless: linie z '/' to komendy, reszta to czat
+pub struct Console<W: ChunkMaster = DashMap<Coord3, Chunk>>{
+    registry: CommandRegistry<W>,
+    open: bool,
+    input: String,
+    lines: VecDeque<String>,
+    // linie czatu czekajace na wyslanie do serwera
+    chat: Vec<String>
+}
+impl<W: ChunkMaster> Default for Console<W> {
+    fn default() -> Self {
+        Console::new(CommandRegistry::default())
+    }
+}
+impl Console {
+    // tyle ostatnich linii jest pamietanych i rysowanych
+    pub const MAX_LINES: usize = 10;
+}
+impl<W: ChunkMaster> Console<W> {
+    const MAX_INPUT: usize = 120;
+
+    pub fn new(registry: CommandRegistry<W>) -> Self{
+        Console{registry, open: false, input: String::new(), lines: VecDeque::new(), chat: Vec::new()}
+    }
+    pub fn get_registry_mut(&mut self) -> &mut CommandRegistry<W>{
+        &mut self.registry
+    }
+    pub fn is_open(&self) -> bool{
+        self.open
+    }
+    pub fn open(&mut self){
+        self.open = true;
+    }
+    pub fn close(&mut self){
+        self.open = false;
+        self.input.clear();
+    }
+    pub fn get_input(&self) -> &str{
+        &self.input
+    }
+    pub fn get_lines(&self) -> &VecDeque<String>{
+        &self.lines
+    }
+    // np. czat od innych graczy
+    pub fn add_line(&mut self, line: String){
+        self.lines.push_back(line);
+        if self.lines.len() > Console::MAX_LINES{
+            self.lines.pop_front();
+        }
+    }
+    pub fn take_chat(&mut self) -> Vec<String>{
+        std::mem::take(&mut self.chat)
+    }
+    pub fn type_char(&mut self, c: char){
+        if !c.is_control() && self.input.chars().count() < Self::MAX_INPUT{
+            self.input.push(c);
+        }
+    }
+    pub fn backspace(&mut self){
+        self.input.pop();
+    }
+    // wykonuje wpisana linie i zamyka konsole
+    pub fn submit(&mut self, game: &mut GameState<W>) -> Option<String>{
+        let line = std::mem::take(&mut self.input);
+        self.close();
+        if line.trim().is_empty(){
+            return None;
+        }
+        Some(self.execute(game, &line))
+    }
+    // zwraca linie dopisana do konsoli
+    pub fn execute(&mut self, game: &mut GameState<W>, line: &str) -> String{
+        let output = if line.trim_start().starts_with('/') {
+            match self.registry.execute(game, line) {
+                Ok(output) => output,
+                Err(error) => format!("error: {error}")
+            }
+        } else {
+            // bez serwera nikt tego nie odbiera, wiec kolejka jest ograniczona
+            if self.chat.len() == Console::MAX_LINES{
+                self.chat.remove(0);
+            }
+            self.chat.push(line.trim().to_string());
+            format!("<player> {}", line.trim())
+        };
+        self.add_line(output.clone());
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk_loader::ChunkLoader, chunk_master::MemoryWorld, input::InputState, player::Player};
+
+    use super::*;
+
+    fn game() -> GameState<MemoryWorld>{
+        let world = MemoryWorld::with_chunks(Coord3::ZERO, Coord3::ZERO);
+        let mut game = GameState::new(world, Player::new(), InputState::default());
+        game.player.set_position(Vec3::new(5.0, 5.0, 5.0));
+        game.set_seed(2137);
+        game
+    }
+
+    #[test]
+    fn builtin_commands_change_the_game(){
+        let mut game = game();
+        let registry = CommandRegistry::default();
+        assert_eq!(registry.execute(&mut game, "/seed"), Ok("seed: 2137".to_string()));
+        registry.execute(&mut game, "/tp 1 ~2 -3.5").unwrap();
+        assert_eq!(game.player.get_position(), Vec3::new(1.0, 7.0, -3.5));
+        registry.execute(&mut game, "/setblock 1 2 3 sand").unwrap();
+        assert_eq!(game.world.get_voxel(Coord3::new(1, 2, 3)), BlockType::Sand);
+        assert_eq!(registry.execute(&mut game, "/fill 0 0 0 2 1 1 Stone"), Ok("filled 12 blocks".to_string()));
+        assert_eq!(game.world.get_voxel(Coord3::new(2, 1, 1)), BlockType::Stone);
+        registry.execute(&mut game, "/time noon").unwrap();
+        assert_eq!(game.time_of_day, 0.5);
+        assert!(game.get_sun_direction().y < -0.9);
+        registry.execute(&mut game, "/renderdistance 1000").unwrap();
+        assert_eq!(game.render_distance, ChunkLoader::MAX_LOAD_DISTANCE);
+        registry.execute(&mut game, "/renderdistance 3").unwrap();
+        assert_eq!(game.chunk_loader.get_load_distance(), 3);
+        let flying = game.player.is_flying();
+        registry.execute(&mut game, "fly").unwrap();
+        assert_ne!(game.player.is_flying(), flying);
+    }
+
+    #[test]
+    fn edit_commands_use_the_clipboard_and_history(){
+        let mut game = game();
+        let registry = CommandRegistry::default();
+        registry.execute(&mut game, "/fill 0 0 0 1 0 0 stone").unwrap();
+        assert_eq!(registry.execute(&mut game, "/replace 0 0 0 3 0 0 stone sand"), Ok("replaced 2 blocks".to_string()));
+        registry.execute(&mut game, "/setblock 1 0 0 dirt").unwrap();
+        registry.execute(&mut game, "/copy 0 0 0 1 0 0").unwrap();
+        registry.execute(&mut game, "/flip x").unwrap();
+        registry.execute(&mut game, "/paste 10 0 10").unwrap();
+        assert_eq!(game.world.get_voxel(Coord3::new(10, 0, 10)), BlockType::Dirt);
+        assert_eq!(game.world.get_voxel(Coord3::new(11, 0, 10)), BlockType::Sand);
+        registry.execute(&mut game, "/rotate 90").unwrap();
+        registry.execute(&mut game, "/paste").unwrap();
+        assert_eq!(game.world.get_voxel(Coord3::new(5, 5, 5)), BlockType::Sand);
+        assert_eq!(game.world.get_voxel(Coord3::new(5, 5, 6)), BlockType::Dirt);
+        assert!(game.take_changed_blocks().contains(&Coord3::new(5, 5, 6)));
+
+        assert_eq!(registry.execute(&mut game, "/undo"), Ok("undone 2 blocks".to_string()));
+        assert_eq!(game.world.get_voxel(Coord3::new(5, 5, 6)), BlockType::Air);
+        assert!(game.take_changed_blocks().contains(&Coord3::new(5, 5, 6)));
+        registry.execute(&mut game, "/undo").unwrap();
+        assert_eq!(game.world.get_voxel(Coord3::new(10, 0, 10)), BlockType::Air);
+        registry.execute(&mut game, "/redo").unwrap();
+        assert_eq!(game.world.get_voxel(Coord3::new(10, 0, 10)), BlockType::Dirt);
+        // setblock nie trafia do historii, cofniecie replace przywraca zapisany kamien
+        registry.execute(&mut game, "/undo").unwrap();
+        registry.execute(&mut game, "/undo").unwrap();
+        assert_eq!(game.world.get_voxel(Coord3::new(0, 0, 0)), BlockType::Stone);
+        assert_eq!(game.world.get_voxel(Coord3::new(1, 0, 0)), BlockType::Stone);
+        registry.execute(&mut game, "/sphere 20 20 20 2 stone").unwrap();
+        registry.execute(&mut game, "/cylinder 30 20 30 1 4 stone").unwrap();
+        assert_eq!(game.world.get_voxel(Coord3::new(22, 20, 20)), BlockType::Stone);
+        assert_eq!(game.world.get_voxel(Coord3::new(30, 23, 31)), BlockType::Stone);
+        assert!(registry.execute(&mut game, "/sphere 20 20 20 100 stone").is_err());
+        assert!(registry.execute(&mut game, "/flip w").is_err());
+        // wklejany schowek podlega temu samemu limitowi co fill
+        game.clipboard = Some(Structure::new(vec![(Coord3::ZERO, BlockType::Stone), (Coord3::new(40, 40, 40), BlockType::Stone)]));
+        assert!(registry.execute(&mut game, "/paste 0 5 0").unwrap_err().contains("more than 32768"));
+        assert_eq!(game.world.get_voxel(Coord3::new(0, 5, 0)), BlockType::Air);
+    }
+
+    #[test]
+    fn bad_commands_report_errors(){
+        let mut game = game();
+        let registry = CommandRegistry::default();
+        assert!(registry.execute(&mut game, "/nope").unwrap_err().contains("unknown command"));
+        assert!(registry.execute(&mut game, "/tp 1 2").unwrap_err().contains("usage: /tp <x> <y> <z>"));
+        assert!(registry.execute(&mut game, "/setblock 1 2 3 cheese").is_err());
+        assert!(registry.execute(&mut game, "/fill 0 0 0 100 100 100 stone").is_err());
+        assert!(registry.execute(&mut game, "/time 2").is_err());
+        game.prediction = Some(Default::default());
+        assert!(registry.execute(&mut game, "/setblock 1 2 3 sand").is_err());
+        assert!(registry.execute(&mut game, "/help").unwrap().contains("/renderdistance <chunks>"));
+    }
+
+    #[test]
+    fn console_runs_registered_commands_and_keeps_chat(){
+        let mut game = game();
+        let mut console = Console::default();
+        console.get_registry_mut().register("ping", "", |_, _| Ok("pong".to_string()));
+        console.open();
+        for c in "/ping\n".chars(){
+            console.type_char(c);
+        }
+        assert_eq!(console.get_input(), "/ping");
+        assert_eq!(console.submit(&mut game), Some("pong".to_string()));
+        assert!(!console.is_open());
+        assert_eq!(console.submit(&mut game), None);
+        assert_eq!(console.execute(&mut game, "hello"), "<player> hello");
+        assert_eq!(console.take_chat(), vec!["hello".to_string()]);
+        assert!(console.take_chat().is_empty());
+        for _ in 0..Console::MAX_LINES{
+            console.execute(&mut game, "/seed");
+        }
+        assert_eq!(console.get_lines().len(), Console::MAX_LINES);
+        assert!(console.get_lines().iter().all(|line| line == "seed: 2137"));
+    }
+}
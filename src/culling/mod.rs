@@ -0,0 +1,112 @@
+use std::{collections::{HashSet, VecDeque}, fmt::Display};
+
+use crate::{chunk::ChunkVisibility, math::{Coord3, Frustum}, mesh::ChunkMesh};
+
+#[derive(Default, Clone, Copy)]
+pub struct DrawStats{
+    pub total: usize,
+    pub frustum_culled: usize,
+    pub occlusion_culled: usize,
+    pub drawn: usize,
+    pub lod_drawn: usize
+}
+impl Display for DrawStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "chunks drawn: {}/{} (frustum culled: {}, occlusion culled: {}), lod tiles drawn: {}",
+            self.drawn, self.total, self.frustum_culled, self.occlusion_culled, self.lod_drawn)
+    }
+}
+
+// przejscie BFS przez chunki zaczynajac od chunka kamery, do sasiada mozna wejsc tylko gdy
+// sciana wejscia jest polaczona ze sciana wyjscia, kierunek nigdy nie wraca w strone kamery
+pub fn find_visible_chunks(
+    camera_chunk: Coord3,
+    frustum: &Frustum,
+    max_distance: i32,
+    get_visibility: impl Fn(Coord3) -> Option<ChunkVisibility>
+) -> HashSet<Coord3>{
+    let neighbors: Vec<Coord3> = Coord3::neighbors_into_iter().collect();
+    let mut visible: HashSet<Coord3> = HashSet::new();
+    let mut queue: VecDeque<(Coord3, Option<usize>, u8)> = VecDeque::new();
+    visible.insert(camera_chunk);
+    queue.push_back((camera_chunk, None, 0));
+
+    while let Some((position, entered_from, directions)) = queue.pop_front(){
+        let visibility = match get_visibility(position){
+            Some(visibility) => visibility,
+            None => continue
+        };
+        for (face, offset) in neighbors.iter().enumerate(){
+            // sciany sa w parach (+y,-y), (+x,-x), (+z,-z)
+            let opposite = face^1;
+            if directions & 1<<opposite != 0{
+                continue;
+            }
+            if let Some(entered) = entered_from{
+                if !visibility.is_connected(entered, face){
+                    continue;
+                }
+            }
+            let next = position+*offset;
+            if visible.contains(&next) || next.distance2(camera_chunk) > max_distance.pow(2){
+                continue;
+            }
+            if !frustum.intersects_aabb(&ChunkMesh::get_chunk_aabb(next)){
+                continue;
+            }
+            visible.insert(next);
+            queue.push_back((next, Some(opposite), directions | 1<<face));
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Matrix4;
+
+    use super::*;
+
+    fn everything() -> Frustum{
+        // ortho obejmujace caly testowy swiat
+        Frustum::from_matrix(&Matrix4::new_orthographic(-1000.0, 1000.0, -1000.0, 1000.0, -1000.0, 1000.0))
+    }
+
+    #[test]
+    fn open_world_is_fully_visible(){
+        let visible = find_visible_chunks(Coord3::ZERO, &everything(), 2, |_| Some(ChunkVisibility::ALL));
+        assert!(visible.contains(&Coord3::new(2, 0, 0)));
+        assert!(visible.contains(&Coord3::new(1, 1, -1)));
+        assert!(!visible.contains(&Coord3::new(2, 2, 0)));
+    }
+
+    #[test]
+    fn solid_chunks_hide_what_is_behind_them(){
+        // sciana pelnych chunkow na x=1
+        let visible = find_visible_chunks(Coord3::ZERO, &everything(), 4, |position| {
+            if position.x == 1 {Some(ChunkVisibility::NONE)} else {Some(ChunkVisibility::ALL)}
+        });
+        assert!(visible.contains(&Coord3::new(1, 0, 0)));
+        assert!(!visible.contains(&Coord3::new(2, 0, 0)));
+        assert!(!visible.contains(&Coord3::new(3, 1, 0)));
+        assert!(visible.contains(&Coord3::new(-3, 0, 0)));
+    }
+
+    #[test]
+    fn only_connected_faces_let_the_view_through(){
+        let wall_with = |tunnel: ChunkVisibility| find_visible_chunks(Coord3::ZERO, &everything(), 4, move |position| {
+            if position == Coord3::new(1, 0, 0) {Some(tunnel)}
+            else if position.x == 1 {Some(ChunkVisibility::NONE)}
+            else {Some(ChunkVisibility::ALL)}
+        });
+        let mut tunnel = ChunkVisibility::NONE;
+        // -x (3) z +x (2)
+        tunnel.connect(3, 2);
+        assert!(wall_with(tunnel).contains(&Coord3::new(2, 0, 0)));
+
+        let mut shaft = ChunkVisibility::NONE;
+        // +y (0) z -y (1)
+        shaft.connect(0, 1);
+        assert!(!wall_with(shaft).contains(&Coord3::new(2, 0, 0)));
+    }
+}
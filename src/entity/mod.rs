@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+
+use crate::{chunk_master::ChunkMaster, math::{Aabb, Vec3}, physics::{self, PhysicsSettings}, transform::Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(u32);
+
+// obiekt poza siatka wokseli, pozycja to srodek jego aabb
+#[derive(Clone)]
+pub struct Entity{
+    pub transform: Transform,
+    pub previous_position: Vec3,
+    pub half_extents: Vec3,
+    pub velocity: Vec3,
+    pub gravity: bool,
+    pub on_ground: bool,
+    pub color: Vector3<f32>
+}
+impl Entity {
+    pub fn new(position: Vec3, half_extents: Vec3) -> Self{
+        Entity{
+            transform: Transform::new(position),
+            previous_position: position,
+            half_extents,
+            velocity: Vec3::ZERO,
+            gravity: true,
+            on_ground: false,
+            color: Vector3::new(0.8, 0.2, 0.2)
+        }
+    }
+    pub fn get_aabb(&self) -> Aabb{
+        Aabb::from_center(self.transform.position, self.half_extents)
+    }
+    pub fn get_interpolated_position(&self, alpha: f32) -> Vec3{
+        self.previous_position+(self.transform.position-self.previous_position)*alpha
+    }
+    fn update(&mut self, delta_time: f32, physics: &PhysicsSettings, world: &impl ChunkMaster){
+        self.previous_position = self.transform.position;
+        if self.gravity{
+            self.velocity.y = (self.velocity.y-physics.gravity*delta_time).max(-physics.terminal_velocity);
+        }
+        let result = physics::move_aabb(&self.get_aabb(), self.velocity*delta_time, world);
+        self.transform.position += result.motion;
+        self.on_ground = result.blocked[1] && self.velocity.y < 0.0;
+        if result.blocked[0]{
+            self.velocity.x = 0.0;
+        }
+        if result.blocked[1]{
+            self.velocity.y = 0.0;
+        }
+        if result.blocked[2]{
+            self.velocity.z = 0.0;
+        }
+    }
+}
+
+// proste przechowywanie obiektow dynamicznych, aktualizowanych w kroku symulacji
+#[derive(Default)]
+pub struct EntityStore{
+    entities: HashMap<EntityId, Entity>,
+    next_id: u32,
+    pub physics: PhysicsSettings
+}
+impl EntityStore {
+    pub fn spawn(&mut self, entity: Entity) -> EntityId{
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, entity);
+        id
+    }
+    pub fn despawn(&mut self, id: EntityId) -> Option<Entity>{
+        self.entities.remove(&id)
+    }
+    pub fn get(&self, id: EntityId) -> Option<&Entity>{
+        self.entities.get(&id)
+    }
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity>{
+        self.entities.get_mut(&id)
+    }
+    pub fn len(&self) -> usize{
+        self.entities.len()
+    }
+    pub fn is_empty(&self) -> bool{
+        self.entities.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Entity)>{
+        self.entities.iter().map(|(id, entity)| (*id, entity))
+    }
+    pub fn tick(&mut self, delta_time: f32, world: &impl ChunkMaster){
+        for entity in self.entities.values_mut(){
+            entity.update(delta_time, &self.physics, world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block::BlockType, chunk_master::MemoryWorld, math::Coord3};
+
+    use super::*;
+
+    fn test_world() -> MemoryWorld{
+        let world = MemoryWorld::default();
+        world.fill(Coord3::ZERO, Coord3::new(9, 0, 9), BlockType::Stone);
+        world.fill(Coord3::new(8, 1, 5), Coord3::new(8, 3, 5), BlockType::Stone);
+        world
+    }
+
+    fn run(store: &mut EntityStore, world: &MemoryWorld, ticks: usize){
+        for _ in 0..ticks{
+            store.tick(1.0/60.0, world);
+        }
+    }
+
+    #[test]
+    fn entity_falls_and_lands_on_terrain(){
+        let world = test_world();
+        let mut store = EntityStore::default();
+        let id = store.spawn(Entity::new(Vec3::new(3.0, 8.0, 3.0), Vec3::new(0.25, 0.25, 0.25)));
+        run(&mut store, &world, 120);
+        let entity = store.get(id).unwrap();
+        assert!(entity.on_ground);
+        // podloga konczy sie na y=0.5
+        assert!((entity.transform.position.y-0.75).abs() < 1e-3);
+    }
+
+    #[test]
+    fn entity_is_stopped_by_walls(){
+        let world = test_world();
+        let mut store = EntityStore::default();
+        let mut entity = Entity::new(Vec3::new(5.0, 1.0, 5.0), Vec3::new(0.25, 0.25, 0.25));
+        entity.velocity = Vec3::new(5.0, 0.0, 0.0);
+        let id = store.spawn(entity);
+        run(&mut store, &world, 120);
+        let entity = store.get(id).unwrap();
+        assert!((entity.transform.position.x-7.25).abs() < 1e-3);
+        assert_eq!(entity.velocity.x, 0.0);
+    }
+
+    #[test]
+    fn entities_without_gravity_float(){
+        let world = test_world();
+        let mut store = EntityStore::default();
+        let mut entity = Entity::new(Vec3::new(3.0, 8.0, 3.0), Vec3::new(0.25, 0.25, 0.25));
+        entity.gravity = false;
+        let id = store.spawn(entity);
+        let other = store.spawn(Entity::new(Vec3::new(4.0, 8.0, 4.0), Vec3::new(0.25, 0.25, 0.25)));
+        run(&mut store, &world, 30);
+        assert_eq!(store.get(id).unwrap().transform.position.y, 8.0);
+        assert!(store.despawn(other).is_some());
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,546 @@
+use std::{fmt::Write as _, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use crate::{block::BlockType, chunk::Chunk, chunk_master::ChunkMaster, math::{Coord3, Vec3}};
+
+// eksport fragmentu swiata do formatow czytanych przez inne programy
+
+// prostopadloscian blokow, obie granice wlacznie
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region{
+    pub min: Coord3,
+    pub max: Coord3
+}
+impl Region {
+    pub fn new(a: Coord3, b: Coord3) -> Self{
+        Region{
+            min: Coord3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Coord3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z))
+        }
+    }
+    // caly chunk
+    pub fn from_chunk(chunk_position: Coord3) -> Self{
+        let min = chunk_position*Chunk::CHUNK_SIZE as i32;
+        Region{min, max: min+Coord3::new(1, 1, 1)*(Chunk::CHUNK_SIZE as i32-1)}
+    }
+    pub fn get_size(&self) -> Coord3{
+        self.max-self.min+Coord3::new(1, 1, 1)
+    }
+    pub fn contains(&self, position: Coord3) -> bool{
+        (self.min.x..=self.max.x).contains(&position.x)
+            && (self.min.y..=self.max.y).contains(&position.y)
+            && (self.min.z..=self.max.z).contains(&position.z)
+    }
+    // chunki, ktore trzeba wczytac, zeby miec caly obszar
+    pub fn get_chunks(&self) -> Vec<Coord3>{
+        let (min, max) = (self.min.div_euclid(Chunk::CHUNK_SIZE as i32), self.max.div_euclid(Chunk::CHUNK_SIZE as i32));
+        let mut chunks = Vec::new();
+        for x in min.x..=max.x{
+            for y in min.y..=max.y{
+                for z in min.z..=max.z{
+                    chunks.push(Coord3::new(x, y, z));
+                }
+            }
+        }
+        chunks
+    }
+    pub fn positions(&self) -> impl Iterator<Item = Coord3> + '_{
+        (self.min.x..=self.max.x).flat_map(move |x| (self.min.y..=self.max.y)
+            .flat_map(move |y| (self.min.z..=self.max.z).map(move |z| Coord3::new(x, y, z))))
+    }
+}
+
+// widoczna sciana bloku, rogi przeciwnie do wskazowek zegara patrzac od strony normalnej
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quad{
+    pub block: BlockType,
+    pub corners: [Vec3; 4],
+    pub normal: Coord3
+}
+
+// sciany z tablicy scian chunka, tej samej co w siatce do rysowania;
+// na granicy obszaru zawsze, zeby wycinek byl zamkniety
+pub fn build_quads(world: &impl ChunkMaster, region: &Region) -> Vec<Quad>{
+    let normals: Vec<Coord3> = Coord3::neighbors_into_iter().collect();
+    let mut quads = Vec::new();
+    for chunk_position in region.get_chunks(){
+        let Some(chunk) = world.get_chunk(chunk_position) else {
+            continue;
+        };
+        let faces_table = chunk.calculate_faces_table(&world.get_neighbourhood(chunk_position));
+        let mut voxels: Vec<usize> = chunk.get_voxels().iter()
+            .copied()
+            .filter(|index| region.contains(chunk.get_world_position(Chunk::get_local_position_from_index(*index))))
+            .collect();
+        // stala kolejnosc, niezalezna od kolejnosci stawiania blokow
+        voxels.sort_unstable();
+        for index in voxels{
+            let position = chunk.get_world_position(Chunk::get_local_position_from_index(index));
+            let block = chunk.get_voxel_from_index(index);
+            for (i, normal) in normals.iter().enumerate(){
+                let hidden = faces_table[index] & 1<<i != 0;
+                if hidden && region.contains(position+*normal){
+                    continue;
+                }
+                quads.push(Quad{block, corners: get_face_corners(position, *normal), normal: *normal});
+            }
+        }
+    }
+    quads
+}
+
+fn to_vec3(coord: Coord3) -> Vec3{
+    Vec3::new(coord.x as f32, coord.y as f32, coord.z as f32)
+}
+fn get_face_corners(position: Coord3, normal: Coord3) -> [Vec3; 4]{
+    // u x v = normal, dla ujemnej normalnej osie sa zamienione
+    let axis = Coord3::new(normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let (u, v) = if normal == axis {(axis.zxy(), axis.yzx())} else {(axis.yzx(), axis.zxy())};
+    let (u, v) = (to_vec3(u)*0.5, to_vec3(v)*0.5);
+    let center = to_vec3(position)+to_vec3(normal)*0.5;
+    [center-u-v, center+u-v, center+u+v, center-u+v]
+}
+
+// bloki z obszaru w liniach "x y z Blok", zwraca liczbe blokow
+pub fn write_xyz(world: &impl ChunkMaster, region: &Region, out: &mut impl Write) -> io::Result<usize>{
+    writeln!(out, "# x y z block")?;
+    let mut count = 0;
+    for position in region.positions(){
+        let block = world.get_voxel(position);
+        if block != BlockType::Air{
+            writeln!(out, "{} {} {} {:?}", position.x, position.y, position.z, block)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn blocks_in(quads: &[Quad]) -> Vec<BlockType>{
+    BlockType::ALL.into_iter().filter(|block| quads.iter().any(|quad| quad.block == *block)).collect()
+}
+
+// Wavefront OBJ z materialem na kazdy typ bloku, mtl_name to nazwa pliku .mtl obok .obj
+pub fn write_obj(quads: &[Quad], mtl_name: &str, obj: &mut impl Write, mtl: &mut impl Write) -> io::Result<()>{
+    let blocks = blocks_in(quads);
+    for block in blocks.iter(){
+        let color = block.get_color();
+        writeln!(mtl, "newmtl {block:?}")?;
+        writeln!(mtl, "Kd {} {} {}", color.x, color.y, color.z)?;
+    }
+    writeln!(obj, "mtllib {mtl_name}")?;
+    let normals: Vec<Coord3> = Coord3::neighbors_into_iter().collect();
+    for normal in normals.iter(){
+        writeln!(obj, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+    }
+    // indeksy w OBJ licza sie od 1
+    let mut next_vertex = 1;
+    for block in blocks{
+        writeln!(obj, "usemtl {block:?}")?;
+        for quad in quads.iter().filter(|quad| quad.block == block){
+            for corner in quad.corners{
+                writeln!(obj, "v {} {} {}", corner.x, corner.y, corner.z)?;
+            }
+            let normal = normals.iter().position(|normal| *normal == quad.normal).unwrap()+1;
+            writeln!(obj, "f {0}//{4} {1}//{4} {2}//{4} {3}//{4}", next_vertex, next_vertex+1, next_vertex+2, next_vertex+3, normal)?;
+            next_vertex += 4;
+        }
+    }
+    Ok(())
+}
+
+// glTF 2.0 w wersji binarnej (.glb): jedna siatka, prymityw z materialem na kazdy typ bloku
+pub fn write_glb(quads: &[Quad], out: &mut impl Write) -> io::Result<()>{
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    let mut binary: Vec<u8> = Vec::new();
+    let (mut primitives, mut materials, mut accessors, mut views) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for block in blocks_in(quads){
+        let block_quads: Vec<&Quad> = quads.iter().filter(|quad| quad.block == block).collect();
+        let count = block_quads.len()*4;
+        let positions: Vec<Vec3> = block_quads.iter().flat_map(|quad| quad.corners).collect();
+        let normals: Vec<Vec3> = block_quads.iter().flat_map(|quad| [to_vec3(quad.normal); 4]).collect();
+        let indices: Vec<u32> = (0..block_quads.len() as u32).flat_map(|i| [0, 1, 2, 0, 2, 3].map(|corner| i*4+corner)).collect();
+        let min = positions.iter().fold(positions[0], |min, p| Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)));
+        let max = positions.iter().fold(positions[0], |max, p| Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)));
+        let first = accessors.len();
+        for (values, target) in [(&positions, ARRAY_BUFFER), (&normals, ARRAY_BUFFER)]{
+            views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#, binary.len(), count*12));
+            for value in values.iter(){
+                binary.extend([value.x, value.y, value.z].iter().flat_map(|v| v.to_le_bytes()));
+            }
+        }
+        views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{ELEMENT_ARRAY_BUFFER}}}"#, binary.len(), indices.len()*4));
+        binary.extend(indices.iter().flat_map(|index| index.to_le_bytes()));
+        accessors.push(format!(r#"{{"bufferView":{first},"componentType":{FLOAT},"count":{count},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            min.x, min.y, min.z, max.x, max.y, max.z));
+        accessors.push(format!(r#"{{"bufferView":{},"componentType":{FLOAT},"count":{count},"type":"VEC3"}}"#, first+1));
+        accessors.push(format!(r#"{{"bufferView":{},"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#, first+2, indices.len()));
+        primitives.push(format!(r#"{{"attributes":{{"POSITION":{first},"NORMAL":{}}},"indices":{},"material":{}}}"#, first+1, first+2, materials.len()));
+        let color = block.get_color();
+        materials.push(format!(r#"{{"name":"{block:?}","pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},1],"metallicFactor":0,"roughnessFactor":1}}}}"#,
+            color.x, color.y, color.z));
+    }
+    let mut json = String::from(r#"{"asset":{"version":"2.0","generator":"my_opengl_game"},"scene":0"#);
+    if primitives.is_empty(){
+        json.push_str(r#","scenes":[{}]}"#);
+    }
+    else{
+        let _ = write!(json, r#","scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"meshes":[{{"primitives":[{}]}}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#,
+            primitives.join(","), materials.join(","), accessors.join(","), views.join(","), binary.len());
+    }
+    // kawalki sa wyrownane do 4 bajtow: JSON spacjami, dane zerami
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    binary.resize(binary.len().next_multiple_of(4), 0);
+    let binary_chunk = if binary.is_empty() {0} else {8+binary.len()};
+    out.write_all(b"glTF")?;
+    out.write_all(&2u32.to_le_bytes())?;
+    out.write_all(&((12+8+json.len()+binary_chunk) as u32).to_le_bytes())?;
+    out.write_all(&(json.len() as u32).to_le_bytes())?;
+    out.write_all(b"JSON")?;
+    out.write_all(&json)?;
+    if !binary.is_empty(){
+        out.write_all(&(binary.len() as u32).to_le_bytes())?;
+        out.write_all(b"BIN\0")?;
+        out.write_all(&binary)?;
+    }
+    Ok(())
+}
+
+// MagicaVoxel .vox, os z jest w gore, kolor to id bloku w palecie
+pub const MAX_VOX_SIZE: i32 = 256;
+pub fn write_vox(world: &impl ChunkMaster, region: &Region, out: &mut impl Write) -> io::Result<usize>{
+    let size = region.get_size();
+    if size.x > MAX_VOX_SIZE || size.y > MAX_VOX_SIZE || size.z > MAX_VOX_SIZE{
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("region {size} is larger than {MAX_VOX_SIZE} in .vox")));
+    }
+    let mut voxels: Vec<u8> = Vec::new();
+    for position in region.positions(){
+        let block = world.get_voxel(position);
+        if block != BlockType::Air{
+            // obrot wokol x, zeby uklad dalej byl prawoskretny
+            let local = position-region.min;
+            voxels.extend_from_slice(&[local.x as u8, (size.z-1-local.z) as u8, local.y as u8, block as u8]);
+        }
+    }
+    let mut palette = [0u8; 256*4];
+    for block in BlockType::ALL.into_iter().filter(|block| *block != BlockType::Air){
+        let color = block.get_color();
+        let entry = (block as usize-1)*4;
+        palette[entry..entry+4].copy_from_slice(&[(color.x*255.0) as u8, (color.y*255.0) as u8, (color.z*255.0) as u8, 255]);
+    }
+    let mut children: Vec<u8> = Vec::new();
+    let mut chunk = |id: &[u8; 4], content: &[u8]|{
+        children.extend_from_slice(id);
+        children.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        children.extend_from_slice(&0u32.to_le_bytes());
+        children.extend_from_slice(content);
+    };
+    chunk(b"SIZE", &[size.x as u32, size.z as u32, size.y as u32].map(u32::to_le_bytes).concat());
+    chunk(b"XYZI", &[&((voxels.len()/4) as u32).to_le_bytes()[..], &voxels].concat());
+    chunk(b"RGBA", &palette);
+    out.write_all(b"VOX ")?;
+    out.write_all(&150u32.to_le_bytes())?;
+    out.write_all(b"MAIN")?;
+    out.write_all(&0u32.to_le_bytes())?;
+    out.write_all(&(children.len() as u32).to_le_bytes())?;
+    out.write_all(&children)?;
+    Ok(voxels.len()/4)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Xyz,
+    Obj,
+    Gltf,
+    Vox
+}
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<ExportFormat>{
+        match name.to_ascii_lowercase().as_str() {
+            "xyz" | "txt" => Some(ExportFormat::Xyz),
+            "obj" => Some(ExportFormat::Obj),
+            "gltf" | "glb" => Some(ExportFormat::Gltf),
+            "vox" => Some(ExportFormat::Vox),
+            _ => None
+        }
+    }
+    // po rozszerzeniu pliku wyjsciowego
+    pub fn from_path(path: &Path) -> Option<ExportFormat>{
+        path.extension().and_then(|extension| extension.to_str()).and_then(ExportFormat::parse)
+    }
+}
+
+// zapis do pliku, OBJ dostaje obok plik .mtl o tej samej nazwie
+pub fn export_to_file(world: &impl ChunkMaster, region: &Region, format: ExportFormat, path: &Path) -> io::Result<()>{
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ExportFormat::Xyz => write_xyz(world, region, &mut out).map(|_| ())?,
+        ExportFormat::Obj => {
+            let mtl_path = path.with_extension("mtl");
+            let mtl_name = mtl_path.file_name().and_then(|name| name.to_str()).unwrap_or("world.mtl").to_string();
+            let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+            write_obj(&build_quads(world, region), &mtl_name, &mut out, &mut mtl)?;
+            mtl.flush()?;
+        }
+        ExportFormat::Gltf => write_glb(&build_quads(world, region), &mut out)?,
+        ExportFormat::Vox => write_vox(world, region, &mut out).map(|_| ())?
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk_master::MemoryWorld, mesh::ChunkMesher};
+
+    use super::*;
+
+    // maly parser JSON tylko do sprawdzania eksportu glTF
+    #[derive(Debug, PartialEq)]
+    enum Json {
+        Number(f64),
+        Text(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+        Other
+    }
+    impl Json {
+        fn get(&self, key: &str) -> &Json{
+            match self {
+                Json::Object(fields) => &fields.iter().find(|(name, _)| name == key).unwrap_or_else(|| panic!("no {key}")).1,
+                _ => panic!("{key} in a non-object")
+            }
+        }
+        fn at(&self, index: usize) -> &Json{
+            match self {
+                Json::Array(items) => &items[index],
+                _ => panic!("index in a non-array")
+            }
+        }
+        fn len(&self) -> usize{
+            match self {
+                Json::Array(items) => items.len(),
+                _ => panic!("len of a non-array")
+            }
+        }
+        fn number(&self) -> f64{
+            match self {
+                Json::Number(value) => *value,
+                _ => panic!("not a number")
+            }
+        }
+        fn parse(text: &mut &str) -> Json{
+            *text = text.trim_start();
+            let first = text.chars().next().unwrap();
+            let mut rest = text[1..].trim_start();
+            let value = match first {
+                '{' | '[' => {
+                    let mut fields = Vec::new();
+                    while !rest.starts_with(['}', ']']){
+                        let key = if first == '{' {
+                            let Json::Text(key) = Json::parse(&mut rest) else {panic!("object key")};
+                            rest = rest.trim_start().strip_prefix(':').unwrap();
+                            key
+                        } else {String::new()};
+                        fields.push((key, Json::parse(&mut rest)));
+                        rest = rest.trim_start();
+                        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+                    }
+                    rest = &rest[1..];
+                    if first == '{' {Json::Object(fields)} else {Json::Array(fields.into_iter().map(|(_, value)| value).collect())}
+                }
+                '"' => {
+                    let end = text[1..].find('"').unwrap();
+                    rest = &text[end+2..];
+                    Json::Text(text[1..end+1].to_string())
+                }
+                _ => {
+                    let end = text.find([',', '}', ']']).unwrap_or(text.len());
+                    rest = &text[end..];
+                    text[..end].trim().parse().map(Json::Number).unwrap_or(Json::Other)
+                }
+            };
+            *text = rest;
+            value
+        }
+    }
+
+    fn world() -> MemoryWorld{
+        MemoryWorld::with_blocks([
+            (Coord3::new(1, 1, 1), BlockType::Stone),
+            (Coord3::new(2, 1, 1), BlockType::Stone),
+            (Coord3::new(1, 2, 1), BlockType::Sand)
+        ])
+    }
+    fn read_u32(bytes: &[u8], offset: usize) -> u32{
+        u32::from_le_bytes(bytes[offset..offset+4].try_into().unwrap())
+    }
+
+    #[test]
+    fn quads_face_outwards_and_respect_the_region(){
+        let world = world();
+        let region = Region::new(Coord3::new(3, 3, 3), Coord3::ZERO);
+        let quads = build_quads(&world, &region);
+        // dwa stykajace sie bloki kamienia i piasek na jednym z nich
+        assert_eq!(quads.len(), 3*6-4);
+        for quad in quads.iter(){
+            let [a, b, c, _] = quad.corners;
+            let normal = (b-a).cross(c-b).normalize();
+            assert_eq!(normal, to_vec3(quad.normal));
+        }
+        // granica obszaru przecina kamien, wiec sciana na granicy jest widoczna
+        let cut = build_quads(&world, &Region::new(Coord3::new(1, 1, 1), Coord3::new(1, 1, 1)));
+        assert_eq!(cut.len(), 6);
+        assert_eq!(Region::from_chunk(Coord3::new(-1, 0, 0)).get_chunks(), vec![Coord3::new(-1, 0, 0)]);
+        assert_eq!(Region::new(Coord3::new(-1, 0, 0), Coord3::new(50, 0, 0)).get_chunks().len(), 3);
+    }
+
+    #[test]
+    fn quads_match_the_chunk_mesh(){
+        let world = world();
+        world.fill(Coord3::new(5, 0, 5), Coord3::new(8, 2, 8), BlockType::Water);
+        world.set_voxel(Coord3::new(6, 3, 6), BlockType::Stone);
+        world.place(Coord3::new(49, 0, 0), BlockType::Dirt);
+        // chunk bez sasiadow, wiec siatka tez ma sciany na jego granicy
+        let mesh = ChunkMesher::new().build_mesh(&world.get_chunk(Coord3::ZERO).unwrap(), &world);
+        let faces = (mesh.get_vertices().len()+mesh.get_vertices_water().len())/(ChunkMesher::VERTEX_SIZE*6);
+        assert_eq!(build_quads(&world, &Region::from_chunk(Coord3::ZERO)).len(), faces);
+    }
+
+    #[test]
+    fn obj_parses_back_with_block_materials(){
+        let world = world();
+        let quads = build_quads(&world, &Region::new(Coord3::ZERO, Coord3::new(3, 3, 3)));
+        let (mut obj, mut mtl) = (Vec::new(), Vec::new());
+        write_obj(&quads, "world.mtl", &mut obj, &mut mtl).unwrap();
+        let (obj, mtl) = (String::from_utf8(obj).unwrap(), String::from_utf8(mtl).unwrap());
+
+        let mut colors = Vec::new();
+        for line in mtl.lines(){
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["newmtl", name] => colors.push((BlockType::parse(name).unwrap(), Vec::new())),
+                ["Kd", r, g, b] => colors.last_mut().unwrap().1 = [r, g, b].map(|value| value.parse::<f32>().unwrap()).to_vec(),
+                _ => {}
+            }
+        }
+        assert_eq!(colors.len(), 2);
+        for (block, color) in colors{
+            let expected = block.get_color();
+            assert_eq!(color, vec![expected.x, expected.y, expected.z]);
+        }
+
+        let (mut vertices, mut normals, mut faces) = (Vec::new(), Vec::new(), Vec::new());
+        let mut material = None;
+        assert!(obj.starts_with("mtllib world.mtl\n"));
+        for line in obj.lines(){
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[0] {
+                "v" => vertices.push(Vec3::new(words[1].parse().unwrap(), words[2].parse().unwrap(), words[3].parse().unwrap())),
+                "vn" => normals.push(Coord3::new(words[1].parse().unwrap(), words[2].parse().unwrap(), words[3].parse().unwrap())),
+                "usemtl" => material = BlockType::parse(words[1]),
+                "f" => {
+                    let corners: Vec<(usize, usize)> = words[1..].iter().map(|corner|{
+                        let (vertex, normal) = corner.split_once("//").unwrap();
+                        (vertex.parse().unwrap(), normal.parse().unwrap())
+                    }).collect();
+                    faces.push((material.unwrap(), corners));
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(faces.len(), quads.len());
+        for (block, corners) in faces{
+            let normal = normals[corners[0].1-1];
+            let corners: Vec<Vec3> = corners.iter().map(|(vertex, _)| vertices[vertex-1]).collect();
+            assert!(quads.iter().any(|quad| quad.block == block && quad.normal == normal && quad.corners.to_vec() == corners));
+        }
+    }
+
+    #[test]
+    fn glb_parses_back_into_the_same_faces(){
+        let world = world();
+        let quads = build_quads(&world, &Region::new(Coord3::ZERO, Coord3::new(3, 3, 3)));
+        let mut glb = Vec::new();
+        write_glb(&quads, &mut glb).unwrap();
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(read_u32(&glb, 4), 2);
+        assert_eq!(read_u32(&glb, 8) as usize, glb.len());
+        let json_length = read_u32(&glb, 12) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        let json = Json::parse(&mut std::str::from_utf8(&glb[20..20+json_length]).unwrap());
+        assert_eq!(&glb[24+json_length..28+json_length], b"BIN\0");
+        let binary = &glb[28+json_length..];
+        assert_eq!(json.get("asset").get("version"), &Json::Text("2.0".to_string()));
+
+        let accessor = |index: &Json| json.get("accessors").at(index.number() as usize);
+        let read_floats = |accessor: &Json|{
+            let view = json.get("bufferViews").at(accessor.get("bufferView").number() as usize);
+            let offset = view.get("byteOffset").number() as usize;
+            (0..accessor.get("count").number() as usize*3)
+                .map(|i| f32::from_le_bytes(binary[offset+i*4..offset+i*4+4].try_into().unwrap()))
+                .collect::<Vec<f32>>()
+        };
+        let primitives = json.get("meshes").at(0).get("primitives");
+        let mut triangles = 0;
+        for i in 0..primitives.len(){
+            let primitive = primitives.at(i);
+            let material = json.get("materials").at(primitive.get("material").number() as usize);
+            let Json::Text(name) = material.get("name") else {panic!("material name")};
+            let block = BlockType::parse(name).unwrap();
+            let color = material.get("pbrMetallicRoughness").get("baseColorFactor");
+            assert_eq!(color.at(0).number() as f32, block.get_color().x);
+            let positions = read_floats(accessor(primitive.get("attributes").get("POSITION")));
+            let block_quads: Vec<&Quad> = quads.iter().filter(|quad| quad.block == block).collect();
+            assert_eq!(positions.len(), block_quads.len()*12);
+            for (quad, values) in block_quads.iter().zip(positions.chunks(12)){
+                let corners: Vec<Vec3> = values.chunks(3).map(|v| Vec3::new(v[0], v[1], v[2])).collect();
+                assert_eq!(corners, quad.corners.to_vec());
+            }
+            let lowest = positions.chunks(3).map(|v| v[1]).fold(f32::MAX, f32::min);
+            assert_eq!(accessor(primitive.get("attributes").get("POSITION")).get("min").at(1).number() as f32, lowest);
+            triangles += accessor(primitive.get("indices")).get("count").number() as usize/3;
+        }
+        assert_eq!(triangles, quads.len()*2);
+
+        let mut empty = Vec::new();
+        write_glb(&[], &mut empty).unwrap();
+        assert_eq!(read_u32(&empty, 8) as usize, empty.len());
+    }
+
+    #[test]
+    fn vox_parses_back_into_the_same_blocks(){
+        let world = world();
+        let region = Region::new(Coord3::new(1, 1, 1), Coord3::new(2, 3, 1));
+        let mut vox = Vec::new();
+        assert_eq!(write_vox(&world, &region, &mut vox).unwrap(), 3);
+        assert_eq!(&vox[0..4], b"VOX ");
+        assert_eq!(read_u32(&vox, 4), 150);
+        assert_eq!(&vox[8..12], b"MAIN");
+        assert_eq!(read_u32(&vox, 16) as usize, vox.len()-20);
+        let mut offset = 20;
+        let (mut size, mut blocks, mut palette) = (Vec::new(), Vec::new(), Vec::new());
+        while offset < vox.len(){
+            let (id, length) = (&vox[offset..offset+4], read_u32(&vox, offset+4) as usize);
+            let content = &vox[offset+12..offset+12+length];
+            match id {
+                b"SIZE" => size = (0..3).map(|i| read_u32(content, i*4)).collect(),
+                b"XYZI" => blocks = content[4..].chunks(4).map(|voxel| voxel.to_vec()).collect(),
+                b"RGBA" => palette = content.to_vec(),
+                _ => panic!("unexpected chunk")
+            }
+            offset += 12+length;
+        }
+        // x, z i y z obszaru 2x3x1
+        assert_eq!(size, vec![2, 1, 3]);
+        assert_eq!(blocks, vec![
+            vec![0, 0, 0, BlockType::Stone as u8],
+            vec![0, 0, 1, BlockType::Sand as u8],
+            vec![1, 0, 0, BlockType::Stone as u8]
+        ]);
+        let sand = (BlockType::Sand as usize-1)*4;
+        assert_eq!(palette[sand..sand+4], [(0.7*255.0) as u8, (0.5*255.0) as u8, (0.1*255.0) as u8, 255]);
+        assert!(write_vox(&world, &Region::new(Coord3::ZERO, Coord3::new(256, 0, 0)), &mut Vec::new()).is_err());
+        assert_eq!(ExportFormat::from_path(Path::new("a/world.GLB")), Some(ExportFormat::Gltf));
+    }
+}
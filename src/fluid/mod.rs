@@ -0,0 +1,139 @@
+use crate::{block::BlockType, block_update::BlockUpdateScheduler, chunk::Chunk, chunk_master::ChunkMaster, math::Coord3};
+
+// automat komorkowy dla wody: zrodla maja poziom SOURCE_FLUID_LEVEL, plynaca woda traci
+// jeden poziom na kazdy blok w bok, a spadajaca ma zawsze poziom FALLING_LEVEL
+pub const FALLING_LEVEL: u8 = Chunk::SOURCE_FLUID_LEVEL-1;
+
+// poziom, jaki powinna miec plynaca woda na podstawie sasiadow
+fn get_expected_level(position: Coord3, world: &impl ChunkMaster) -> u8{
+    if world.get_voxel(position.upper()) == BlockType::Water{
+        return FALLING_LEVEL;
+    }
+    let mut level = 0;
+    for offset in Coord3::neighbors_into_iter().filter(|offset| offset.y == 0){
+        let neighbor = position+offset;
+        let neighbor_level = world.get_fluid_level(neighbor);
+        // woda plynie w bok tylko po czyms stalym, tak jak w update_fluid
+        if neighbor_level > 1 && world.get_voxel(neighbor.lower()).is_solid(){
+            level = level.max(neighbor_level-1);
+        }
+    }
+    level
+}
+
+// aktualizacja jednego woksela wody, wywolywana przez BlockUpdateScheduler
+pub fn update_fluid(position: Coord3, world: &impl ChunkMaster, scheduler: &mut BlockUpdateScheduler, changed: &mut Vec<Coord3>){
+    if world.get_voxel(position) != BlockType::Water{
+        return;
+    }
+    let mut level = world.get_fluid_level(position);
+    if level < Chunk::SOURCE_FLUID_LEVEL{
+        let expected = get_expected_level(position, world);
+        if expected == 0{
+            world.set_voxel(position, BlockType::Air);
+            changed.push(position);
+            scheduler.schedule(position);
+            return;
+        }
+        if expected != level{
+            world.set_fluid(position, expected);
+            changed.push(position);
+            scheduler.schedule(position);
+            level = expected;
+        }
+    }
+
+    let below = position.lower();
+    let below_block = world.get_voxel(below);
+    if below_block == BlockType::Air{
+        if world.set_fluid(below, FALLING_LEVEL){
+            changed.push(below);
+            scheduler.schedule(below);
+        }
+        return;
+    }
+    if !below_block.is_solid() || level <= 1{
+        return;
+    }
+    for offset in Coord3::neighbors_into_iter().filter(|offset| offset.y == 0){
+        let neighbor = position+offset;
+        if world.get_voxel(neighbor) == BlockType::Air && world.set_fluid(neighbor, level-1){
+            changed.push(neighbor);
+            scheduler.schedule(neighbor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_master::MemoryWorld;
+
+    use super::*;
+
+    // kamienna podloga na y=10
+    fn test_world() -> MemoryWorld{
+        let world = MemoryWorld::default();
+        world.fill(Coord3::new(0, 10, 0), Coord3::new(19, 10, 19), BlockType::Stone);
+        world
+    }
+
+    fn run(scheduler: &mut BlockUpdateScheduler, world: &MemoryWorld, ticks: usize){
+        for _ in 0..ticks{
+            scheduler.tick(world);
+        }
+    }
+
+    #[test]
+    fn source_spreads_sideways_with_decreasing_level(){
+        let world = test_world();
+        let source = Coord3::new(10, 11, 10);
+        world.set_fluid(source, Chunk::SOURCE_FLUID_LEVEL);
+        let mut scheduler = BlockUpdateScheduler::default();
+        scheduler.schedule(source);
+        run(&mut scheduler, &world, 50);
+        assert_eq!(world.get_fluid_level(Coord3::new(11, 11, 10)), 7);
+        assert_eq!(world.get_fluid_level(Coord3::new(13, 11, 10)), 5);
+        assert_eq!(world.get_fluid_level(Coord3::new(17, 11, 10)), 1);
+        assert_eq!(world.get_voxel(Coord3::new(18, 11, 10)), BlockType::Air);
+        assert_eq!(world.get_voxel(Coord3::new(10, 12, 10)), BlockType::Air);
+    }
+
+    #[test]
+    fn water_falls_through_removed_floor_and_dries_up_without_source(){
+        let world = test_world();
+        let source = Coord3::new(10, 11, 10);
+        world.set_fluid(source, Chunk::SOURCE_FLUID_LEVEL);
+        let mut scheduler = BlockUpdateScheduler::default();
+        scheduler.schedule(source);
+        run(&mut scheduler, &world, 50);
+
+        // dziura w podlodze obok zrodla
+        let hole = Coord3::new(12, 10, 10);
+        world.set_voxel(hole, BlockType::Air);
+        scheduler.schedule(hole);
+        run(&mut scheduler, &world, 30);
+        assert_eq!(world.get_voxel(hole), BlockType::Water);
+        assert_eq!(world.get_fluid_level(Coord3::new(12, 5, 10)), FALLING_LEVEL);
+
+        // bez zrodla cala plynaca woda znika
+        world.set_voxel(source, BlockType::Air);
+        scheduler.schedule(source);
+        run(&mut scheduler, &world, 200);
+        assert_eq!(world.get_voxel(Coord3::new(11, 11, 10)), BlockType::Air);
+        assert_eq!(world.get_voxel(Coord3::new(12, 5, 10)), BlockType::Air);
+        assert_eq!(scheduler.get_pending(), 0);
+    }
+
+    #[test]
+    fn updates_are_bounded_per_tick(){
+        let world = test_world();
+        let source = Coord3::new(10, 11, 10);
+        world.set_fluid(source, Chunk::SOURCE_FLUID_LEVEL);
+        let mut scheduler = BlockUpdateScheduler::new(3);
+        scheduler.schedule(source);
+        scheduler.tick(&world);
+        assert!(scheduler.get_pending() > 0);
+        // pierwsze 3 pozycje to zrodlo i dwoch sasiadow, ktorzy nie sa woda
+        assert_eq!(world.get_voxel(Coord3::new(11, 11, 10)), BlockType::Water);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{io, time::Instant};
+
+use dashmap::DashMap;
+
+use crate::{block::BlockType, block_update::BlockUpdateScheduler, chunk::Chunk, chunk_loader::ChunkLoader, chunk_master::ChunkMaster, entity::EntityStore, mob::MobController, pathfinding::get_feet_voxel, input::InputState, math::{Coord3, Vec3}, persistence::WorldStorage, player::Player, prediction::MovementPrediction, random_tick::RandomTicker, structure::Structure, world_edit::{Edit, History}, world_generator::WorldGenerator};
+
+// wszystko, co jest aktualizowane w stalym kroku symulacji
+pub trait Simulation {
+    fn tick(&mut self, tick: u64, delta_time: f32);
+}
+
+// stan gry bez okna, mozna go symulowac w testach i w trybie headless
+// swiat moze byc dowolnym ChunkMaster, domyslnie DashMap wspoldzielona z watkami renderera
+pub struct GameState<W: ChunkMaster = DashMap<Coord3, Chunk>>{
+    pub world: W,
+    pub player: Player,
+    pub input: InputState,
+    pub block_updates: BlockUpdateScheduler,
+    pub random_ticks: RandomTicker,
+    pub entities: EntityStore,
+    pub mobs: MobController,
+    // tylko z serwerem: kroki gracza do wyslania i do powtorzenia po korekcie
+    pub prediction: Option<MovementPrediction>,
+    // ziarno generatora, None gdy swiat przychodzi z serwera
+    pub seed: Option<u32>,
+    // pora dnia od 0 do 1, 0.5 to poludnie
+    pub time_of_day: f32,
+    // w chunkach, dalszych chunkow renderer nie rysuje
+    pub render_distance: i32,
+    // chunki w tym samym zasiegu wokol gracza, uzywany tylko przy swiecie generowanym lokalnie
+    pub chunk_loader: ChunkLoader,
+    // edycje z konsoli do cofania i skopiowany obszar
+    pub edit_history: History,
+    pub clipboard: Option<Structure>,
+    // woksele zmienione od ostatniego odczytu, renderer przebudowuje ich chunki
+    changed_blocks: Vec<Coord3>
+}
+impl<W: ChunkMaster> GameState<W> {
+    // dlugosc doby w sekundach
+    pub const DAY_LENGTH: f32 = 1200.0;
+
+    pub fn new(world: W, player: Player, input: InputState) -> Self{
+        let mut chunk_loader = ChunkLoader::default();
+        chunk_loader.set_load_distance(ChunkLoader::MAX_LOAD_DISTANCE);
+        GameState{
+            world,
+            player,
+            input,
+            block_updates: BlockUpdateScheduler::default(),
+            random_ticks: RandomTicker::default(),
+            entities: EntityStore::default(),
+            mobs: MobController::default(),
+            prediction: None,
+            seed: None,
+            time_of_day: 0.4,
+            render_distance: chunk_loader.get_load_distance(),
+            chunk_loader,
+            edit_history: History::default(),
+            clipboard: None,
+            changed_blocks: Vec::new()
+        }
+    }
+    // zmiana bloku przez gracza, budzi wode i spadajace bloki w okolicy
+    pub fn set_block(&mut self, position: Coord3, block: BlockType) -> bool{
+        if !self.world.set_voxel(position, block){
+            return false;
+        }
+        self.block_updates.schedule(position);
+        self.changed_blocks.push(position);
+        true
+    }
+    // zmiana zrobiona poza GameState, np. przewidywana zmiana z serwera
+    pub fn mark_changed(&mut self, position: Coord3){
+        self.changed_blocks.push(position);
+    }
+    // edycja juz zmienila swiat, tu idzie ta sama droga co set_block i trafia do historii
+    pub fn record_edit(&mut self, edit: Edit) -> usize{
+        let count = edit.len();
+        self.mark_edit(&edit);
+        self.edit_history.push(edit);
+        count
+    }
+    // liczba przywroconych blokow, None gdy nie ma czego cofac
+    pub fn undo_edit(&mut self) -> Option<usize>{
+        let edit = self.edit_history.undo(&self.world)?.clone();
+        self.mark_edit(&edit);
+        Some(edit.len())
+    }
+    pub fn redo_edit(&mut self) -> Option<usize>{
+        let edit = self.edit_history.redo(&self.world)?.clone();
+        self.mark_edit(&edit);
+        Some(edit.len())
+    }
+    fn mark_edit(&mut self, edit: &Edit){
+        for position in edit.get_changed_positions(){
+            self.block_updates.schedule(position);
+            self.changed_blocks.push(position);
+        }
+    }
+    pub fn take_changed_blocks(&mut self) -> Vec<Coord3>{
+        std::mem::take(&mut self.changed_blocks)
+    }
+    // zasieg rysowania i ladowania chunkow, zwraca przycieta wartosc
+    pub fn set_render_distance(&mut self, distance: i32) -> i32{
+        self.chunk_loader.set_load_distance(distance);
+        self.render_distance = self.chunk_loader.get_load_distance();
+        self.render_distance
+    }
+    // ziarno generatora wyznacza tez losowe ticki swiata
+    pub fn set_seed(&mut self, seed: u32){
+        self.seed = Some(seed);
+        self.random_ticks.set_seed(seed as u64);
+    }
+    pub fn get_player_chunk(&self) -> Coord3{
+        self.player.get_position().to_voxel_coord().div_euclid(Chunk::CHUNK_SIZE as i32)
+    }
+    // usuwa chunki poza zasiegiem i wczytuje albo generuje do max_loaded nowych wokol gracza;
+    // zwraca pozycje nowych chunkow i usuniete chunki, zeby wywolujacy mogl je zapisac
+    pub fn stream_chunks(&mut self, generator: &WorldGenerator, storage: Option<&WorldStorage>, max_loaded: usize) -> io::Result<(Vec<Coord3>, Vec<Chunk>)>{
+        self.chunk_loader.set_world_positon(self.get_player_chunk());
+        for join in self.chunk_loader.commit_world_positon(){
+            join.join().unwrap();
+        }
+        let unloaded: Vec<Chunk> = self.chunk_loader.get_coords_to_delete().try_iter()
+            .filter_map(|position| self.world.remove_chunk(position))
+            .collect();
+        let mut loaded = Vec::new();
+        while loaded.len() < max_loaded{
+            let Ok(position) = self.chunk_loader.get_coords_to_load().try_recv() else {
+                break;
+            };
+            // gracz mogl juz odejsc, wtedy loader wysle go ponownie po powrocie
+            if self.world.chunk_exists(position) || !self.chunk_loader.should_be_loaded(position){
+                continue;
+            }
+            let stored = match storage {
+                Some(storage) => storage.load_chunk(position)?,
+                None => None
+            };
+            let chunk = stored.unwrap_or_else(||{
+                let mut chunk = Chunk::default();
+                chunk.set_chunk_position(position);
+                generator.generate_chunk(&mut chunk);
+                chunk
+            });
+            self.world.insert_chunk(chunk);
+            loaded.push(position);
+        }
+        Ok((loaded, unloaded))
+    }
+    // kierunek swiatla: slonce wschodzi o 0.25, zachodzi o 0.75, w nocy swieci od dolu
+    pub fn get_sun_direction(&self) -> Vec3{
+        let angle = (self.time_of_day-0.25)*std::f32::consts::TAU;
+        Vec3::new(-angle.cos(), -angle.sin(), -0.3).normalize()
+    }
+}
+impl<W: ChunkMaster> Simulation for GameState<W> {
+    fn tick(&mut self, tick: u64, delta_time: f32){
+        self.time_of_day = (self.time_of_day+delta_time/Self::DAY_LENGTH).fract();
+        self.player.update(&self.input, delta_time, &self.world);
+        if let Some(prediction) = &mut self.prediction{
+            // numer 0 jest zarezerwowany dla wiadomosci bez numeru
+            prediction.record(tick as u32+1, &self.input, delta_time, &self.player);
+        }
+        self.mobs.tick(&mut self.entities, &self.world, get_feet_voxel(&self.player.get_aabb()));
+        self.entities.tick(delta_time, &self.world);
+        let changed = self.block_updates.tick(&self.world);
+        self.changed_blocks.extend(changed);
+        let changed = self.random_ticks.tick(&self.world);
+        self.changed_blocks.extend(changed);
+    }
+}
+
+// staly krok symulacji niezalezny od liczby klatek, renderowanie interpoluje miedzy krokami
+pub struct GameLoop{
+    tick_duration: f32,
+    accumulator: f32,
+    tick: u64,
+    frames: u64
+}
+impl Default for GameLoop {
+    fn default() -> Self {
+        GameLoop::new(GameLoop::DEFAULT_TICK_RATE)
+    }
+}
+impl GameLoop {
+    pub const DEFAULT_TICK_RATE: u32 = 60;
+    // po dlugiej przerwie (np. przeciaganie okna) symulacja nie nadrabia wiecej niz tyle
+    const MAX_FRAME_TIME: f32 = 0.25;
+
+    pub fn new(tick_rate: u32) -> Self{
+        GameLoop{
+            tick_duration: 1.0/tick_rate.max(1) as f32,
+            accumulator: 0.0,
+            tick: 0,
+            frames: 0
+        }
+    }
+    pub fn get_tick_duration(&self) -> f32{
+        self.tick_duration
+    }
+    pub fn get_tick(&self) -> u64{
+        self.tick
+    }
+    pub fn get_frames(&self) -> u64{
+        self.frames
+    }
+    // ulamek kroku, ktory minal od ostatniego ticka, do interpolacji przy renderowaniu
+    pub fn get_alpha(&self) -> f32{
+        self.accumulator/self.tick_duration
+    }
+    // wywolywane raz na klatke z czasem jej trwania, zwraca alpha
+    pub fn advance(&mut self, frame_time: f32, simulation: &mut impl Simulation) -> f32{
+        self.frames += 1;
+        self.accumulator += frame_time.clamp(0.0, GameLoop::MAX_FRAME_TIME);
+        while self.accumulator >= self.tick_duration{
+            simulation.tick(self.tick, self.tick_duration);
+            self.tick += 1;
+            self.accumulator -= self.tick_duration;
+        }
+        self.get_alpha()
+    }
+    // tryb bez okna: wykonuje podana liczbe krokow tak szybko jak sie da, zwraca czas w sekundach
+    pub fn run_headless(&mut self, ticks: u64, simulation: &mut impl Simulation) -> f32{
+        let start = Instant::now();
+        for _ in 0..ticks{
+            simulation.tick(self.tick, self.tick_duration);
+            self.tick += 1;
+        }
+        start.elapsed().as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk_master::MemoryWorld, math::Vec3};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter{
+        ticks: Vec<u64>,
+        time: f32
+    }
+    impl Simulation for Counter {
+        fn tick(&mut self, tick: u64, delta_time: f32){
+            self.ticks.push(tick);
+            self.time += delta_time;
+        }
+    }
+
+    #[test]
+    fn ticks_are_independent_of_frame_rate(){
+        let mut fast = Counter::default();
+        let mut game_loop = GameLoop::new(20);
+        for _ in 0..100{
+            game_loop.advance(0.01, &mut fast);
+        }
+        let mut slow = Counter::default();
+        let mut other_loop = GameLoop::new(20);
+        for _ in 0..10{
+            other_loop.advance(0.1, &mut slow);
+        }
+        // 1 sekunda przy 20 tickach na sekunde, zaokraglenia moga zgubic ostatni tick
+        assert!((19..=20).contains(&fast.ticks.len()));
+        assert!((19..=20).contains(&slow.ticks.len()));
+        assert!((fast.time-slow.time).abs() <= 0.05+1e-4);
+        assert_eq!(fast.ticks, (0..fast.ticks.len() as u64).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn default_tick_rate_matches_move_sequences(){
+        // numery ruchow wysylanych na serwer to numery krokow petli
+        assert_eq!(GameLoop::DEFAULT_TICK_RATE, crate::protocol::MOVE_SEQUENCE_RATE);
+    }
+
+    #[test]
+    fn alpha_is_the_leftover_fraction_of_a_tick(){
+        let mut counter = Counter::default();
+        let mut game_loop = GameLoop::new(10);
+        let alpha = game_loop.advance(0.125, &mut counter);
+        assert_eq!(counter.ticks.len(), 1);
+        assert!((alpha-0.25).abs() < 1e-4);
+
+        // dluga klatka jest przycinana
+        game_loop.advance(10.0, &mut counter);
+        assert!(counter.ticks.len() <= 4);
+    }
+
+    #[test]
+    fn headless_player_falls_onto_the_ground(){
+        let world = MemoryWorld::default();
+        world.fill(Coord3::ZERO, Coord3::new(9, 0, 9), BlockType::Stone);
+        let mut player = Player::new();
+        player.toggle_flying();
+        player.set_position(Vec3::new(5.0, 10.0, 5.0));
+        let mut game = GameState::new(world, player, InputState::default());
+
+        let mut game_loop = GameLoop::default();
+        game_loop.run_headless(120, &mut game);
+        assert_eq!(game_loop.get_tick(), 120);
+        assert!(game.player.is_on_ground());
+        assert!((game.player.get_position().y-0.5-Player::EYE_HEIGHT).abs() < 1e-3);
+    }
+
+    #[test]
+    fn chunks_follow_the_player_within_render_distance(){
+        let mut game = GameState::new(MemoryWorld::default(), Player::new(), InputState::default());
+        let generator = WorldGenerator::new(7);
+        assert_eq!(game.set_render_distance(0), ChunkLoader::MIN_LOAD_DISTANCE);
+        let (loaded, unloaded) = game.stream_chunks(&generator, None, 3).unwrap();
+        assert_eq!((loaded.len(), unloaded.len()), (3, 0));
+        let (loaded, _) = game.stream_chunks(&generator, None, 100).unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(game.world.get_chunk_positions().len(), 7);
+
+        // dwa chunki dalej zostaje tylko wspolny chunk (1, 0, 0)
+        game.player.set_position(Vec3::new(2.5*Chunk::CHUNK_SIZE as f32, 10.0, 10.0));
+        let (loaded, unloaded) = game.stream_chunks(&generator, None, 100).unwrap();
+        assert_eq!((loaded.len(), unloaded.len()), (6, 6));
+        assert!(game.world.chunk_exists(Coord3::new(1, 0, 0)));
+        assert!(!game.world.chunk_exists(Coord3::ZERO));
+    }
+
+    #[test]
+    fn breaking_a_block_lets_water_flow_on_tick(){
+        let world = MemoryWorld::with_blocks([
+            (Coord3::new(5, 5, 5), BlockType::Water),
+            (Coord3::new(5, 4, 5), BlockType::Stone)
+        ]);
+        let mut game = GameState::new(world, Player::new(), InputState::default());
+
+        assert!(game.set_block(Coord3::new(5, 4, 5), BlockType::Air));
+        assert_eq!(game.take_changed_blocks(), vec![Coord3::new(5, 4, 5)]);
+        GameLoop::default().run_headless(3, &mut game);
+        assert_eq!(game.world.get_voxel(Coord3::new(5, 3, 5)), BlockType::Water);
+        assert!(game.take_changed_blocks().contains(&Coord3::new(5, 4, 5)));
+    }
+}
@@ -1,8 +1,12 @@
-use crate::{block::BlockType, world_generator::WorldGenerator, Coord3};
+use std::collections::HashMap;
 
+use crate::{block::BlockType, chunk::Chunk, world_generator::WorldGenerator, math::Coord3};
+
+#[derive(Default)]
 pub enum OctaHyperChunk<T> {
+    #[default]
     None,
-    Solid(T), 
+    Solid(T),
     SubChunk(SubOHCChunk<T>)
 }
 pub struct SubOHCChunk<T>{
@@ -12,68 +16,184 @@ pub struct SubOHCChunk<T>{
 
 pub trait VoxelWorld {
     fn get_block_type(&self, coord: Coord3) -> BlockType;
-    //fn set_block_type(&mut self, coord: Coord3, block: BlockType);
+    fn set_block_type(&mut self, coord: Coord3, block: BlockType) -> Result<(), String>;
     fn generate_world(&mut self, coord: Coord3, generator: &WorldGenerator);
 }
 
-impl VoxelWorld for OctaHyperChunk<BlockType>{
-    fn get_block_type(&self, coord: Coord3) -> BlockType{
+// wspolrzedne przekazywane do wezla sa wzgledne wzgledem jego rogu, level to log2 boku wezla
+impl<T: Copy + PartialEq> OctaHyperChunk<T> {
+    #[inline(always)]
+    fn get_leaf_index(coord: Coord3, level: i32) -> usize{
+        let half = 1<<(level-1);
+        coord.div_euclid(half).mod_euclid(2).bin_hash() as usize
+    }
+    #[inline(always)]
+    fn get_leaf_offset(index: usize) -> Coord3{
+        Coord3::new((index>>2&1) as i32, (index>>1&1) as i32, (index&1) as i32)
+    }
+
+    pub fn get(&self, coord: Coord3) -> Option<T>{
         match self{
-            OctaHyperChunk::Solid(block) => {
-                return *block;
+            OctaHyperChunk::Solid(value) => Some(*value),
+            OctaHyperChunk::SubChunk(sub_chunk) => {
+                let index = OctaHyperChunk::<T>::get_leaf_index(coord, sub_chunk.level);
+                sub_chunk.leafs[index].get(coord)
             },
-            OctaHyperChunk::SubChunk(sub_chunk) =>{
-                let new_coord = coord.div_euclid(2); 
-                let index = coord.mod_euclid(2).bin_hash() as usize;
-                let leaf = &sub_chunk.leafs[index];
-                return leaf.get_block_type(new_coord);
+            OctaHyperChunk::None => None
+        }
+    }
+
+    pub fn set(&mut self, level: i32, coord: Coord3, value: Option<T>){
+        if level == 0{
+            *self = match value{
+                Some(value) => OctaHyperChunk::Solid(value),
+                None => OctaHyperChunk::None
+            };
+            return;
+        }
+        let current = match self{
+            OctaHyperChunk::Solid(current) => Some(*current),
+            OctaHyperChunk::None => None,
+            OctaHyperChunk::SubChunk(_) => value
+        };
+        if !matches!(self, OctaHyperChunk::SubChunk(_)){
+            if current == value{
+                return;
+            }
+            // rozbicie jednolitego liscia na 8 takich samych dzieci
+            *self = OctaHyperChunk::SubChunk(SubOHCChunk{
+                level,
+                leafs: Box::new(std::array::from_fn(|_| match current{
+                    Some(current) => OctaHyperChunk::Solid(current),
+                    None => OctaHyperChunk::None
+                }))
+            });
+        }
+        if let OctaHyperChunk::SubChunk(sub_chunk) = self{
+            let index = OctaHyperChunk::<T>::get_leaf_index(coord, level);
+            sub_chunk.leafs[index].set(level-1, coord, value);
+        }
+        self.collapse();
+    }
+
+    // zamienia wezel na lisc, gdy wszystkie dzieci sa takimi samymi liscmi
+    fn collapse(&mut self){
+        let collapsed = match self{
+            OctaHyperChunk::SubChunk(sub_chunk) => {
+                let leafs = &sub_chunk.leafs;
+                match &leafs[0]{
+                    OctaHyperChunk::None if leafs.iter().all(|leaf| matches!(leaf, OctaHyperChunk::None)) => {
+                        Some(OctaHyperChunk::None)
+                    },
+                    OctaHyperChunk::Solid(value) if leafs.iter().all(|leaf| matches!(leaf, OctaHyperChunk::Solid(other) if other == value)) => {
+                        Some(OctaHyperChunk::Solid(*value))
+                    },
+                    _ => None
+                }
             },
-            OctaHyperChunk::None =>{
-                return BlockType::Air;
+            _ => None
+        };
+        if let Some(collapsed) = collapsed{
+            *self = collapsed;
+        }
+    }
+
+    // buduje drzewo o boku 2^level komorek, jednolite galezie sa od razu zwijane
+    pub fn build(level: i32, sample: &impl Fn(Coord3) -> Option<T>) -> Self{
+        OctaHyperChunk::build_node(level, Coord3::ZERO, sample)
+    }
+    fn build_node(level: i32, origin: Coord3, sample: &impl Fn(Coord3) -> Option<T>) -> Self{
+        if level == 0{
+            return match sample(origin){
+                Some(value) => OctaHyperChunk::Solid(value),
+                None => OctaHyperChunk::None
+            };
+        }
+        let half = 1<<(level-1);
+        let leafs: [OctaHyperChunk<T>; 8] = std::array::from_fn(|index|{
+            let offset = OctaHyperChunk::<T>::get_leaf_offset(index);
+            OctaHyperChunk::build_node(level-1, origin+offset*half, sample)
+        });
+        let mut node = OctaHyperChunk::SubChunk(SubOHCChunk{
+            level,
+            leafs: Box::new(leafs)
+        });
+        node.collapse();
+        node
+    }
+
+    pub fn get_stats(&self, level: i32) -> OctreeStats{
+        let mut stats = OctreeStats{
+            dense_bytes: (1usize<<(3*level))*std::mem::size_of::<T>(),
+            bytes: std::mem::size_of::<OctaHyperChunk<T>>(),
+            ..Default::default()
+        };
+        self.collect_stats(0, &mut stats);
+        stats
+    }
+    fn collect_stats(&self, depth: i32, stats: &mut OctreeStats){
+        stats.nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        match self{
+            OctaHyperChunk::None => stats.empty_leaves += 1,
+            OctaHyperChunk::Solid(_) => stats.solid_leaves += 1,
+            OctaHyperChunk::SubChunk(sub_chunk) => {
+                stats.branches += 1;
+                stats.bytes += std::mem::size_of::<[OctaHyperChunk<T>; 8]>();
+                for leaf in sub_chunk.leafs.iter(){
+                    leaf.collect_stats(depth+1, stats);
+                }
             }
         }
-    } 
-    // fn set_block_type(&mut self, coord: Coord3, block: BlockType){
-    //     match self{
-    //         OctaHyperChunk::Solid(block) => {
-    //             return *block;
-    //         },
-    //         OctaHyperChunk::SubChunk(sub_chunk) =>{
-    //             let new_coord = coord.div_euclid(2); 
-    //             let index = coord.mod_euclid(2).bin_hash() as usize;
-    //             let leaf = &sub_chunk.leafs[index];
-    //             return leaf.get_block_type(new_coord);
-    //         },
-    //         OctaHyperChunk::None =>{
-    //             return BlockType::Air;
-    //         }
-    //     }
-    // } 
-    fn generate_world(&mut self, coord: Coord3, generator: &WorldGenerator){
-        let blocks = generator.generate_world();
-        for block in blocks{
-
-        }
-        // match self{
-        //     OctaHyperChunk::Solid(block) => {
-        //         return *block;
-        //     },
-        //     OctaHyperChunk::SubChunk(sub_chunk) =>{
-        //         let new_coord = coord.div_euclid(2); 
-        //         let index = coord.mod_euclid(2).bin_hash() as usize;
-        //         let leaf = &sub_chunk.leafs[index];
-        //         return leaf.get_block_type(new_coord, generator);
-        //     },
-        //     OctaHyperChunk::None =>{
-        //         return BlockType::Air;
-        //     }
-        // }
-    } 
+    }
+
+    pub fn iter_solid_leaves(&self, level: i32) -> SolidLeafIterator<'_, T>{
+        SolidLeafIterator{
+            stack: vec![(self, Coord3::ZERO, level)]
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OctreeStats{
+    pub nodes: usize,
+    pub branches: usize,
+    pub solid_leaves: usize,
+    pub empty_leaves: usize,
+    pub max_depth: i32,
+    // pamiec zajmowana przez wezly drzewa
+    pub bytes: usize,
+    // pamiec tablicy wokseli o tym samym rozmiarze
+    pub dense_bytes: usize
+}
+
+// zwraca (rog liscia, log2 boku liscia, wartosc)
+pub struct SolidLeafIterator<'a, T>{
+    stack: Vec<(&'a OctaHyperChunk<T>, Coord3, i32)>
+}
+impl<'a, T: Copy + PartialEq> Iterator for SolidLeafIterator<'a, T> {
+    type Item = (Coord3, i32, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, origin, level)) = self.stack.pop(){
+            match node{
+                OctaHyperChunk::Solid(value) => return Some((origin, level, *value)),
+                OctaHyperChunk::None => {},
+                OctaHyperChunk::SubChunk(sub_chunk) => {
+                    let half = 1<<(level-1);
+                    for (index, leaf) in sub_chunk.leafs.iter().enumerate().rev(){
+                        let offset = OctaHyperChunk::<T>::get_leaf_offset(index);
+                        self.stack.push((leaf, origin+offset*half, level-1));
+                    }
+                }
+            }
+        }
+        None
+    }
 }
 
 impl OctaHyperChunk<BlockType> {
     pub fn new() -> Self{
-        OctaHyperChunk::None
+        OctaHyperChunk::default()
     }
     pub fn get_face(&self, coord: Coord3, world :&impl VoxelWorld) -> u8{
         let mut mesh_type_mask: u8 = 0b00000000;
@@ -85,10 +205,285 @@ impl OctaHyperChunk<BlockType> {
         for i in 0..6{
             let pos = coord+neighbors[i];
             let block_type = world.get_block_type(pos);
-            if !(block_type == BlockType::Air || (current_block != BlockType::Water && block_type == BlockType::Water)){
+            if !current_block.is_face_visible(block_type){
                 mesh_type_mask |= 1<<i;
             }
         }
         mesh_type_mask
-    } 
+    }
+}
+
+// drzewo osadzone w swiecie: szescian o rogu origin i boku 2^level wokseli
+pub struct VoxelOctree{
+    origin: Coord3,
+    level: i32,
+    root: OctaHyperChunk<BlockType>,
+    // poziomy plynacej wody jak w chunku, zrodla nie maja wpisu
+    fluid_levels: HashMap<Coord3, u8>
+}
+
+impl VoxelOctree {
+    pub fn new(origin: Coord3, level: i32) -> Self{
+        VoxelOctree{
+            origin,
+            level,
+            root: OctaHyperChunk::None,
+            fluid_levels: HashMap::new()
+        }
+    }
+    pub fn from_fn(origin: Coord3, level: i32, sample: impl Fn(Coord3) -> BlockType) -> Self{
+        VoxelOctree{
+            origin,
+            level,
+            root: OctaHyperChunk::build(level, &|local: Coord3|{
+                match sample(origin+local){
+                    BlockType::Air => None,
+                    block => Some(block)
+                }
+            }),
+            fluid_levels: HashMap::new()
+        }
+    }
+    pub fn from_chunk(chunk: &Chunk) -> Self{
+        let size = Chunk::CHUNK_SIZE as i32;
+        let mut level = 0;
+        while (1<<level) < size{
+            level += 1;
+        }
+        let mut octree = VoxelOctree::from_fn(chunk.get_world_position(Coord3::ZERO), level, |world_position|{
+            let local = world_position-chunk.get_world_position(Coord3::ZERO);
+            if Chunk::is_outer(local) {BlockType::Air} else {chunk.get_voxel(local)}
+        });
+        for index in chunk.get_voxels(){
+            let local = Chunk::get_local_position_from_index(*index);
+            let level = chunk.get_fluid_level(local);
+            if level > 0 && level < Chunk::SOURCE_FLUID_LEVEL{
+                octree.fluid_levels.insert(chunk.get_world_position(local), level);
+            }
+        }
+        octree
+    }
+    pub fn to_chunk(&self, chunk_position: Coord3) -> Chunk{
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(chunk_position);
+        let chunk_origin = chunk.get_world_position(Coord3::ZERO);
+        let size = Chunk::CHUNK_SIZE as i32;
+        for (leaf_origin, leaf_level, block) in self.iter_solid_leaves(){
+            let leaf_size = 1<<leaf_level;
+            let min = leaf_origin-chunk_origin;
+            for x in min.x.max(0)..(min.x+leaf_size).min(size){
+                for y in min.y.max(0)..(min.y+leaf_size).min(size){
+                    for z in min.z.max(0)..(min.z+leaf_size).min(size){
+                        chunk.set_voxel(Coord3::new(x, y, z), block);
+                    }
+                }
+            }
+        }
+        for (position, level) in self.fluid_levels.iter(){
+            let local = *position-chunk_origin;
+            if !Chunk::is_outer(local){
+                chunk.set_fluid(local, *level);
+            }
+        }
+        chunk
+    }
+    // 0 dla blokow, ktore nie sa woda
+    pub fn get_fluid_level(&self, coord: Coord3) -> u8{
+        if self.get_block_type(coord) != BlockType::Water{
+            return 0;
+        }
+        self.fluid_levels.get(&coord).copied().unwrap_or(Chunk::SOURCE_FLUID_LEVEL)
+    }
+    // ustawia wode o danym poziomie, od 1 do SOURCE_FLUID_LEVEL
+    pub fn set_fluid(&mut self, coord: Coord3, level: u8) -> Result<(), String>{
+        self.set_block_type(coord, BlockType::Water)?;
+        if level >= Chunk::SOURCE_FLUID_LEVEL{
+            self.fluid_levels.remove(&coord);
+        }
+        else{
+            self.fluid_levels.insert(coord, level.max(1));
+        }
+        Ok(())
+    }
+    pub fn get_origin(&self) -> Coord3{
+        self.origin
+    }
+    pub fn get_size(&self) -> i32{
+        1<<self.level
+    }
+    pub fn is_inside(&self, coord: Coord3) -> bool{
+        let local = coord-self.origin;
+        let size = self.get_size();
+        local.x >= 0 && local.y >= 0 && local.z >= 0 && local.x < size && local.y < size && local.z < size
+    }
+    pub fn is_empty(&self) -> bool{
+        matches!(self.root, OctaHyperChunk::None)
+    }
+    pub fn remove_block(&mut self, coord: Coord3) -> Result<(), String>{
+        self.set_block_type(coord, BlockType::Air)
+    }
+    // liscie w swiecie: (rog, log2 boku, blok)
+    pub fn iter_solid_leaves(&self) -> impl Iterator<Item = (Coord3, i32, BlockType)> + '_{
+        let origin = self.origin;
+        self.root.iter_solid_leaves(self.level).map(move |(local, level, block)| (origin+local, level, block))
+    }
+    pub fn get_stats(&self) -> OctreeStats{
+        self.root.get_stats(self.level)
+    }
+}
+
+impl VoxelWorld for VoxelOctree{
+    fn get_block_type(&self, coord: Coord3) -> BlockType{
+        if !self.is_inside(coord){
+            return BlockType::Air;
+        }
+        self.root.get(coord-self.origin).unwrap_or(BlockType::Air)
+    }
+    fn set_block_type(&mut self, coord: Coord3, block: BlockType) -> Result<(), String>{
+        if !self.is_inside(coord){
+            return Err(format!("{} is outside of the octree", coord));
+        }
+        let value = if block == BlockType::Air {None} else {Some(block)};
+        self.root.set(self.level, coord-self.origin, value);
+        if block != BlockType::Water{
+            self.fluid_levels.remove(&coord);
+        }
+        Ok(())
+    }
+    fn generate_world(&mut self, coord: Coord3, generator: &WorldGenerator){
+        *self = VoxelOctree::from_fn(coord, self.level, |world_position| generator.get_voxel_type(world_position));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Rng;
+
+    use super::*;
+
+    const BLOCKS: [BlockType; 4] = [BlockType::Air, BlockType::Stone, BlockType::Dirt, BlockType::Water];
+
+    fn dense_index(local: Coord3, size: i32) -> usize{
+        ((local.x*size+local.y)*size+local.z) as usize
+    }
+
+    fn assert_collapsed(node: &OctaHyperChunk<BlockType>){
+        if let OctaHyperChunk::SubChunk(sub_chunk) = node{
+            let first = sub_chunk.leafs[0].get(Coord3::ZERO);
+            let uniform = sub_chunk.leafs.iter().all(|leaf| !matches!(leaf, OctaHyperChunk::SubChunk(_)) && leaf.get(Coord3::ZERO) == first);
+            assert!(!uniform, "uniform branch was not collapsed");
+            sub_chunk.leafs.iter().for_each(assert_collapsed);
+        }
+    }
+
+    #[test]
+    fn random_edits_match_dense_array(){
+        for seed in 1..20u64{
+            let mut rng = Rng::new(seed);
+            let level = 1+(seed%4) as i32;
+            let size = 1<<level;
+            let origin = Coord3::new(rng.range(100)-50, rng.range(100)-50, rng.range(100)-50);
+            let mut octree = VoxelOctree::new(origin, level);
+            let mut dense = vec![BlockType::Air; (size*size*size) as usize];
+            for _ in 0..400{
+                let local = Coord3::new(rng.range(size), rng.range(size), rng.range(size));
+                // czesto te same bloki, zeby galezie sie zwijaly
+                let kinds = if rng.range(3) == 0 {4} else {2};
+                let block = BLOCKS[rng.range(kinds) as usize];
+                octree.set_block_type(origin+local, block).unwrap();
+                dense[dense_index(local, size)] = block;
+            }
+            assert_collapsed(&octree.root);
+            let mut solid_count = 0;
+            for x in 0..size{
+                for y in 0..size{
+                    for z in 0..size{
+                        let local = Coord3::new(x, y, z);
+                        assert_eq!(octree.get_block_type(origin+local), dense[dense_index(local, size)]);
+                        if dense[dense_index(local, size)] != BlockType::Air{
+                            solid_count += 1;
+                        }
+                    }
+                }
+            }
+            let leaves_volume: i32 = octree.iter_solid_leaves().map(|(_, level, _)| 1<<(3*level)).sum();
+            assert_eq!(leaves_volume, solid_count);
+            for (leaf_origin, leaf_level, block) in octree.iter_solid_leaves(){
+                assert!(octree.is_inside(leaf_origin));
+                assert_eq!(dense[dense_index(leaf_origin-origin, size)], block);
+                assert!(leaf_level <= level);
+            }
+            let stats = octree.get_stats();
+            assert_eq!(stats.nodes, stats.branches*8+1);
+            assert_eq!(stats.solid_leaves, octree.iter_solid_leaves().count());
+        }
+    }
+
+    #[test]
+    fn filling_and_clearing_collapses_to_a_single_leaf(){
+        let mut octree = VoxelOctree::new(Coord3::new(-4, -4, -4), 2);
+        for x in -4..0{
+            for y in -4..0{
+                for z in -4..0{
+                    octree.set_block_type(Coord3::new(x, y, z), BlockType::Stone).unwrap();
+                }
+            }
+        }
+        assert_eq!(octree.get_stats().nodes, 1);
+        assert!(octree.get_stats().bytes < octree.get_stats().dense_bytes);
+        assert_eq!(octree.iter_solid_leaves().collect::<Vec<_>>().len(), 1);
+        octree.remove_block(Coord3::new(-1, -2, -3)).unwrap();
+        assert_eq!(octree.get_block_type(Coord3::new(-1, -2, -3)), BlockType::Air);
+        assert_eq!(octree.get_stats().branches, 2);
+        octree.set_block_type(Coord3::new(-1, -2, -3), BlockType::Stone).unwrap();
+        assert_eq!(octree.get_stats().nodes, 1);
+    }
+
+    #[test]
+    fn coordinates_outside_bounds(){
+        let mut octree = VoxelOctree::new(Coord3::new(10, 0, 0), 3);
+        assert!(octree.set_block_type(Coord3::new(9, 0, 0), BlockType::Stone).is_err());
+        assert!(octree.set_block_type(Coord3::new(18, 0, 0), BlockType::Stone).is_err());
+        octree.set_block_type(Coord3::new(17, 7, 0), BlockType::Stone).unwrap();
+        assert_eq!(octree.get_block_type(Coord3::new(17, 7, 0)), BlockType::Stone);
+        // poza drzewem zawsze powietrze, bez zawijania wspolrzednych
+        assert_eq!(octree.get_block_type(Coord3::new(25, 7, 0)), BlockType::Air);
+    }
+
+    #[test]
+    fn chunk_round_trip(){
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(Coord3::new(1, -1, 2));
+        let mut rng = Rng::new(42);
+        for _ in 0..2000{
+            let local = Coord3::new(rng.range(50), rng.range(50), rng.range(50));
+            chunk.set_voxel(local, BLOCKS[1+rng.range(3) as usize]);
+        }
+        chunk.set_fluid(Coord3::new(3, 4, 5), 3);
+        chunk.set_fluid(Coord3::new(49, 0, 0), 1);
+        let octree = VoxelOctree::from_chunk(&chunk);
+        assert_eq!(octree.get_origin(), Coord3::new(50, -50, 100));
+        let restored = octree.to_chunk(chunk.get_chunk_position());
+        for index in 0..Chunk::CHUNK_SIZE.pow(3){
+            assert_eq!(restored.get_voxel_from_index(index), chunk.get_voxel_from_index(index));
+        }
+        assert_eq!(restored.get_voxels().len(), chunk.get_voxels().len());
+        for index in chunk.get_voxels(){
+            let local = Chunk::get_local_position_from_index(*index);
+            assert_eq!(restored.get_fluid_level(local), chunk.get_fluid_level(local));
+        }
+        assert_eq!(octree.get_fluid_level(Coord3::new(53, -46, 105)), 3);
+    }
+
+    #[test]
+    fn replacing_water_clears_its_level(){
+        let mut octree = VoxelOctree::new(Coord3::ZERO, 2);
+        octree.set_fluid(Coord3::new(1, 1, 1), 4).unwrap();
+        assert_eq!(octree.get_fluid_level(Coord3::new(1, 1, 1)), 4);
+        octree.set_block_type(Coord3::new(1, 1, 1), BlockType::Stone).unwrap();
+        assert_eq!(octree.get_fluid_level(Coord3::new(1, 1, 1)), 0);
+        octree.set_block_type(Coord3::new(1, 1, 1), BlockType::Water).unwrap();
+        assert_eq!(octree.get_fluid_level(Coord3::new(1, 1, 1)), Chunk::SOURCE_FLUID_LEVEL);
+        assert!(octree.set_fluid(Coord3::new(4, 0, 0), 2).is_err());
+    }
 }
@@ -0,0 +1,324 @@
+use std::{collections::{HashMap, HashSet}, fmt::Display, fs};
+
+use glutin::event::{MouseButton, VirtualKeyCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Break,
+    Place,
+    ToggleCursor,
+    ToggleFlying,
+    ToggleOcclusionCulling,
+    SpawnEntity,
+    SpawnMob,
+    OpenConsole,
+    // numer slotu paska, od 0
+    SelectSlot(usize)
+}
+impl Action {
+    pub const HOTBAR_SLOTS: usize = 9;
+    const NAMED: [Action; 13] = [
+        Action::MoveForward, Action::MoveBack, Action::MoveLeft, Action::MoveRight, Action::Jump,
+        Action::Break, Action::Place, Action::ToggleCursor, Action::ToggleFlying, Action::ToggleOcclusionCulling,
+        Action::SpawnEntity, Action::SpawnMob, Action::OpenConsole
+    ];
+    pub fn parse(name: &str) -> Option<Action>{
+        if let Some(slot) = name.strip_prefix("SelectSlot"){
+            return match slot.parse::<usize>() {
+                Ok(slot) if (1..=Action::HOTBAR_SLOTS).contains(&slot) => Some(Action::SelectSlot(slot-1)),
+                _ => None
+            };
+        }
+        Action::NAMED.into_iter().find(|action| action.to_string() == name)
+    }
+}
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // w pliku sloty sa numerowane od 1, jak klawisze
+            Action::SelectSlot(slot) => write!(f, "SelectSlot{}", slot+1),
+            _ => write!(f, "{:?}", self)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputKey {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton)
+}
+impl InputKey {
+    // klawisze, ktore mozna wpisac w pliku z przypisaniami
+    const KEYS: [VirtualKeyCode; 62] = [
+        VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D, VirtualKeyCode::E,
+        VirtualKeyCode::F, VirtualKeyCode::G, VirtualKeyCode::H, VirtualKeyCode::I, VirtualKeyCode::J,
+        VirtualKeyCode::K, VirtualKeyCode::L, VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O,
+        VirtualKeyCode::P, VirtualKeyCode::Q, VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T,
+        VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X, VirtualKeyCode::Y,
+        VirtualKeyCode::Z,
+        VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3, VirtualKeyCode::Key4,
+        VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+        VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3, VirtualKeyCode::F4, VirtualKeyCode::F5,
+        VirtualKeyCode::F6, VirtualKeyCode::F7, VirtualKeyCode::F8, VirtualKeyCode::F9, VirtualKeyCode::F10,
+        VirtualKeyCode::F11, VirtualKeyCode::F12,
+        VirtualKeyCode::Space, VirtualKeyCode::Tab, VirtualKeyCode::Escape, VirtualKeyCode::Return, VirtualKeyCode::Back,
+        VirtualKeyCode::LShift, VirtualKeyCode::RShift, VirtualKeyCode::LControl, VirtualKeyCode::RControl,
+        VirtualKeyCode::LAlt, VirtualKeyCode::Up, VirtualKeyCode::Down, VirtualKeyCode::Left, VirtualKeyCode::Right
+    ];
+    pub fn parse(name: &str) -> Option<InputKey>{
+        match name {
+            "MouseLeft" => Some(InputKey::Mouse(MouseButton::Left)),
+            "MouseRight" => Some(InputKey::Mouse(MouseButton::Right)),
+            "MouseMiddle" => Some(InputKey::Mouse(MouseButton::Middle)),
+            _ => InputKey::KEYS.into_iter()
+                .find(|key| format!("{:?}", key) == name)
+                .map(InputKey::Key)
+        }
+    }
+}
+impl Display for InputKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputKey::Key(key) => write!(f, "{:?}", key),
+            InputKey::Mouse(MouseButton::Other(button)) => write!(f, "Mouse{}", button),
+            InputKey::Mouse(button) => write!(f, "Mouse{:?}", button)
+        }
+    }
+}
+
+// przypisania klawiszy do akcji, jedna akcja moze miec kilka klawiszy
+#[derive(Clone)]
+pub struct Bindings{
+    bindings: HashMap<InputKey, Action>
+}
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Bindings{bindings: HashMap::new()};
+        bindings.bind(InputKey::Key(VirtualKeyCode::W), Action::MoveForward);
+        bindings.bind(InputKey::Key(VirtualKeyCode::S), Action::MoveBack);
+        bindings.bind(InputKey::Key(VirtualKeyCode::A), Action::MoveLeft);
+        bindings.bind(InputKey::Key(VirtualKeyCode::D), Action::MoveRight);
+        bindings.bind(InputKey::Key(VirtualKeyCode::Space), Action::Jump);
+        bindings.bind(InputKey::Mouse(MouseButton::Left), Action::Break);
+        bindings.bind(InputKey::Mouse(MouseButton::Right), Action::Place);
+        bindings.bind(InputKey::Key(VirtualKeyCode::E), Action::ToggleCursor);
+        bindings.bind(InputKey::Key(VirtualKeyCode::F), Action::ToggleFlying);
+        bindings.bind(InputKey::Key(VirtualKeyCode::O), Action::ToggleOcclusionCulling);
+        bindings.bind(InputKey::Key(VirtualKeyCode::Q), Action::SpawnEntity);
+        bindings.bind(InputKey::Key(VirtualKeyCode::M), Action::SpawnMob);
+        bindings.bind(InputKey::Key(VirtualKeyCode::T), Action::OpenConsole);
+        let digits = [
+            VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3, VirtualKeyCode::Key4, VirtualKeyCode::Key5,
+            VirtualKeyCode::Key6, VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9
+        ];
+        for (slot, key) in digits.into_iter().enumerate(){
+            bindings.bind(InputKey::Key(key), Action::SelectSlot(slot));
+        }
+        bindings
+    }
+}
+impl Bindings {
+    pub const FILE: &'static str = "bindings.txt";
+
+    pub fn empty() -> Self{
+        Bindings{bindings: HashMap::new()}
+    }
+    pub fn bind(&mut self, key: InputKey, action: Action){
+        self.bindings.insert(key, action);
+    }
+    pub fn get_action(&self, key: InputKey) -> Option<Action>{
+        self.bindings.get(&key).copied()
+    }
+    pub fn get_keys(&self, action: Action) -> Vec<InputKey>{
+        let mut keys: Vec<InputKey> = self.bindings.iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(key, _)| *key)
+            .collect();
+        keys.sort_by_key(|key| key.to_string());
+        keys
+    }
+    // linie "Akcja = Klawisz", # zaczyna komentarz
+    pub fn parse(text: &str) -> Result<Bindings, String>{
+        let mut bindings = Bindings::empty();
+        for (number, line) in text.lines().enumerate(){
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty(){
+                continue;
+            }
+            let (action, key) = line.split_once('=')
+                .ok_or(format!("line {}: expected 'Action = Key'", number+1))?;
+            let action = Action::parse(action.trim())
+                .ok_or(format!("line {}: unknown action '{}'", number+1, action.trim()))?;
+            let key = InputKey::parse(key.trim())
+                .ok_or(format!("line {}: unknown key '{}'", number+1, key.trim()))?;
+            bindings.bind(key, action);
+        }
+        Ok(bindings)
+    }
+    // brak pliku to domyslne przypisania, bledny plik jest zglaszany
+    pub fn load(path: &str) -> Result<Bindings, String>{
+        match fs::read_to_string(path) {
+            Ok(text) => Bindings::parse(&text).map_err(|error| format!("{path}: {error}")),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Bindings::default()),
+            Err(error) => Err(format!("{path}: {error}"))
+        }
+    }
+    // dla okien: bledny plik jest zglaszany, a gra startuje z domyslnymi klawiszami
+    pub fn load_or_default(path: &str) -> Bindings{
+        Bindings::load(path).unwrap_or_else(|error|{
+            println!("{error}, using default bindings");
+            Bindings::default()
+        })
+    }
+}
+impl Display for Bindings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines: Vec<String> = self.bindings.iter()
+            .map(|(key, action)| format!("{action} = {key}"))
+            .collect();
+        lines.sort();
+        for line in lines{
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+// stan wejscia wspolny dla petli renderujacych, zdarzenia okna sa tlumaczone na akcje
+pub struct InputState{
+    bindings: Bindings,
+    // wcisniete klawisze; akcja z kilkoma klawiszami jest trzymana, dopoki trzymany jest ktorykolwiek
+    held_keys: HashSet<InputKey>,
+    // akcje ustawione wprost przez set_held, bez klawisza
+    held: HashSet<Action>,
+    triggered: Vec<Action>
+}
+impl Default for InputState {
+    fn default() -> Self {
+        InputState::new(Bindings::default())
+    }
+}
+impl InputState {
+    pub fn new(bindings: Bindings) -> Self{
+        InputState{
+            bindings,
+            held_keys: HashSet::new(),
+            held: HashSet::new(),
+            triggered: Vec::new()
+        }
+    }
+    // powtorzenia klawisza trzymanego w dol nie wyzwalaja akcji ponownie
+    pub fn handle(&mut self, key: InputKey, pressed: bool){
+        let Some(action) = self.bindings.get_action(key) else {
+            return;
+        };
+        let was_held = self.is_held(action);
+        if pressed{
+            self.held_keys.insert(key);
+            if !was_held{
+                self.triggered.push(action);
+            }
+        }
+        else{
+            self.held_keys.remove(&key);
+        }
+    }
+    // puszczenie akcji puszcza tez wszystkie jej klawisze
+    pub fn set_held(&mut self, action: Action, held: bool){
+        if held{
+            if !self.is_held(action){
+                self.triggered.push(action);
+            }
+            self.held.insert(action);
+        }
+        else{
+            self.held.remove(&action);
+            let bindings = &self.bindings;
+            self.held_keys.retain(|key| bindings.get_action(*key) != Some(action));
+        }
+    }
+    pub fn is_held(&self, action: Action) -> bool{
+        self.held.contains(&action) || self.held_keys.iter().any(|key| self.bindings.get_action(*key) == Some(action))
+    }
+    // akcje wcisniete od ostatniego wywolania, w kolejnosci wcisniecia
+    pub fn take_triggered(&mut self) -> Vec<Action>{
+        std::mem::take(&mut self.triggered)
+    }
+    pub fn release_all(&mut self){
+        self.held_keys.clear();
+        self.held.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_survive_round_trip(){
+        let bindings = Bindings::default();
+        let parsed = Bindings::parse(&bindings.to_string()).unwrap();
+        assert_eq!(parsed.to_string(), bindings.to_string());
+        assert_eq!(parsed.get_action(InputKey::Key(VirtualKeyCode::Key3)), Some(Action::SelectSlot(2)));
+        assert_eq!(parsed.get_action(InputKey::Mouse(MouseButton::Right)), Some(Action::Place));
+    }
+
+    #[test]
+    fn shipped_bindings_file_matches_defaults(){
+        let shipped = Bindings::parse(include_str!("../../bindings.txt")).unwrap();
+        assert_eq!(shipped.to_string(), Bindings::default().to_string());
+    }
+
+    #[test]
+    fn bindings_file_is_parsed_with_comments_and_errors(){
+        let text = "# ruch strzalkami\nMoveForward = Up\nMoveForward = W  # oba dzialaja\n\nJump = LShift\n";
+        let bindings = Bindings::parse(text).unwrap();
+        assert_eq!(bindings.get_keys(Action::MoveForward), vec![InputKey::Key(VirtualKeyCode::Up), InputKey::Key(VirtualKeyCode::W)]);
+        assert_eq!(bindings.get_action(InputKey::Key(VirtualKeyCode::LShift)), Some(Action::Jump));
+        assert_eq!(bindings.get_action(InputKey::Key(VirtualKeyCode::Space)), None);
+
+        assert_eq!(Bindings::parse("Jump = Space\nFly = F").err().unwrap(), "line 2: unknown action 'Fly'");
+        assert_eq!(Bindings::parse("Jump = Spacebar").err().unwrap(), "line 1: unknown key 'Spacebar'");
+        assert_eq!(Bindings::parse("SelectSlot10 = Key0").err().unwrap(), "line 1: unknown action 'SelectSlot10'");
+        assert!(Bindings::parse("Jump Space").is_err());
+    }
+
+    #[test]
+    fn held_actions_trigger_once(){
+        let mut input = InputState::default();
+        input.handle(InputKey::Key(VirtualKeyCode::W), true);
+        input.handle(InputKey::Key(VirtualKeyCode::W), true);
+        input.handle(InputKey::Mouse(MouseButton::Left), true);
+        input.handle(InputKey::Key(VirtualKeyCode::Y), true);
+        assert!(input.is_held(Action::MoveForward));
+        assert_eq!(input.take_triggered(), vec![Action::MoveForward, Action::Break]);
+        assert!(input.take_triggered().is_empty());
+
+        input.handle(InputKey::Key(VirtualKeyCode::W), false);
+        assert!(!input.is_held(Action::MoveForward));
+        assert!(input.is_held(Action::Break));
+        input.release_all();
+        assert!(!input.is_held(Action::Break));
+    }
+
+    #[test]
+    fn action_stays_held_while_any_of_its_keys_is_down(){
+        let mut input = InputState::default();
+        input.handle(InputKey::Key(VirtualKeyCode::W), true);
+        input.handle(InputKey::Key(VirtualKeyCode::Up), true);
+        assert_eq!(input.take_triggered(), vec![Action::MoveForward]);
+        input.handle(InputKey::Key(VirtualKeyCode::Up), false);
+        assert!(input.is_held(Action::MoveForward));
+        input.handle(InputKey::Key(VirtualKeyCode::W), false);
+        assert!(!input.is_held(Action::MoveForward));
+
+        input.handle(InputKey::Key(VirtualKeyCode::W), true);
+        input.set_held(Action::MoveForward, false);
+        assert!(!input.is_held(Action::MoveForward));
+    }
+}
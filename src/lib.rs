@@ -1,11 +1,47 @@
-mod chunk;
-mod chunk_loader;
-mod chunk_master;
-mod math;
-mod block;
+pub mod math;
+pub mod chunk;
+pub mod chunk_master;
+pub mod chunk_loader;
+pub mod camera;
+pub mod block;
+pub mod world_generator;
+pub mod player;
+pub mod hyper_chunk;
+pub mod rendering;
+pub mod transform;
+pub mod shadow;
+pub mod mesh;
+pub mod culling;
+pub mod lod;
+pub mod physics;
+pub mod input;
+pub mod game_loop;
+pub mod fluid;
+pub mod block_update;
+pub mod random_tick;
+pub mod entity;
+pub mod pathfinding;
+pub mod mob;
+pub mod persistence;
+pub mod protocol;
+pub mod net;
+pub mod authority;
+pub mod prediction;
+pub mod server;
+pub mod client;
+pub mod font;
+pub mod console;
+pub mod export;
+pub mod structure;
+pub mod world_edit;
+pub mod world_tool;
+pub mod settings;
+pub mod prototype;
+#[cfg(test)]
+pub mod test_util;
 
 pub mod prelude{
     // pub use crate::chunk::*;
     // pub use crate::coords::*;
     // pub use crate::chunk_loader::*;
-}
\ No newline at end of file
+}
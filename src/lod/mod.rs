@@ -0,0 +1,529 @@
+use std::{collections::{HashMap, HashSet}, io};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    block::BlockType,
+    chunk::Chunk,
+    hyper_chunk::{VoxelOctree, VoxelWorld},
+    math::{Aabb, Coord3, Vec3},
+    mesh::{ChunkMesh, ChunkMesher},
+    persistence::WorldStorage,
+    world_generator::WorldGenerator
+};
+
+#[derive(Clone, Copy)]
+pub struct LodSettings{
+    // w chunkach, liczone w poziomie od chunka gracza
+    pub max_distance: i32
+}
+impl Default for LodSettings {
+    fn default() -> Self {
+        LodSettings{
+            max_distance: 60
+        }
+    }
+}
+
+// siatka komorek o boku 2^level wokseli, w kazdym chunku komorki zaczynaja sie od nowa,
+// wiec ostatnia komorka przy krawedzi chunka moze byc przycieta
+#[derive(Clone, Copy)]
+pub struct LodGrid{
+    scale: i32,
+    cells_per_chunk: i32
+}
+impl LodGrid {
+    pub fn new(level: i32) -> Self{
+        let scale = 1<<level;
+        LodGrid{
+            scale,
+            cells_per_chunk: (Chunk::CHUNK_SIZE as i32+scale-1)/scale
+        }
+    }
+    pub fn get_scale(&self) -> i32{
+        self.scale
+    }
+    pub fn get_cells_per_chunk(&self) -> i32{
+        self.cells_per_chunk
+    }
+    fn axis_cell(&self, position: i32) -> i32{
+        let size = Chunk::CHUNK_SIZE as i32;
+        position.div_euclid(size)*self.cells_per_chunk+position.rem_euclid(size)/self.scale
+    }
+    fn axis_cell_bounds(&self, cell: i32) -> (i32, i32){
+        let size = Chunk::CHUNK_SIZE as i32;
+        let chunk = cell.div_euclid(self.cells_per_chunk);
+        let min = chunk*size+cell.rem_euclid(self.cells_per_chunk)*self.scale;
+        (min, (min+self.scale).min(chunk*size+size))
+    }
+    // dzieli [min, max) na kawalki po granicach komorek, granice grubszej siatki sa tez granicami drobniejszej
+    fn split_axis(&self, min: i32, max: i32) -> Vec<(i32, i32)>{
+        let mut ranges = Vec::new();
+        let mut start = min;
+        while start < max{
+            let end = self.axis_cell_bounds(self.axis_cell(start)).1.min(max);
+            ranges.push((start, end));
+            start = end;
+        }
+        ranges
+    }
+    // komorka zawierajaca woksel
+    pub fn get_cell(&self, position: Coord3) -> Coord3{
+        Coord3::new(self.axis_cell(position.x), self.axis_cell(position.y), self.axis_cell(position.z))
+    }
+    // zakres wokseli komorki, max nie wchodzi w zakres
+    pub fn get_cell_bounds(&self, cell: Coord3) -> (Coord3, Coord3){
+        let (min_x, max_x) = self.axis_cell_bounds(cell.x);
+        let (min_y, max_y) = self.axis_cell_bounds(cell.y);
+        let (min_z, max_z) = self.axis_cell_bounds(cell.z);
+        (Coord3::new(min_x, min_y, min_z), Coord3::new(max_x, max_y, max_z))
+    }
+    pub fn get_representative(&self, cell: Coord3) -> Coord3{
+        let (min, max) = self.get_cell_bounds(cell);
+        // zaokraglenie w dol, zeby na poziomie 0 reprezentantem byl sam woksel takze dla ujemnych
+        Coord3::new((min.x+max.x).div_euclid(2), (min.y+max.y).div_euclid(2), (min.z+max.z).div_euclid(2))
+    }
+}
+
+pub struct LodBuilder<'a>{
+    generator: &'a WorldGenerator,
+    // zapisany swiat i pozycje jego chunkow
+    storage: Option<(&'a WorldStorage, HashSet<Coord3>)>,
+    mesher: ChunkMesher,
+    settings: LodSettings
+}
+
+impl<'a> LodBuilder<'a> {
+    pub const MAX_LEVEL: i32 = 3;
+    // kafelek LOD to kolumna chunkow od MIN_CHUNK_Y do MAX_CHUNK_Y (bez), teren generatora miesci sie w niej
+    const MIN_CHUNK_Y: i32 = -1;
+    const MAX_CHUNK_Y: i32 = 1;
+
+    pub fn new(generator: &'a WorldGenerator, settings: LodSettings) -> Self{
+        LodBuilder{
+            generator,
+            storage: None,
+            mesher: ChunkMesher::new(),
+            settings
+        }
+    }
+    // zapisane chunki zastepuja teren generatora, zeby z daleka bylo widac zmiany w swiecie
+    pub fn with_storage(mut self, storage: &'a WorldStorage) -> io::Result<Self>{
+        self.storage = Some((storage, storage.list_chunks()?.into_iter().collect()));
+        Ok(self)
+    }
+
+    // chunki w kuli ChunkLoadera wokol gracza sa rysowane w pelnej rozdzielczosci
+    fn is_chunk_loaded(chunk: Coord3, center: Coord3, load_distance: i32) -> bool{
+        chunk.distance2(center) <= load_distance.pow(2)
+    }
+
+    // poziom wedlug odleglosci w poziomie od chunka gracza: 1 do 2*load_distance, 2 do 4*load_distance, dalej 3
+    fn get_column_level(&self, column: Coord3, center: Coord3, load_distance: i32) -> Option<i32>{
+        let distance2 = (column.x-center.x).pow(2)+(column.z-center.z).pow(2);
+        if distance2 > self.settings.max_distance.pow(2){
+            return None;
+        }
+        let mut level = 1;
+        while level < LodBuilder::MAX_LEVEL && distance2 > (load_distance<<level).pow(2){
+            level += 1;
+        }
+        Some(level)
+    }
+
+    // None gdy cala kolumna jest ladowana w pelnej rozdzielczosci albo jest poza zasiegiem LOD
+    pub fn get_level(&self, column: Coord3, center: Coord3, load_distance: i32) -> Option<i32>{
+        let loaded = (LodBuilder::MIN_CHUNK_Y..LodBuilder::MAX_CHUNK_Y)
+            .all(|y| LodBuilder::is_chunk_loaded(Coord3::new(column.x, y, column.z), center, load_distance));
+        if loaded{
+            return None;
+        }
+        self.get_column_level(column, center, load_distance)
+    }
+
+    // poziom, w ktorym rysowany jest chunk: 0 gdy jest zaladowany, None gdy nic go nie rysuje
+    fn get_chunk_level(&self, chunk: Coord3, center: Coord3, load_distance: i32) -> Option<i32>{
+        if LodBuilder::is_chunk_loaded(chunk, center, load_distance){
+            return Some(0);
+        }
+        if chunk.y < LodBuilder::MIN_CHUNK_Y || chunk.y >= LodBuilder::MAX_CHUNK_Y{
+            return None;
+        }
+        self.get_column_level(chunk, center, load_distance)
+    }
+
+    pub fn get_tile_columns(&self, center: Coord3, load_distance: i32) -> Vec<(Coord3, i32)>{
+        let max = self.settings.max_distance;
+        let mut columns = Vec::new();
+        for x in center.x-max..center.x+max+1{
+            for z in center.z-max..center.z+max+1{
+                let column = Coord3::new(x, 0, z);
+                if let Some(level) = self.get_level(column, center, load_distance){
+                    columns.push((column, level));
+                }
+            }
+        }
+        columns
+    }
+
+    // od tych poziomow zalezy wyglad kafelka: chunki kolumny i czterech sasiednich kolumn;
+    // kafelek z tym samym kluczem nie musi byc budowany od nowa po ruchu gracza
+    pub fn get_tile_key(&self, column: Coord3, center: Coord3, load_distance: i32) -> Vec<Option<i32>>{
+        [Coord3::ZERO, Coord3::new(1, 0, 0), Coord3::new(-1, 0, 0), Coord3::new(0, 0, 1), Coord3::new(0, 0, -1)].iter()
+            .flat_map(|offset| (LodBuilder::MIN_CHUNK_Y..LodBuilder::MAX_CHUNK_Y).map(move |y| Coord3::new(column.x+offset.x, y, column.z+offset.z)))
+            .map(|chunk| self.get_chunk_level(chunk, center, load_distance))
+            .collect()
+    }
+
+    pub fn build_tiles(&self, columns: &[Coord3], center: Coord3, load_distance: i32) -> io::Result<Vec<ChunkMesh>>{
+        columns.par_iter()
+            .map(|column| self.build_tile(*column, center, load_distance))
+            .collect()
+    }
+
+    // zapisane chunki kolumny i czterech sasiednich kolumn, tylko z nich kafelek probkuje teren
+    fn load_saved_chunks(&self, column: Coord3) -> io::Result<HashMap<Coord3, Chunk>>{
+        let mut chunks = HashMap::new();
+        let Some((storage, saved)) = &self.storage else {
+            return Ok(chunks);
+        };
+        for offset in [Coord3::ZERO, Coord3::new(1, 0, 0), Coord3::new(-1, 0, 0), Coord3::new(0, 0, 1), Coord3::new(0, 0, -1)]{
+            for y in LodBuilder::MIN_CHUNK_Y..LodBuilder::MAX_CHUNK_Y{
+                let position = Coord3::new(column.x+offset.x, y, column.z+offset.z);
+                if saved.contains(&position){
+                    if let Some(chunk) = storage.load_chunk(position)?{
+                        chunks.insert(position, chunk);
+                    }
+                }
+            }
+        }
+        Ok(chunks)
+    }
+
+    fn get_voxel_type(&self, saved: &HashMap<Coord3, Chunk>, position: Coord3, height: Option<i32>) -> BlockType{
+        let size = Chunk::CHUNK_SIZE as i32;
+        match saved.get(&position.div_euclid(size)) {
+            Some(chunk) => chunk.get_voxel(position.mod_euclid(size)),
+            None => match height {
+                Some(height) => self.generator.get_voxel_type_with_height(position, height),
+                None => self.generator.get_voxel_type(position)
+            }
+        }
+    }
+
+    // sciana prostopadlosciennego zakresu wokseli [min, max)
+    fn add_wall(&self, mesh: &mut ChunkMesh, min: Coord3, max: Coord3, i: usize, block_type: BlockType){
+        let (axis, mdir) = match i {
+            0 => (0, 1.0),
+            1 => (0, -1.0),
+            2 => (1, 1.0),
+            3 => (1, -1.0),
+            4 => (2, 1.0),
+            _ => (2, -1.0)
+        };
+        let center = Vec3::new((min.x+max.x-1) as f32/2.0, (min.y+max.y-1) as f32/2.0, (min.z+max.z-1) as f32/2.0);
+        let size = Vec3::new((max.x-min.x) as f32, (max.y-min.y) as f32, (max.z-min.z) as f32);
+        let vertices = if block_type == BlockType::Water {mesh.get_vertices_water_mut()} else {mesh.get_vertices_mut()};
+        self.mesher.add_box_wall(vertices, center, size, mdir, axis, block_type, 0);
+    }
+
+    pub fn build_tile(&self, column: Coord3, center: Coord3, load_distance: i32) -> io::Result<ChunkMesh>{
+        let size = Chunk::CHUNK_SIZE as i32;
+        let mut mesh = ChunkMesh::new(column);
+        let min = Vec3::new((column.x*size) as f32-0.5, (LodBuilder::MIN_CHUNK_Y*size) as f32-0.5, (column.z*size) as f32-0.5);
+        let height = ((LodBuilder::MAX_CHUNK_Y-LodBuilder::MIN_CHUNK_Y)*size) as f32;
+        mesh.set_aabb(Aabb::new(min, min+Vec3::new(size as f32, height, size as f32)));
+        let Some(level) = self.get_level(column, center, load_distance) else {
+            return Ok(mesh);
+        };
+        let saved = self.load_saved_chunks(column)?;
+
+        let grid = LodGrid::new(level);
+        let n = grid.get_cells_per_chunk();
+        let first_cell = Coord3::new(column.x*n, LodBuilder::MIN_CHUNK_Y*n, column.z*n);
+        let cells = Coord3::new(n, (LodBuilder::MAX_CHUNK_Y-LodBuilder::MIN_CHUNK_Y)*n, n);
+        // zaladowane chunki kolumny rysuje zwykly mesher
+        let is_layer_loaded = |y: i32| LodBuilder::is_chunk_loaded(
+            Coord3::new(column.x, LodBuilder::MIN_CHUNK_Y+y.div_euclid(n), column.z), center, load_distance
+        );
+
+        // wysokosc terenu liczona raz na kolumne komorek
+        let mut heights = vec![0; (n*n) as usize];
+        for x in 0..n{
+            for z in 0..n{
+                let position = grid.get_representative(first_cell+Coord3::new(x, 0, z));
+                heights[(x*n+z) as usize] = self.generator.get_terrein_height(position);
+            }
+        }
+        let mut depth = 0;
+        while (1<<depth) < cells.x.max(cells.y).max(cells.z){
+            depth += 1;
+        }
+        let octree = VoxelOctree::from_fn(Coord3::ZERO, depth, |local: Coord3|{
+            if local.x >= cells.x || local.y >= cells.y || local.z >= cells.z || is_layer_loaded(local.y){
+                return BlockType::Air;
+            }
+            let position = grid.get_representative(first_cell+local);
+            self.get_voxel_type(&saved, position, Some(heights[(local.x*n+local.z) as usize]))
+        });
+        if octree.is_empty(){
+            return Ok(mesh);
+        }
+
+        let neighbors: Vec<Coord3> = Coord3::neighbors_into_iter().collect();
+        for x in 0..cells.x{
+            for y in 0..cells.y{
+                for z in 0..cells.z{
+                    let local = Coord3::new(x, y, z);
+                    let block_type = octree.get_block_type(local);
+                    if block_type == BlockType::Air{
+                        continue;
+                    }
+                    let (cell_min, cell_max) = grid.get_cell_bounds(first_cell+local);
+                    for (i, offset) in neighbors.iter().enumerate(){
+                        let neighbor = local+*offset;
+                        if neighbor.y < 0 || neighbor.y >= cells.y{
+                            // pod kafelkiem zawsze jest kamien, nad nim powietrze
+                            let neighbor_block = if neighbor.y < 0 {BlockType::Stone} else {BlockType::Air};
+                            if block_type.is_face_visible(neighbor_block){
+                                self.add_wall(&mut mesh, cell_min, cell_max, i, block_type);
+                            }
+                            continue;
+                        }
+                        let inside = neighbor.x >= 0 && neighbor.z >= 0 && neighbor.x < cells.x && neighbor.z < cells.z;
+                        if inside && !is_layer_loaded(neighbor.y){
+                            if block_type.is_face_visible(octree.get_block_type(neighbor)){
+                                self.add_wall(&mut mesh, cell_min, cell_max, i, block_type);
+                            }
+                            continue;
+                        }
+                        // sasiad z innym poziomem: sciana jest dzielona na jego komorki (drobniejszy ma ich kilka,
+                        // grubszy jedna) i kazdy kawalek jest rysowany tam, gdzie sasiad jest przezroczysty;
+                        // sasiad rysuje swoja strone tak samo, wiec miedzy poziomami nie ma szpar
+                        let across = |offset: i32, min: i32, max: i32| if offset > 0 {max} else if offset < 0 {min-1} else {min};
+                        let across = Coord3::new(
+                            across(offset.x, cell_min.x, cell_max.x),
+                            across(offset.y, cell_min.y, cell_max.y),
+                            across(offset.z, cell_min.z, cell_max.z)
+                        );
+                        let neighbor_level = self.get_chunk_level(across.div_euclid(size), center, load_distance).unwrap_or(level);
+                        let neighbor_grid = LodGrid::new(neighbor_level);
+                        let split = |offset: i32, min: i32, max: i32| if offset == 0 {neighbor_grid.split_axis(min, max)} else {vec![(min, max)]};
+                        for (min_x, max_x) in split(offset.x, cell_min.x, cell_max.x){
+                            for (min_y, max_y) in split(offset.y, cell_min.y, cell_max.y){
+                                for (min_z, max_z) in split(offset.z, cell_min.z, cell_max.z){
+                                    let probe = Coord3::new(
+                                        if offset.x == 0 {min_x} else {across.x},
+                                        if offset.y == 0 {min_y} else {across.y},
+                                        if offset.z == 0 {min_z} else {across.z}
+                                    );
+                                    let neighbor_block = self.get_voxel_type(&saved, neighbor_grid.get_representative(neighbor_grid.get_cell(probe)), None);
+                                    if block_type.is_face_visible(neighbor_block){
+                                        self.add_wall(&mut mesh, Coord3::new(min_x, min_y, min_z), Coord3::new(max_x, max_y, max_z), i, block_type);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{settings::Settings, test_util::TempDir};
+
+    use super::*;
+
+    #[test]
+    fn grid_cells_cover_each_chunk_without_gaps(){
+        let size = Chunk::CHUNK_SIZE as i32;
+        for level in 0..LodBuilder::MAX_LEVEL+1{
+            let grid = LodGrid::new(level);
+            let n = grid.get_cells_per_chunk();
+            // dwa chunki, po obu stronach zera
+            let mut next = -size;
+            for cell in -n..n{
+                let (min, max) = grid.get_cell_bounds(Coord3::new(cell, 0, 0));
+                assert_eq!(min.x, next, "level {level}, cell {cell}");
+                assert!(max.x > min.x && max.x-min.x <= grid.get_scale());
+                assert_eq!(grid.get_cell(min), Coord3::new(cell, 0, 0));
+                assert_eq!(grid.get_cell(max-Coord3::new(1, 1, 1)), Coord3::new(cell, 0, 0));
+                let representative = grid.get_representative(Coord3::new(cell, 0, 0));
+                assert!(representative.x >= min.x && representative.x < max.x);
+                next = max.x;
+            }
+            assert_eq!(next, size);
+        }
+        // ostatnia komorka poziomu 3 jest przycieta do krawedzi chunka
+        assert_eq!(LodGrid::new(3).get_cell_bounds(Coord3::new(6, 0, 0)), (Coord3::new(48, 0, 0), Coord3::new(50, 8, 8)));
+        assert_eq!(LodGrid::new(0).get_representative(Coord3::new(-3, 2, -1)), Coord3::new(-3, 2, -1));
+    }
+
+    #[test]
+    fn levels_are_measured_from_the_player(){
+        let generator = WorldGenerator::new(7);
+        let builder = LodBuilder::new(&generator, LodSettings::default());
+        let center = Coord3::ZERO;
+        assert_eq!(builder.get_level(Coord3::ZERO, center, 1), None);
+        // chunk (1, 0, 0) jest zaladowany, ale (1, -1, 0) juz nie
+        assert_eq!(builder.get_level(Coord3::new(1, 0, 0), center, 1), Some(1));
+        assert_eq!(builder.get_level(Coord3::new(2, 0, 0), center, 1), Some(1));
+        assert_eq!(builder.get_level(Coord3::new(0, 0, -3), center, 1), Some(2));
+        assert_eq!(builder.get_level(Coord3::new(4, 0, 0), center, 1), Some(2));
+        assert_eq!(builder.get_level(Coord3::new(5, 0, 0), center, 1), Some(3));
+        assert_eq!(builder.get_level(Coord3::new(7, 0, 0), center, 1), Some(3));
+
+        let center = Coord3::new(3, 0, 0);
+        assert_eq!(builder.get_level(Coord3::new(3, 0, 0), center, 1), None);
+        assert_eq!(builder.get_level(Coord3::new(5, 0, 0), center, 1), Some(1));
+        assert_eq!(builder.get_level(Coord3::ZERO, center, 1), Some(2));
+
+        let near = LodBuilder::new(&generator, LodSettings{max_distance: 4});
+        assert_eq!(near.get_level(Coord3::new(5, 0, 0), Coord3::ZERO, 1), None);
+        assert!(near.get_tile_columns(Coord3::ZERO, 1).iter().all(|(column, _)| column.x.pow(2)+column.z.pow(2) <= 16));
+    }
+
+    // kwadraty 1x1 na plaszczyznie x = plane-0.5 pokryte scianami o normalnej normal_x, z krotnoscia
+    fn covered_squares(mesh: &ChunkMesh, plane: i32, normal_x: f32) -> HashMap<(i32, i32), u32>{
+        let stride = ChunkMesher::VERTEX_SIZE;
+        let mut squares = HashMap::new();
+        for vertices in [mesh.get_vertices(), mesh.get_vertices_water()]{
+            for face in vertices.chunks(stride*6){
+                if face[6] != normal_x || (face[0]-(plane as f32-0.5)).abs() > 1e-3{
+                    continue;
+                }
+                let corner = |offset: usize, max: bool| (0..6).map(|i| face[i*stride+offset])
+                    .fold(if max {f32::MIN} else {f32::MAX}, |a, b| if max {a.max(b)} else {a.min(b)});
+                let rounded = |value: f32| (value+0.5).round() as i32;
+                for y in rounded(corner(1, false))..rounded(corner(1, true)){
+                    for z in rounded(corner(2, false))..rounded(corner(2, true)){
+                        *squares.entry((y, z)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        squares
+    }
+
+    // granica miedzy kolumna left a nastepna w x: kazdy kwadrat, w ktorym jedna strona jest pelna, a druga
+    // przezroczysta, ma dokladnie jedna sciane; strone zaladowana rysuje zwykly mesher
+    fn assert_seamless(builder: &LodBuilder, generator: &WorldGenerator, left: Coord3, center: Coord3, load_distance: i32){
+        let size = Chunk::CHUNK_SIZE as i32;
+        let right = left+Coord3::new(1, 0, 0);
+        let plane = right.x*size;
+        let left_faces = covered_squares(&builder.build_tile(left, center, load_distance).unwrap(), plane, 1.0);
+        let right_faces = covered_squares(&builder.build_tile(right, center, load_distance).unwrap(), plane, -1.0);
+        let sample = |voxel: Coord3|{
+            let level = builder.get_chunk_level(voxel.div_euclid(size), center, load_distance).unwrap();
+            let grid = LodGrid::new(level);
+            (level, generator.get_voxel_type(grid.get_representative(grid.get_cell(voxel))))
+        };
+        let mut checked = 0;
+        for y in LodBuilder::MIN_CHUNK_Y*size..LodBuilder::MAX_CHUNK_Y*size{
+            for z in left.z*size..left.z*size+size{
+                let (left_level, left_block) = sample(Coord3::new(plane-1, y, z));
+                let (right_level, right_block) = sample(Coord3::new(plane, y, z));
+                let expected_left = left_level > 0 && left_block.is_face_visible(right_block);
+                let expected_right = right_level > 0 && right_block.is_face_visible(left_block);
+                assert_eq!(left_faces.get(&(y, z)).copied().unwrap_or(0), expected_left as u32, "left side at y:{y} z:{z}");
+                assert_eq!(right_faces.get(&(y, z)).copied().unwrap_or(0), expected_right as u32, "right side at y:{y} z:{z}");
+                checked += (expected_left || expected_right) as i32;
+            }
+        }
+        assert!(checked > 0, "border without any faces does not test anything");
+    }
+
+    #[test]
+    fn tiles_are_stitched_with_neighbours_of_other_levels(){
+        let generator = WorldGenerator::new(7);
+        let builder = LodBuilder::new(&generator, LodSettings::default());
+        // poziom 1 obok poziomu 2, takze po ujemnej stronie
+        assert_seamless(&builder, &generator, Coord3::new(2, 0, 0), Coord3::ZERO, 1);
+        assert_seamless(&builder, &generator, Coord3::new(-3, 0, -1), Coord3::new(0, 0, -1), 1);
+        // poziom 2 obok poziomu 3
+        assert_seamless(&builder, &generator, Coord3::new(4, 0, 0), Coord3::ZERO, 1);
+        // kafelek obok zaladowanej kolumny i obok kolumny zaladowanej tylko czesciowo
+        assert_seamless(&builder, &generator, Coord3::new(1, 0, 1), Coord3::ZERO, 2);
+        assert_seamless(&builder, &generator, Coord3::new(1, 0, 0), Coord3::ZERO, 1);
+    }
+
+    #[test]
+    fn tiles_skip_loaded_chunks(){
+        let generator = WorldGenerator::new(7);
+        let builder = LodBuilder::new(&generator, LodSettings::default());
+        assert!(builder.build_tile(Coord3::ZERO, Coord3::ZERO, 1).unwrap().is_empty());
+        // w kolumnie (1, 0, 0) zaladowany jest chunk y=0, wiec kafelek konczy sie na y=-0.5
+        let tile = builder.build_tile(Coord3::new(1, 0, 0), Coord3::ZERO, 1).unwrap();
+        assert!(!tile.is_empty());
+        let stride = ChunkMesher::VERTEX_SIZE;
+        assert!(tile.get_vertices().chunks(stride).chain(tile.get_vertices_water().chunks(stride)).all(|vertex| vertex[1] <= -0.5+1e-3));
+        let columns: Vec<Coord3> = builder.get_tile_columns(Coord3::ZERO, 1).iter().map(|(column, _)| *column).collect();
+        assert!(!columns.contains(&Coord3::ZERO));
+        assert!(columns.len() > 20);
+    }
+
+    #[test]
+    fn tiles_with_the_same_key_do_not_change_when_the_player_moves(){
+        let generator = WorldGenerator::new(7);
+        let builder = LodBuilder::new(&generator, LodSettings::default());
+        let (before, after) = (Coord3::ZERO, Coord3::new(1, 0, 0));
+        let kept: Vec<Coord3> = builder.get_tile_columns(after, 1).iter()
+            .map(|(column, _)| *column)
+            .filter(|column| builder.get_tile_key(*column, before, 1) == builder.get_tile_key(*column, after, 1))
+            .collect();
+        assert!(!kept.is_empty());
+        for column in kept.iter().take(4){
+            let old = builder.build_tile(*column, before, 1).unwrap();
+            let new = builder.build_tile(*column, after, 1).unwrap();
+            assert!(old.get_vertices() == new.get_vertices() && old.get_vertices_water() == new.get_vertices_water(), "{column}");
+        }
+        // kolumna, ktora przestala byc zaladowana, ma inny klucz
+        assert_ne!(builder.get_tile_key(Coord3::new(-1, 0, 0), before, 1), builder.get_tile_key(Coord3::new(-1, 0, 0), after, 1));
+    }
+
+    #[test]
+    fn tiles_reach_beyond_the_default_load_distance(){
+        let generator = WorldGenerator::new(7);
+        let builder = LodBuilder::new(&generator, LodSettings::default());
+        let load_distance = Settings::default().view_distance;
+        let columns = builder.get_tile_columns(Coord3::ZERO, load_distance);
+        assert!(columns.iter().any(|(column, _)| *column == Coord3::new(load_distance+1, 0, 0)));
+        assert!(columns.iter().any(|(_, level)| *level == LodBuilder::MAX_LEVEL));
+        for column in [Coord3::new(load_distance+1, 0, 0), Coord3::new(-40, 0, 30)]{
+            assert!(!builder.build_tile(column, Coord3::ZERO, load_distance).unwrap().is_empty(), "{column}");
+        }
+    }
+
+    #[test]
+    fn tiles_show_saved_chunks(){
+        let dir = TempDir::new("lod_saved");
+        let storage = WorldStorage::open(&dir).unwrap();
+        let column = Coord3::new(20, 0, 0);
+        // zapisany chunk pelen piasku, generator nigdy nie siega tak wysoko
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(column);
+        for index in 0..Chunk::CHUNK_SIZE.pow(3){
+            chunk.set_voxel(Chunk::get_local_position_from_index(index), BlockType::Sand);
+        }
+        storage.save_chunk(&chunk).unwrap();
+
+        let generator = WorldGenerator::new(7);
+        let generated = LodBuilder::new(&generator, LodSettings::default());
+        let saved = LodBuilder::new(&generator, LodSettings::default()).with_storage(&storage).unwrap();
+        let top = |mesh: &ChunkMesh| mesh.get_vertices().chunks(ChunkMesher::VERTEX_SIZE).map(|vertex| vertex[1]).fold(f32::MIN, f32::max);
+        let size = Chunk::CHUNK_SIZE as f32;
+        assert!(top(&generated.build_tile(column, Coord3::ZERO, 13).unwrap()) < size-0.5);
+        assert_eq!(top(&saved.build_tile(column, Coord3::ZERO, 13).unwrap()), size-0.5);
+        // sasiedni kafelek nie rysuje scian zakrytych zapisanym chunkiem
+        let neighbor = column+Coord3::new(1, 0, 0);
+        let plane = neighbor.x*Chunk::CHUNK_SIZE as i32;
+        let generated_faces = covered_squares(&generated.build_tile(neighbor, Coord3::ZERO, 13).unwrap(), plane, -1.0);
+        let saved_faces = covered_squares(&saved.build_tile(neighbor, Coord3::ZERO, 13).unwrap(), plane, -1.0);
+        assert!(!generated_faces.is_empty());
+        assert!(saved_faces.keys().all(|(y, _)| *y < 0));
+    }
+}
@@ -1,34 +1,27 @@
-mod math;
-mod chunk;
-mod chunk_master; 
-mod chunk_loader;
-mod camera;
-mod block;
-mod world_generator;
-mod player;
-mod hyper_chunk;
-mod rendering;
-mod transform;
 
 
 use std::*;
-use alloc::System;
-use block::BlockType;
-use camera::Camera;
+use my_opengl_game::block::BlockType;
 use dashmap::DashMap;
-use chunk_loader::*;
-use chunk_master::ChunkMaster;
-use hyper_chunk::{OctaHyperChunk, VoxelWorld};
-use math::*;
-use chunk::*;
-use gl::COLOR;
-use nalgebra::Vector3;
-use noise::{NoiseFn, Perlin};
+use my_opengl_game::chunk_loader::*;
+use my_opengl_game::chunk_master::{ChunkMaster, MemoryWorld};
+use my_opengl_game::client::Client;
+use my_opengl_game::console::Console;
+use my_opengl_game::persistence::WorldStorage;
+use my_opengl_game::server::{Server, ServerSettings};
+use my_opengl_game::settings::{Mode, Settings};
+use io::IsTerminal;
+use my_opengl_game::math::*;
+use my_opengl_game::mesh::{ChunkMesh, ChunkMesher};
+use my_opengl_game::chunk::*;
+use my_opengl_game::game_loop::{GameLoop, GameState};
+use my_opengl_game::input::InputState;
+use my_opengl_game::player::Player;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use rendering::AppWraper;
+use my_opengl_game::rendering::AppWraper;
+use my_opengl_game::shadow::ShadowSettings;
 use sync::{Arc, Mutex};
-use time::Duration;
-use world_generator::WorldGenerator;
+use my_opengl_game::world_generator::WorldGenerator;
 
 // for value in loader.get_coords_to_load().try_iter(){
     //     println!("{}", value);
@@ -45,10 +38,51 @@ use world_generator::WorldGenerator;
                 //     }
                 // }
                 
-mod prototype;
 fn main() {
     let current = thread::current();
     println!("{:?}", current.name());
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help"){
+        println!("{}", Settings::USAGE);
+        return;
+    }
+    let mut settings = Settings::load(Settings::FILE).unwrap_or_else(|error|{
+        println!("{error}, using default settings");
+        Settings::default()
+    });
+    if let Err(error) = settings.apply_args(&args){
+        println!("{error}\n{}", Settings::USAGE);
+        return;
+    }
+    match &settings.mode {
+        // swiat przychodzi z serwera, lokalnie nic nie jest generowane
+        Mode::Connect(address) => {
+            let client = Client::connect(address).expect("cannot connect to server");
+            println!("connected to {address} as player {}", client.get_player_id());
+            my_opengl_game::prototype::draw(MemoryWorld::default(), Vec::new(), ShadowSettings::default(), &settings, None, Some(client));
+            return;
+        }
+        Mode::Server(port) => {
+            let server_settings = ServerSettings{
+                seed: settings.seed,
+                view_distance: settings.view_distance,
+                world_dir: settings.world_dir.clone(),
+                ..Default::default()
+            };
+            let mut server = Server::bind(&format!("0.0.0.0:{port}"), server_settings).expect("cannot start server");
+            println!("server listening on {}, seed {}", server.local_addr().unwrap(), server.get_seed());
+            if let Err(error) = server.run(){
+                println!("server stopped: {error}");
+            }
+            return;
+        }
+        Mode::Window | Mode::Headless(_) => {}
+    }
+    let storage = settings.world_dir.as_ref().map(|dir|{
+        let storage = WorldStorage::open(dir).expect("cannot open world");
+        settings.seed = storage.load_or_save_seed(settings.seed).expect("cannot read world seed");
+        storage
+    });
     
     // let mut vertices: Vec<f32> = vec![
     //     // -0.5, -0.5, 0.0,         // Lewy dolny
@@ -62,85 +96,8 @@ fn main() {
     //     // 0.0,  1.0, 0.0   //
         
     // ];
-    let vertices_arc: Arc<Mutex<Vec<f32>>> = Arc::new(Mutex::new(Vec::new()));
-    let vertices_water_arc: Arc<Mutex<Vec<f32>>> = Arc::new(Mutex::new(Vec::new()));
-    let wall = vec![
-        Vector3::new(-0.5, 0.5, -0.5),
-        Vector3::new(0.5, 0.5, -0.5),
-        Vector3::new(0.5, 0.5, 0.5),
-
-        Vector3::new(-0.5, 0.5, 0.5),
-        Vector3::new(-0.5, 0.5, -0.5),
-        Vector3::new(0.5, 0.5, 0.5),
-    ];
-
-    let back_wall = vec![
-        Vector3::new(-0.5, 0.5, -0.5),
-        Vector3::new(0.5, 0.5, 0.5),
-        Vector3::new(0.5, 0.5, -0.5),
-
-        Vector3::new(-0.5, 0.5, 0.5),
-        Vector3::new(0.5, 0.5, 0.5),
-        Vector3::new(-0.5, 0.5, -0.5),
-    ];
-
-    let perlin = Perlin::new(2);   
-    let perlin2 = Perlin::new(2);   
-    let add_wall = |vertices: &mut Vec<f32>, coord: Coord3, mdir: f32, axis:i32, block_type: BlockType, ao: u32|{
-        let mut cnt = 0;
-        for i in 0..6{
-            let vertex = if (mdir>0.)^(axis==0) {wall[i]} else {back_wall[i]};
-            let vertex = match axis {
-                2 => vertex.xzy(),
-                0 => vertex.xyz(),
-                _ => vertex.yxz()
-            };
-            let normal = Vector3::new(0.0, mdir*1.0, 0.0); 
-            let normal = match axis {
-                2 => normal.xzy(),
-                0 => normal.xyz(),
-                _ => normal.yxz()
-            };
-            let vx = coord.x as f32+mdir*vertex.x;
-            let vy = coord.y as f32+mdir*vertex.y;
-            let vz = coord.z as f32+mdir*vertex.z;
-            vertices.push(vx);
-            vertices.push(vy);
-            vertices.push(vz);
-            let z = cnt%6;
-            let z = match z {
-                1 => 1,
-                2 => 2,
-                4 => 3,
-                5 => 2,
-                _ => 0
-            };
-            cnt += 1;
-            let mut color = block_type.get_color();
-            if ao & 1<<((axis*2+((-mdir as i32+1)/2))*4+z) > 0{
-                color = color-Vector3::new(0.1, 0.1, 0.1);
-            }
-            if block_type == BlockType::Water{
-                let frequency = 3e-3;
-                color.x = perlin.get([vx as f64 * frequency, vz as f64 * frequency]) as f32;
-                color.y = perlin2.get([vx as f64 * frequency, vz as f64 * frequency]) as f32;
-            }
-            if block_type == BlockType::Grass{
-                color.y+=(coord.z as f32/10.0).sin()/16.0;
-            }
-            vertices.push(color.x);
-            vertices.push(color.y);
-            vertices.push(color.z);
-
-            vertices.push(normal.x);
-            vertices.push(normal.y);
-            vertices.push(normal.z);
-        }
-    };
-
-    
     let mut loader = ChunkLoader::default();
-    loader.set_load_distance(13);
+    loader.set_load_distance(settings.view_distance);
     let joins = loader.commit_world_positon();
     for handler in joins.into_iter(){
         handler.join().unwrap();
@@ -151,7 +108,7 @@ fn main() {
     let chunks_cnt = chunks.len();
 
 
-    let generator = WorldGenerator::new(2137);
+    let generator = WorldGenerator::new(settings.seed);
     ///ohc 
     // let cntr = Arc::new(Mutex::new(0));
     // let vertices_mutex = Arc::clone(&vertices_arc);
@@ -190,8 +147,13 @@ fn main() {
                 chunk_map.insert(*coord, Chunk::default());
             }
             let chunkers = &mut *chunk_map.get_mut(coord).unwrap();
-            chunkers.set_chunk_position(*coord);    
-            generator.generate_chunk(chunkers);
+            match storage.as_ref().and_then(|storage| storage.load_chunk(*coord).expect("cannot read chunk")) {
+                Some(stored) => *chunkers = stored,
+                None => {
+                    chunkers.set_chunk_position(*coord);    
+                    generator.generate_chunk(chunkers);
+                }
+            }
             let cntr_mutex=cntr.clone();
             let mut cntr = cntr_mutex.lock().unwrap();
             *cntr+=1;
@@ -199,19 +161,74 @@ fn main() {
     });
     println!("generated");
 
+    if let Mode::Headless(ticks) = settings.mode{
+        let mut player = Player::new();
+        player.toggle_flying();
+        let spawn_height = generator.get_terrein_height(Coord3::ZERO) as f32+10.0;
+        player.set_position(Vec3::new(0.0, spawn_height, 0.0));
+        let mut game = GameState::new(chunk_map, player, InputState::default());
+        game.set_seed(generator.get_seed());
+        game.set_render_distance(settings.view_distance);
+        let mut game_loop = GameLoop::default();
+        // komendy konsoli z stdin, czytane w osobnym watku, zeby symulacja na nie nie czekala
+        let mut console = Console::default();
+        let piped = !io::stdin().is_terminal();
+        let (sender, commands) = sync::mpsc::channel::<String>();
+        thread::spawn(move ||{
+            for line in io::stdin().lines().map_while(Result::ok){
+                if sender.send(line).is_err(){
+                    break;
+                }
+            }
+        });
+        let mut run_command = |game: &mut GameState, line: String|{
+            if !line.trim().is_empty(){
+                println!("{}", console.execute(game, &line));
+            }
+        };
+        let mut seconds = 0.0;
+        for _ in 0..ticks{
+            for line in commands.try_iter(){
+                run_command(&mut game, line);
+            }
+            seconds += game_loop.run_headless(1, &mut game);
+            // chunki wokol gracza jak w oknie, np. po /tp albo /renderdistance
+            let (_, unloaded) = game.stream_chunks(&generator, storage.as_ref(), 8).expect("cannot read chunk");
+            if let Some(storage) = &storage{
+                for chunk in unloaded{
+                    storage.save_chunk(&chunk).expect("cannot save chunk");
+                }
+            }
+        }
+        // komendy z pliku albo potoku sa wykonywane do konca wejscia
+        if piped{
+            for line in commands.iter(){
+                run_command(&mut game, line);
+            }
+        }
+        println!("headless: {ticks} ticks in {:.3} s ({:.1} ticks/s), player at {}",
+            seconds, ticks as f32/seconds.max(1e-6), game.player.get_position());
+        if let Some(storage) = &storage{
+            let chunk_positions = game.world.get_chunk_positions();
+            for chunk_position in chunk_positions.iter(){
+                if let Some(chunk) = game.world.get_chunk(*chunk_position){
+                    storage.save_chunk(&chunk).expect("cannot save chunk");
+                }
+            }
+            println!("saved {} chunks to {}", chunk_positions.len(), storage.get_dir().display());
+        }
+        return;
+    }
+
     let voxelss: Arc<Mutex< Vec<i32> >> = Arc::new(Mutex::new( Vec::new()));
     let cntr = Arc::new(Mutex::new(0));      
-    chunks.par_iter().for_each(|coord: &Coord3| {
+    let mesher = ChunkMesher::new();
+    let meshes: Vec<ChunkMesh> = chunks.par_iter().filter_map(|coord: &Coord3| {
             let chunkers = & *chunk_map.get(coord).unwrap();
             if chunkers.is_empty(){
-                return;
+                return Some(ChunkMesh::new(*coord));
             }
-            let faces_table = chunkers.calculate_faces_table(&chunk_map);
-            let ao_table = chunkers.calculate_ambient_occlusion(&chunk_map, &faces_table);
-
-
-            let vertices_mutex = Arc::clone(&vertices_arc);
-            let vertices_water_mutex = Arc::clone(&vertices_water_arc);
+            let faces_table = chunkers.calculate_faces_table(&chunk_map.get_neighbourhood(*coord));
             for index in chunkers.get_voxels(){
                 let voxel_type = chunkers.get_voxel_from_index(*index);
                 let pos: Coord3 = Chunk::get_local_position_from_index(*index);
@@ -224,39 +241,22 @@ fn main() {
                     vx.push(pos.y);
                     vx.push(pos.z);
                 }
-                for i in 0..3{
-                    for m in [1.0, -1.0]{
-                        if faces_table[*index] & 1<<( i*2+(-m as i32+1)/2)==0{
-                            let mut vertices = vertices_mutex.lock().unwrap();
-                            let mut vertices_water = vertices_water_mutex.lock().unwrap();
-                            if voxel_type == BlockType::Water{
-                                add_wall(&mut vertices_water, pos, m, i, voxel_type, ao_table[*index]);
-                            }
-                            else{
-                                add_wall(&mut vertices, pos, m, i, voxel_type, ao_table[*index]);
-                            }
-                        } 
-                    }
-                }
             }
+            let mesh = mesher.build_mesh(chunkers, &chunk_map);
             let cntr_mutex=cntr.clone();
             let mut cntr = cntr_mutex.lock().unwrap();
             *cntr+=1;
             println!("rendering: {}/{}", *cntr, chunks_cnt);
-    });
+            Some(mesh)
+    }).collect();
     println!("generating ends");
 
-    let vertices_mutex = Arc::clone(&vertices_arc);
-    let vertices  = vertices_mutex.lock().unwrap();
-    let vertices_water_mutex = Arc::clone(&vertices_water_arc);
-    let vertices_water  = vertices_water_mutex.lock().unwrap();
-
     let mut x = AppWraper::new();
     for xd in voxelss.clone().lock().unwrap().clone(){
         //println!("{}", xd.0);
     }
     //x.run(voxelss.clone().lock().unwrap().clone());
 
-    prototype::draw(vertices.clone(), vertices_water.clone());
+    my_opengl_game::prototype::draw(chunk_map, meshes, ShadowSettings::default(), &settings, storage, None);
     println!("program ends");
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use nalgebra::Matrix4;
 use std::{array::IntoIter, fmt::Display, ops::{Add, AddAssign, Div, Mul, Sub, SubAssign}};
 
 #[macro_export]
@@ -7,7 +8,7 @@ macro_rules! c3d3 {
     };
 }
 
-#[derive(PartialEq, Clone, Copy, Hash, Eq)]
+#[derive(Debug, PartialEq, Clone, Copy, Hash, Eq)]
 pub struct Coord3{
     pub x: i32,
     pub y: i32,
@@ -121,7 +122,7 @@ impl Coord3 {
     }
 }
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Vec3{
     pub x: f32,
     pub y: f32,
@@ -160,6 +161,10 @@ impl Vec3 {
     pub fn to_tuple(&self) -> (f32, f32, f32){
         (self.x, self.y, self.z)
     }
+    // woksel o srodku w punkcie calkowitym zajmuje [-0.5, 0.5]
+    pub fn to_voxel_coord(&self) -> Coord3{
+        Coord3::new(self.x.round() as i32, self.y.round() as i32, self.z.round() as i32)
+    }
 }
 impl Add for Vec3 {
     type Output = Vec3;
@@ -284,4 +289,243 @@ impl Mul for Quat {
             z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w
         }
     }
-}
\ No newline at end of file
+}
+// xorshift z ziarnem, dla mechanik, ktore musza byc powtarzalne
+#[derive(Clone)]
+pub struct Rng{
+    state: u64
+}
+impl Rng {
+    pub fn new(seed: u64) -> Self{
+        // xorshift nie moze startowac od zera
+        Rng{state: seed.wrapping_mul(0x9E3779B97F4A7C15).max(1)}
+    }
+    pub fn next_u64(&mut self) -> u64{
+        self.state ^= self.state<<13;
+        self.state ^= self.state>>7;
+        self.state ^= self.state<<17;
+        self.state
+    }
+    // liczba z zakresu [0, max), max musi byc dodatnie
+    pub fn range(&mut self, max: i32) -> i32{
+        assert!(max > 0, "rng range must be positive, got {max}");
+        (self.next_u64()%max as u64) as i32
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub struct Aabb{
+    pub min: Vec3,
+    pub max: Vec3
+}
+impl Aabb {
+    pub const fn new(min: Vec3, max: Vec3) -> Aabb{
+        Aabb{min, max}
+    }
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Aabb{
+        Aabb::new(center-half_extents, center+half_extents)
+    }
+    pub fn get_center(&self) -> Vec3{
+        (self.min+self.max)/2.0
+    }
+    pub fn contains(&self, point: Vec3) -> bool{
+        point.x >= self.min.x && point.x <= self.max.x
+        && point.y >= self.min.y && point.y <= self.max.y
+        && point.z >= self.min.z && point.z <= self.max.z
+    }
+    pub fn intersects(&self, other: &Aabb) -> bool{
+        self.min.x < other.max.x && self.max.x > other.min.x
+        && self.min.y < other.max.y && self.max.y > other.min.y
+        && self.min.z < other.max.z && self.max.z > other.min.z
+    }
+}
+
+// plaszczyzny w postaci ax+by+cz+d, punkty wewnatrz frustum maja wartosc >= 0
+#[derive(Clone, Copy)]
+pub struct Frustum{
+    planes: [[f32; 4]; 6]
+}
+impl Frustum {
+    pub fn from_matrix(view_projection: &Matrix4<f32>) -> Frustum{
+        let row = |i: usize| [
+            view_projection[(i, 0)], view_projection[(i, 1)],
+            view_projection[(i, 2)], view_projection[(i, 3)]
+        ];
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let plane = |sign: f32, r: [f32; 4]| {
+            let p = [r3[0]+sign*r[0], r3[1]+sign*r[1], r3[2]+sign*r[2], r3[3]+sign*r[3]];
+            let length = (p[0].powi(2)+p[1].powi(2)+p[2].powi(2)).sqrt();
+            if length > 0.0 {[p[0]/length, p[1]/length, p[2]/length, p[3]/length]} else {p}
+        };
+        Frustum{
+            planes: [
+                plane(1.0, r0), plane(-1.0, r0),
+                plane(1.0, r1), plane(-1.0, r1),
+                plane(1.0, r2), plane(-1.0, r2)
+            ]
+        }
+    }
+    pub fn contains_point(&self, point: Vec3) -> bool{
+        self.planes.iter().all(|p| p[0]*point.x+p[1]*point.y+p[2]*point.z+p[3] >= 0.0)
+    }
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool{
+        for p in self.planes.iter(){
+            // wierzcholek najdalej w kierunku normalnej plaszczyzny
+            let x = if p[0] >= 0.0 {aabb.max.x} else {aabb.min.x};
+            let y = if p[1] >= 0.0 {aabb.max.y} else {aabb.min.y};
+            let z = if p[2] >= 0.0 {aabb.max.z} else {aabb.min.z};
+            if p[0]*x+p[1]*y+p[2]*z+p[3] < 0.0{
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RayHit{
+    pub position: Coord3,
+    // sciana w ktora trafil promien, zero gdy promien zaczyna sie w bloku
+    pub normal: Coord3,
+    pub distance: f32
+}
+
+// DDA (Amanatides & Woo), woksel c zajmuje [c-0.5, c+0.5]
+pub fn raycast_voxels(origin: Vec3, direction: Vec3, max_distance: f32, is_solid: impl Fn(Coord3) -> bool) -> Option<RayHit>{
+    let length = direction.magnitude();
+    if length == 0.0 || length.is_nan(){
+        return None;
+    }
+    let direction = [direction.x/length, direction.y/length, direction.z/length];
+    let start = [origin.x+0.5, origin.y+0.5, origin.z+0.5];
+    let mut voxel = [start[0].floor() as i32, start[1].floor() as i32, start[2].floor() as i32];
+    let mut step = [0; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+    for axis in 0..3{
+        if direction[axis] > 0.0{
+            step[axis] = 1;
+            t_max[axis] = (start[axis].floor()+1.0-start[axis])/direction[axis];
+            t_delta[axis] = 1.0/direction[axis];
+        }
+        else if direction[axis] < 0.0{
+            step[axis] = -1;
+            t_max[axis] = (start[axis]-start[axis].floor())/-direction[axis];
+            t_delta[axis] = -1.0/direction[axis];
+        }
+    }
+    let mut normal = [0; 3];
+    let mut distance = 0.0;
+    loop{
+        let position = Coord3::new(voxel[0], voxel[1], voxel[2]);
+        if is_solid(position){
+            return Some(RayHit{
+                position,
+                normal: Coord3::new(normal[0], normal[1], normal[2]),
+                distance
+            });
+        }
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] {0} else {2}
+        }
+        else if t_max[1] < t_max[2] {1} else {2};
+        if t_max[axis] > max_distance{
+            return None;
+        }
+        voxel[axis] += step[axis];
+        distance = t_max[axis];
+        t_max[axis] += t_delta[axis];
+        normal = [0; 3];
+        normal[axis] = -step[axis];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Matrix4, Perspective3, Point3, Vector3};
+
+    use super::*;
+
+    fn unit_box(center: Vec3, half: f32) -> Aabb{
+        Aabb::from_center(center, Vec3::new(half, half, half))
+    }
+
+    #[test]
+    fn identity_frustum_is_clip_cube(){
+        let frustum = Frustum::from_matrix(&Matrix4::identity());
+        assert!(frustum.contains_point(Vec3::ZERO));
+        assert!(frustum.contains_point(Vec3::new(0.99, -0.99, 0.5)));
+        assert!(!frustum.contains_point(Vec3::new(1.5, 0.0, 0.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -1.01)));
+    }
+
+    #[test]
+    fn aabb_inside_outside_and_crossing(){
+        let frustum = Frustum::from_matrix(&Matrix4::identity());
+        assert!(frustum.intersects_aabb(&unit_box(Vec3::ZERO, 0.5)));
+        assert!(frustum.intersects_aabb(&unit_box(Vec3::new(1.2, 0.0, 0.0), 0.5)));
+        assert!(frustum.intersects_aabb(&unit_box(Vec3::ZERO, 10.0)));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(3.0, 0.0, 0.0), 0.5)));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(0.0, -3.0, 0.0), 0.5)));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(0.0, 0.0, 1.6), 0.5)));
+    }
+
+    #[test]
+    fn perspective_frustum_culls_behind_and_beyond_far(){
+        let projection = Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0).to_homogeneous();
+        let view = Matrix4::look_at_rh(&Point3::origin(), &Point3::new(0.0, 0.0, -1.0), &Vector3::y());
+        let frustum = Frustum::from_matrix(&(projection*view));
+        assert!(frustum.intersects_aabb(&unit_box(Vec3::new(0.0, 0.0, -10.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(0.0, 0.0, 10.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(0.0, 0.0, -150.0), 1.0)));
+        // 90 stopni fov, wiec przy z=-10 widac do x=10
+        assert!(frustum.intersects_aabb(&unit_box(Vec3::new(10.5, 0.0, -10.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(13.0, 0.0, -10.0), 1.0)));
+    }
+
+    #[test]
+    fn raycast_hits_first_solid_voxel_face(){
+        let floor = |position: Coord3| position.y <= 0;
+        let hit = raycast_voxels(Vec3::new(0.2, 5.0, -0.3), Vec3::new(0.0, -1.0, 0.0), 10.0, floor).unwrap();
+        assert_eq!(hit.position, Coord3::new(0, 0, 0));
+        assert_eq!(hit.normal, Coord3::new(0, 1, 0));
+        assert!((hit.distance-4.5).abs() < 1e-5);
+
+        let wall = |position: Coord3| position.x >= 3;
+        let hit = raycast_voxels(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 1.0), 10.0, wall).unwrap();
+        assert_eq!(hit.position.x, 3);
+        assert_eq!(hit.normal, Coord3::new(-1, 0, 0));
+        assert!((hit.distance-2.5*2f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn raycast_respects_max_distance_and_negative_coords(){
+        let block = |position: Coord3| position == Coord3::new(-4, 2, -7);
+        assert!(raycast_voxels(Vec3::new(-4.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 6.0, block).is_none());
+        let hit = raycast_voxels(Vec3::new(-4.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 7.0, block).unwrap();
+        assert_eq!(hit.normal, Coord3::new(0, 0, 1));
+        assert!((hit.distance-6.5).abs() < 1e-5);
+        assert!(raycast_voxels(Vec3::ZERO, Vec3::ZERO, 7.0, block).is_none());
+    }
+
+    #[test]
+    fn aabb_intersection(){
+        let a = unit_box(Vec3::ZERO, 1.0);
+        assert!(a.intersects(&unit_box(Vec3::new(1.5, 0.0, 0.0), 1.0)));
+        assert!(!a.intersects(&unit_box(Vec3::new(2.5, 0.0, 0.0), 1.0)));
+        assert!(a.contains(Vec3::new(1.0, -1.0, 0.0)));
+    }
+
+    #[test]
+    fn rng_range_stays_in_bounds(){
+        let mut rng = Rng::new(3);
+        assert!((0..1000).all(|_| (0..7).contains(&rng.range(7))));
+        assert_eq!(rng.range(1), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rng_range_rejects_empty_range(){
+        Rng::new(3).range(0);
+    }
+}
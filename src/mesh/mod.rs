@@ -0,0 +1,239 @@
+use nalgebra::Vector3;
+use noise::{NoiseFn, Perlin};
+
+use crate::{block::BlockType, chunk::{Chunk, ChunkVisibility}, chunk_master::{ChunkMaster, Neighbourhood}, math::{Aabb, Coord3, Vec3}};
+
+pub struct ChunkMesh{
+    chunk_position: Coord3,
+    vertices: Vec<f32>,
+    vertices_water: Vec<f32>,
+    visibility: ChunkVisibility,
+    aabb: Aabb
+}
+impl ChunkMesh {
+    pub fn new(chunk_position: Coord3) -> Self{
+        ChunkMesh{
+            chunk_position,
+            vertices: Vec::new(),
+            vertices_water: Vec::new(),
+            visibility: ChunkVisibility::ALL,
+            aabb: ChunkMesh::get_chunk_aabb(chunk_position)
+        }
+    }
+    pub fn get_chunk_position(&self) -> Coord3{
+        self.chunk_position
+    }
+    pub fn get_vertices(&self) -> &Vec<f32>{
+        &self.vertices
+    }
+    pub fn get_vertices_water(&self) -> &Vec<f32>{
+        &self.vertices_water
+    }
+    pub fn get_visibility(&self) -> ChunkVisibility{
+        self.visibility
+    }
+    pub fn is_empty(&self) -> bool{
+        self.vertices.is_empty() && self.vertices_water.is_empty()
+    }
+    pub fn get_aabb(&self) -> Aabb{
+        self.aabb
+    }
+    pub fn set_aabb(&mut self, aabb: Aabb){
+        self.aabb = aabb;
+    }
+    pub fn get_vertices_mut(&mut self) -> &mut Vec<f32>{
+        &mut self.vertices
+    }
+    pub fn get_vertices_water_mut(&mut self) -> &mut Vec<f32>{
+        &mut self.vertices_water
+    }
+    // sciany wokseli leza na +-0.5, fale wody moga wyjsc troche wyzej
+    pub fn get_chunk_aabb(chunk_position: Coord3) -> Aabb{
+        let size = Chunk::CHUNK_SIZE as f32;
+        let origin = chunk_position*Chunk::CHUNK_SIZE as i32;
+        let min = Vec3::new(origin.x as f32-1.0, origin.y as f32-1.0, origin.z as f32-1.0);
+        Aabb::new(min, min+Vec3::new(size+1.0, size+1.0, size+1.0))
+    }
+}
+
+pub struct ChunkMesher{
+    perlin: Perlin,
+    perlin2: Perlin,
+    wall: [Vector3<f32>; 6],
+    back_wall: [Vector3<f32>; 6]
+}
+impl Default for ChunkMesher {
+    fn default() -> Self {
+        ChunkMesher::new()
+    }
+}
+impl ChunkMesher {
+    pub const VERTEX_SIZE: usize = 9;
+
+    pub fn new() -> Self{
+        ChunkMesher{
+            perlin: Perlin::new(2),
+            perlin2: Perlin::new(2),
+            wall: [
+                Vector3::new(-0.5, 0.5, -0.5),
+                Vector3::new(0.5, 0.5, -0.5),
+                Vector3::new(0.5, 0.5, 0.5),
+
+                Vector3::new(-0.5, 0.5, 0.5),
+                Vector3::new(-0.5, 0.5, -0.5),
+                Vector3::new(0.5, 0.5, 0.5),
+            ],
+            back_wall: [
+                Vector3::new(-0.5, 0.5, -0.5),
+                Vector3::new(0.5, 0.5, 0.5),
+                Vector3::new(0.5, 0.5, -0.5),
+
+                Vector3::new(-0.5, 0.5, 0.5),
+                Vector3::new(0.5, 0.5, 0.5),
+                Vector3::new(-0.5, 0.5, -0.5),
+            ]
+        }
+    }
+
+    pub fn build_mesh(&self, chunk: &Chunk, master: &impl ChunkMaster) -> ChunkMesh{
+        let mut mesh = ChunkMesh::new(chunk.get_chunk_position());
+        if chunk.is_empty(){
+            return mesh;
+        }
+        mesh.visibility = chunk.calculate_visibility();
+        let neighbourhood = master.get_neighbourhood(chunk.get_chunk_position());
+        let faces_table = chunk.calculate_faces_table(&neighbourhood);
+        let ao_table = chunk.calculate_ambient_occlusion(&neighbourhood, &faces_table);
+        for index in chunk.get_voxels(){
+            let voxel_type = chunk.get_voxel_from_index(*index);
+            let pos: Coord3 = Chunk::get_local_position_from_index(*index);
+            let local = pos;
+            let pos = chunk.get_world_position(pos);
+            let water_height = if voxel_type == BlockType::Water {ChunkMesher::get_water_height(chunk, local, &neighbourhood)} else {1.0};
+            for i in 0..3{
+                for m in [1.0, -1.0]{
+                    if faces_table[*index] & 1<<( i*2+(-m as i32+1)/2)==0{
+                        if voxel_type == BlockType::Water && water_height < 1.0{
+                            let center = Vec3::new(pos.x as f32, pos.y as f32-0.5+water_height/2.0, pos.z as f32);
+                            self.add_box_wall(&mut mesh.vertices_water, center, Vec3::new(1.0, water_height, 1.0), m, i, voxel_type, ao_table[*index]);
+                        }
+                        else if voxel_type == BlockType::Water{
+                            self.add_wall(&mut mesh.vertices_water, pos, m, i, voxel_type, ao_table[*index]);
+                        }
+                        else{
+                            self.add_wall(&mut mesh.vertices, pos, m, i, voxel_type, ao_table[*index]);
+                        }
+                    }
+                }
+            }
+        }
+        mesh
+    }
+
+    // plynaca woda jest nizsza od pelnego bloku, chyba ze ma nad soba wode
+    fn get_water_height(chunk: &Chunk, local: Coord3, neighbourhood: &Neighbourhood) -> f32{
+        let level = chunk.get_fluid_level(local);
+        let above = if Chunk::is_outer(local.upper()) {neighbourhood.get_voxel(local.upper())} else {chunk.get_voxel(local.upper())};
+        if level >= Chunk::SOURCE_FLUID_LEVEL || above == BlockType::Water{
+            return 1.0;
+        }
+        level as f32/Chunk::SOURCE_FLUID_LEVEL as f32
+    }
+
+    pub fn add_wall(&self, vertices: &mut Vec<f32>, coord: Coord3, mdir: f32, axis:i32, block_type: BlockType, ao: u32){
+        let center = Vec3::new(coord.x as f32, coord.y as f32, coord.z as f32);
+        self.add_box_wall(vertices, center, Vec3::new(1.0, 1.0, 1.0), mdir, axis, block_type, ao);
+    }
+
+    // sciana prostopadloscianu o srodku center i rozmiarach size, uzywane tez przez LOD
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_box_wall(&self, vertices: &mut Vec<f32>, center: Vec3, size: Vec3, mdir: f32, axis:i32, block_type: BlockType, ao: u32){
+        for i in 0..6{
+            let vertex = if (mdir>0.)^(axis==0) {self.wall[i]} else {self.back_wall[i]};
+            let vertex = match axis {
+                2 => vertex.xzy(),
+                0 => vertex.xyz(),
+                _ => vertex.yxz()
+            };
+            let normal = Vector3::new(0.0, mdir*1.0, 0.0);
+            let normal = match axis {
+                2 => normal.xzy(),
+                0 => normal.xyz(),
+                _ => normal.yxz()
+            };
+            let vx = center.x+mdir*vertex.x*size.x;
+            let vy = center.y+mdir*vertex.y*size.y;
+            let vz = center.z+mdir*vertex.z*size.z;
+            vertices.push(vx);
+            vertices.push(vy);
+            vertices.push(vz);
+            let z = match i {
+                1 => 1,
+                2 => 2,
+                4 => 3,
+                5 => 2,
+                _ => 0
+            };
+            let mut color = block_type.get_color();
+            if ao & 1<<((axis*2+((-mdir as i32+1)/2))*4+z) > 0{
+                color -= Vector3::new(0.1, 0.1, 0.1);
+            }
+            if block_type == BlockType::Water{
+                let frequency = 3e-3;
+                color.x = self.perlin.get([vx as f64 * frequency, vz as f64 * frequency]) as f32;
+                color.y = self.perlin2.get([vx as f64 * frequency, vz as f64 * frequency]) as f32;
+            }
+            if block_type == BlockType::Grass{
+                color.y+=(center.z/10.0).sin()/16.0;
+            }
+            vertices.push(color.x);
+            vertices.push(color.y);
+            vertices.push(color.z);
+
+            vertices.push(normal.x);
+            vertices.push(normal.y);
+            vertices.push(normal.z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dashmap::DashMap;
+
+    use crate::chunk_master::MemoryWorld;
+
+    use super::*;
+
+    fn add_chunk(world: &impl ChunkMaster, chunk_position: Coord3){
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(chunk_position);
+        world.insert_chunk(chunk);
+    }
+
+    #[test]
+    fn faces_touching_neighbour_chunks_are_hidden(){
+        let mesher = ChunkMesher::new();
+        let face = 6*ChunkMesher::VERTEX_SIZE;
+        let memory = MemoryWorld::default();
+        let dashmap: DashMap<Coord3, Chunk> = DashMap::new();
+        add_chunk(&memory, Coord3::ZERO);
+        add_chunk(&dashmap, Coord3::ZERO);
+        memory.set_voxel(Coord3::new(0, 5, 5), BlockType::Stone);
+        dashmap.set_voxel(Coord3::new(0, 5, 5), BlockType::Stone);
+        let alone = mesher.build_mesh(&memory.get_chunk(Coord3::ZERO).unwrap(), &memory);
+        assert_eq!(alone.get_vertices().len(), 6*face);
+
+        add_chunk(&memory, Coord3::new(-1, 0, 0));
+        add_chunk(&dashmap, Coord3::new(-1, 0, 0));
+        for position in [Coord3::new(-1, 5, 5), Coord3::new(-1, 6, 5)]{
+            memory.set_voxel(position, BlockType::Stone);
+            dashmap.set_voxel(position, BlockType::Stone);
+        }
+        let mesh = mesher.build_mesh(&memory.get_chunk(Coord3::ZERO).unwrap(), &memory);
+        assert_eq!(mesh.get_vertices().len(), 5*face);
+        // AO od bloku nad sasiadem przyciemnia gorna sciane, w obu swiatach tak samo
+        assert_ne!(mesh.get_vertices()[..face], alone.get_vertices()[..face]);
+        assert_eq!(mesh.get_vertices(), mesher.build_mesh(&dashmap.get(&Coord3::ZERO).unwrap(), &dashmap).get_vertices());
+    }
+}
@@ -0,0 +1,199 @@
+use nalgebra::Vector3;
+
+use crate::{chunk_master::ChunkMaster, entity::{Entity, EntityId, EntityStore}, math::{Coord3, Rng, Vec3}, pathfinding::{find_path, get_feet_voxel, is_walkable, PathSettings}};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Behaviour {
+    // chodzi do losowych pol w promieniu radius od miejsca, w ktorym stoi
+    Wander{radius: i32},
+    // idzie za celem (graczem), zatrzymuje sie blizej niz distance blokow
+    Follow{distance: i32}
+}
+
+pub struct Mob{
+    pub entity: EntityId,
+    pub behaviour: Behaviour,
+    path: Vec<Coord3>,
+    // ile tickow do ponownego szukania sciezki
+    repath_in: u32
+}
+
+// zachowania stworow, steruja predkoscia ich obiektow w EntityStore
+pub struct MobController{
+    mobs: Vec<Mob>,
+    rng: Rng,
+    pub path_settings: PathSettings,
+    pub walk_speed: f32
+}
+impl Default for MobController {
+    fn default() -> Self {
+        MobController::new(MobController::DEFAULT_SEED)
+    }
+}
+impl MobController {
+    pub const DEFAULT_SEED: u64 = 2137;
+    // stwor ma dwa bloki wysokosci, jak w is_walkable
+    pub const HALF_EXTENTS: Vec3 = Vec3::new(0.3, 0.9, 0.3);
+    const REPATH_TICKS: u32 = 40;
+    const MAX_WANDER_WAIT: i32 = 120;
+    // w takiej odleglosci od srodka pola uznajemy, ze stwor do niego doszedl
+    const WAYPOINT_DISTANCE: f32 = 0.2;
+
+    pub fn new(seed: u64) -> Self{
+        MobController{
+            mobs: Vec::new(),
+            rng: Rng::new(seed),
+            path_settings: PathSettings::default(),
+            walk_speed: 3.0
+        }
+    }
+    pub fn spawn(&mut self, entities: &mut EntityStore, position: Vec3, behaviour: Behaviour) -> EntityId{
+        let mut entity = Entity::new(position, MobController::HALF_EXTENTS);
+        entity.color = Vector3::new(0.2, 0.6, 0.3);
+        let id = entities.spawn(entity);
+        self.mobs.push(Mob{entity: id, behaviour, path: Vec::new(), repath_in: 0});
+        id
+    }
+    pub fn get_mobs(&self) -> &[Mob]{
+        &self.mobs
+    }
+    // losowe pole do chodzenia w okolicy, None gdy w wylosowanej kolumnie nie da sie stanac
+    fn pick_wander_goal(&mut self, feet: Coord3, radius: i32, world: &impl ChunkMaster) -> Option<Coord3>{
+        let x = feet.x+self.rng.range(2*radius+1)-radius;
+        let z = feet.z+self.rng.range(2*radius+1)-radius;
+        (-self.path_settings.max_drop..=self.path_settings.max_step_up).rev()
+            .map(|dy| Coord3::new(x, feet.y+dy, z))
+            .find(|position| is_walkable(*position, world))
+    }
+    // target to pole, na ktorym stoi gracz
+    pub fn tick(&mut self, entities: &mut EntityStore, world: &impl ChunkMaster, target: Coord3){
+        self.mobs.retain(|mob| entities.get(mob.entity).is_some());
+        let jump_speed = entities.physics.jump_speed;
+        for index in 0..self.mobs.len(){
+            let entity_id = self.mobs[index].entity;
+            let feet = get_feet_voxel(&entities.get(entity_id).unwrap().get_aabb());
+            let mob = &mut self.mobs[index];
+            mob.repath_in = mob.repath_in.saturating_sub(1);
+            let goal = match mob.behaviour {
+                Behaviour::Follow{distance} => {
+                    let offset = target-feet;
+                    if offset.x*offset.x+offset.z*offset.z <= distance*distance && offset.y.abs() <= 1{
+                        mob.path.clear();
+                        None
+                    }
+                    else if mob.repath_in == 0 || mob.path.is_empty(){
+                        Some(target)
+                    }
+                    else{
+                        None
+                    }
+                }
+                Behaviour::Wander{radius} => {
+                    if mob.path.is_empty() && mob.repath_in == 0{
+                        let goal = self.pick_wander_goal(feet, radius, world);
+                        let wait = self.rng.range(MobController::MAX_WANDER_WAIT) as u32;
+                        self.mobs[index].repath_in = MobController::REPATH_TICKS+wait;
+                        goal
+                    }
+                    else{
+                        None
+                    }
+                }
+            };
+            let settings = self.path_settings;
+            let mob = &mut self.mobs[index];
+            if let Some(goal) = goal{
+                mob.path = find_path(feet, goal, world, &settings).unwrap_or_default();
+                if matches!(mob.behaviour, Behaviour::Follow{..}){
+                    mob.repath_in = MobController::REPATH_TICKS;
+                }
+            }
+
+            let entity = entities.get_mut(entity_id).unwrap();
+            let position = entity.transform.position;
+            let horizontal = |waypoint: Coord3| Vec3::new(waypoint.x as f32-position.x, 0.0, waypoint.z as f32-position.z);
+            while mob.path.first().is_some_and(|waypoint| horizontal(*waypoint).magnitude() < MobController::WAYPOINT_DISTANCE){
+                mob.path.remove(0);
+            }
+            match mob.path.first() {
+                Some(waypoint) => {
+                    let direction = horizontal(*waypoint).normalize()*self.walk_speed;
+                    entity.velocity.x = direction.x;
+                    entity.velocity.z = direction.z;
+                    if waypoint.y > feet.y && entity.on_ground{
+                        entity.velocity.y = jump_speed;
+                    }
+                }
+                None => {
+                    entity.velocity.x = 0.0;
+                    entity.velocity.z = 0.0;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block::BlockType, chunk_master::MemoryWorld};
+
+    use super::*;
+
+    // podloga na y=0 i stopien na x>=8
+    fn test_world() -> MemoryWorld{
+        let world = MemoryWorld::default();
+        world.fill(Coord3::ZERO, Coord3::new(19, 0, 19), BlockType::Stone);
+        world.fill(Coord3::new(8, 1, 0), Coord3::new(19, 1, 19), BlockType::Stone);
+        world
+    }
+
+    fn run(mobs: &mut MobController, entities: &mut EntityStore, world: &MemoryWorld, target: Coord3, ticks: usize){
+        for _ in 0..ticks{
+            mobs.tick(entities, world, target);
+            entities.tick(1.0/60.0, world);
+        }
+    }
+
+    #[test]
+    fn following_mob_climbs_the_step_and_stops_near_target(){
+        let world = test_world();
+        let mut entities = EntityStore::default();
+        let mut mobs = MobController::default();
+        let id = mobs.spawn(&mut entities, Vec3::new(2.0, 2.0, 2.0), Behaviour::Follow{distance: 2});
+        let target = Coord3::new(14, 2, 10);
+        run(&mut mobs, &mut entities, &world, target, 600);
+        let entity = entities.get(id).unwrap();
+        let feet = get_feet_voxel(&entity.get_aabb());
+        assert_eq!(feet.y, 2);
+        assert!((feet.x-target.x).pow(2)+(feet.z-target.z).pow(2) <= 4);
+        assert_eq!(entity.velocity.x, 0.0);
+    }
+
+    #[test]
+    fn wandering_mob_stays_on_walkable_ground(){
+        let world = test_world();
+        let mut entities = EntityStore::default();
+        let mut mobs = MobController::new(5);
+        let id = mobs.spawn(&mut entities, Vec3::new(4.0, 2.0, 4.0), Behaviour::Wander{radius: 3});
+        let mut moved = false;
+        for _ in 0..20{
+            run(&mut mobs, &mut entities, &world, Coord3::ZERO, 30);
+            let entity = entities.get(id).unwrap();
+            let feet = get_feet_voxel(&entity.get_aabb());
+            moved |= feet != Coord3::new(4, 1, 4);
+            assert!(world.get_voxel(feet.lower()).is_solid() || !entity.on_ground);
+        }
+        assert!(moved);
+    }
+
+    #[test]
+    fn mobs_of_despawned_entities_are_removed(){
+        let world = test_world();
+        let mut entities = EntityStore::default();
+        let mut mobs = MobController::default();
+        let id = mobs.spawn(&mut entities, Vec3::new(4.0, 2.0, 4.0), Behaviour::Wander{radius: 3});
+        entities.despawn(id);
+        mobs.tick(&mut entities, &world, Coord3::ZERO);
+        assert!(mobs.get_mobs().is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use std::{io, net::TcpStream, sync::mpsc::{self, Receiver, TryRecvError}, thread};
+
+use crate::protocol::Message;
+
+// polaczenie TCP: odbieranie w osobnym watku, wysylanie bezposrednio
+pub struct Connection{
+    stream: TcpStream,
+    incoming: Receiver<Message>,
+    closed: bool
+}
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Connection>{
+        stream.set_nodelay(true)?;
+        let mut reader = stream.try_clone()?;
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move ||{
+            while let Ok(message) = Message::read_from(&mut reader){
+                if sender.send(message).is_err(){
+                    break;
+                }
+            }
+        });
+        Ok(Connection{stream, incoming, closed: false})
+    }
+    pub fn send(&mut self, message: &Message) -> io::Result<()>{
+        let result = message.write_to(&mut self.stream);
+        if result.is_err(){
+            self.closed = true;
+        }
+        result
+    }
+    // wiadomosci, ktore przyszly od ostatniego wywolania
+    pub fn receive(&mut self) -> Vec<Message>{
+        let mut messages = Vec::new();
+        loop {
+            match self.incoming.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+    // czeka na jedna wiadomosc, np. odpowiedz na Handshake
+    pub fn receive_blocking(&mut self) -> io::Result<Message>{
+        self.incoming.recv().map_err(|_|{
+            self.closed = true;
+            io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed")
+        })
+    }
+    pub fn close(&mut self){
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+        self.closed = true;
+    }
+    pub fn is_closed(&self) -> bool{
+        self.closed
+    }
+}
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap}};
+
+use crate::{chunk_master::ChunkMaster, math::{Aabb, Coord3}};
+
+#[derive(Clone, Copy)]
+pub struct PathSettings{
+    // o ile blokow mozna wejsc wyzej jednym ruchem
+    pub max_step_up: i32,
+    pub max_drop: i32,
+    // po tylu odwiedzonych polach szukanie sie poddaje
+    pub max_nodes: usize
+}
+impl Default for PathSettings {
+    fn default() -> Self {
+        PathSettings{
+            max_step_up: 1,
+            max_drop: 3,
+            max_nodes: 4096
+        }
+    }
+}
+
+const MOVE_COST: u32 = 10;
+const STEP_UP_COST: u32 = 5;
+const DROP_COST: u32 = 2;
+
+// pole, na ktorym moze stac stwor wysoki na dwa bloki: pod nim cos stalego, nad nim dwa wolne
+pub fn is_walkable(position: Coord3, world: &impl ChunkMaster) -> bool{
+    world.get_voxel(position.lower()).is_solid()
+        && is_clear(position, 2, world)
+}
+
+// pole, w ktorym sa stopy ciala o danym aabb
+pub fn get_feet_voxel(aabb: &Aabb) -> Coord3{
+    let center = aabb.get_center();
+    Coord3::new(center.x.round() as i32, (aabb.min.y+0.5+1e-3).floor() as i32, center.z.round() as i32)
+}
+
+// height wolnych blokow od position w gore
+fn is_clear(position: Coord3, height: i32, world: &impl ChunkMaster) -> bool{
+    (0..height).all(|dy| !world.get_voxel(position+Coord3::new(0, dy, 0)).is_solid())
+}
+
+fn get_neighbors(position: Coord3, world: &impl ChunkMaster, settings: &PathSettings) -> Vec<(Coord3, u32)>{
+    let mut neighbors = Vec::new();
+    for offset in Coord3::neighbors_into_iter().filter(|offset| offset.y == 0){
+        let side = position+offset;
+        for dy in -settings.max_drop..=settings.max_step_up{
+            let target = side+Coord3::new(0, dy, 0);
+            if !is_walkable(target, world){
+                continue;
+            }
+            let passable = if dy > 0 {
+                // skok: nad glowa musi byc miejsce
+                is_clear(position+Coord3::new(0, 2, 0), dy, world)
+            } else {
+                // spadek: cala kolumna nad celem az do wysokosci glowy jest wolna
+                is_clear(target+Coord3::new(0, 2, 0), -dy, world)
+            };
+            if passable{
+                let cost = MOVE_COST+if dy > 0 {STEP_UP_COST*dy as u32} else {DROP_COST*(-dy) as u32};
+                neighbors.push((target, cost));
+            }
+        }
+    }
+    neighbors
+}
+
+fn heuristic(from: Coord3, to: Coord3) -> u32{
+    // kazdy ruch przesuwa o jeden blok w poziomie i kosztuje co najmniej MOVE_COST
+    ((from.x-to.x).unsigned_abs()+(from.z-to.z).unsigned_abs())*MOVE_COST
+}
+
+// A* po polach, na ktorych mozna stac, zwraca kolejne pola od start (bez niego) do goal
+pub fn find_path(start: Coord3, goal: Coord3, world: &impl ChunkMaster, settings: &PathSettings) -> Option<Vec<Coord3>>{
+    if !is_walkable(goal, world){
+        return None;
+    }
+    // w kopcu sa indeksy do nodes, bo Coord3 nie ma porzadku
+    let mut nodes: Vec<Coord3> = vec![start];
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((heuristic(start, goal), 0usize)));
+    let mut costs: HashMap<Coord3, (u32, Option<Coord3>)> = HashMap::new();
+    costs.insert(start, (0, None));
+    let mut visited = 0;
+
+    while let Some(Reverse((_, index))) = open.pop(){
+        let current = nodes[index];
+        if current == goal{
+            let mut path = Vec::new();
+            let mut position = goal;
+            while let Some((_, Some(parent))) = costs.get(&position){
+                path.push(position);
+                position = *parent;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        visited += 1;
+        if visited > settings.max_nodes{
+            return None;
+        }
+        let cost = costs[&current].0;
+        for (neighbor, step_cost) in get_neighbors(current, world, settings){
+            let new_cost = cost+step_cost;
+            if costs.get(&neighbor).is_some_and(|(known, _)| *known <= new_cost){
+                continue;
+            }
+            costs.insert(neighbor, (new_cost, Some(current)));
+            nodes.push(neighbor);
+            open.push(Reverse((new_cost+heuristic(neighbor, goal), nodes.len()-1)));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block::BlockType, chunk_master::MemoryWorld};
+
+    use super::*;
+
+    // kamienna podloga 20x20 na y=0, stwory stoja na y=1
+    fn world_with(blocks: &[(Coord3, BlockType)]) -> MemoryWorld{
+        let world = MemoryWorld::default();
+        world.fill(Coord3::ZERO, Coord3::new(19, 0, 19), BlockType::Stone);
+        for (position, block) in blocks{
+            world.place(*position, *block);
+        }
+        world
+    }
+
+    // sciana wzdluz z na x=5, wysoka na height blokow, z opcjonalna przerwa
+    fn wall(height: i32, gap: Option<i32>) -> Vec<(Coord3, BlockType)>{
+        let mut blocks = Vec::new();
+        for z in 0..20{
+            if Some(z) == gap{
+                continue;
+            }
+            for y in 1..=height{
+                blocks.push((Coord3::new(5, y, z), BlockType::Stone));
+            }
+        }
+        blocks
+    }
+
+    fn assert_connected(start: Coord3, path: &[Coord3], world: &MemoryWorld){
+        let mut previous = start;
+        for position in path{
+            assert!(is_walkable(*position, world));
+            assert_eq!((position.x-previous.x).abs()+(position.z-previous.z).abs(), 1);
+            previous = *position;
+        }
+    }
+
+    #[test]
+    fn straight_path_on_flat_ground(){
+        let world = world_with(&[]);
+        let start = Coord3::new(1, 1, 1);
+        let path = find_path(start, Coord3::new(8, 1, 1), &world, &PathSettings::default()).unwrap();
+        assert_eq!(path.len(), 7);
+        assert_eq!(path.last(), Some(&Coord3::new(8, 1, 1)));
+        assert_connected(start, &path, &world);
+    }
+
+    #[test]
+    fn path_goes_around_high_wall_through_gap(){
+        let world = world_with(&wall(2, Some(15)));
+        let start = Coord3::new(2, 1, 5);
+        let path = find_path(start, Coord3::new(8, 1, 5), &world, &PathSettings::default()).unwrap();
+        assert!(path.contains(&Coord3::new(5, 1, 15)));
+        assert_connected(start, &path, &world);
+
+        let closed = world_with(&wall(2, None));
+        assert!(find_path(start, Coord3::new(8, 1, 5), &closed, &PathSettings::default()).is_none());
+    }
+
+    #[test]
+    fn path_steps_over_low_wall(){
+        let world = world_with(&wall(1, None));
+        let start = Coord3::new(2, 1, 5);
+        let path = find_path(start, Coord3::new(8, 1, 5), &world, &PathSettings::default()).unwrap();
+        assert!(path.contains(&Coord3::new(5, 2, 5)));
+        assert_eq!(path.len(), 6);
+
+        // bez miejsca nad glowa nie da sie wskoczyc
+        let mut blocks = wall(1, None);
+        for z in 0..20{
+            blocks.push((Coord3::new(4, 3, z), BlockType::Stone));
+        }
+        let ceiling = world_with(&blocks);
+        assert!(find_path(start, Coord3::new(8, 1, 5), &ceiling, &PathSettings::default()).is_none());
+    }
+
+    #[test]
+    fn drops_are_limited(){
+        // wieza, z ktorej mozna zejsc tylko po schodach
+        let mut blocks = Vec::new();
+        for y in 1..=4{
+            blocks.push((Coord3::new(10, y, 10), BlockType::Stone));
+        }
+        let world = world_with(&blocks);
+        let settings = PathSettings::default();
+        assert!(find_path(Coord3::new(10, 5, 10), Coord3::new(12, 1, 10), &world, &settings).is_none());
+
+        blocks.push((Coord3::new(11, 1, 10), BlockType::Stone));
+        let stairs = world_with(&blocks);
+        let path = find_path(Coord3::new(10, 5, 10), Coord3::new(12, 1, 10), &stairs, &settings).unwrap();
+        assert_eq!(path, vec![Coord3::new(11, 2, 10), Coord3::new(12, 1, 10)]);
+        assert!(!is_walkable(Coord3::new(10, 3, 10), &stairs));
+    }
+}
@@ -0,0 +1,110 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use crate::{chunk::Chunk, math::Coord3};
+
+// swiat zapisany w katalogu: world.txt z ziarnem i jeden plik na zmieniony chunk
+pub struct WorldStorage{
+    dir: PathBuf
+}
+impl WorldStorage {
+    const META_FILE: &'static str = "world.txt";
+    const CHUNK_EXTENSION: &'static str = "chunk";
+
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<WorldStorage>{
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(WorldStorage{dir: dir.as_ref().to_path_buf()})
+    }
+    pub fn get_dir(&self) -> &Path{
+        &self.dir
+    }
+    fn get_chunk_path(&self, chunk_position: Coord3) -> PathBuf{
+        self.dir.join(format!("{}.{}.{}.{}", chunk_position.x, chunk_position.y, chunk_position.z, WorldStorage::CHUNK_EXTENSION))
+    }
+    // ziarno zapisanego swiata, None dla nowego katalogu
+    pub fn load_seed(&self) -> io::Result<Option<u32>>{
+        let text = match fs::read_to_string(self.dir.join(WorldStorage::META_FILE)) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error)
+        };
+        let seed = text.lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(key, _)| key.trim() == "seed")
+            .and_then(|(_, value)| value.trim().parse().ok());
+        match seed {
+            Some(seed) => Ok(Some(seed)),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, "world.txt has no seed"))
+        }
+    }
+    pub fn save_seed(&self, seed: u32) -> io::Result<()>{
+        fs::write(self.dir.join(WorldStorage::META_FILE), format!("seed = {seed}\n"))
+    }
+    // zapisany swiat zachowuje swoje ziarno, nowy dostaje podane
+    pub fn load_or_save_seed(&self, seed: u32) -> io::Result<u32>{
+        match self.load_seed()? {
+            Some(saved) => Ok(saved),
+            None => self.save_seed(seed).map(|_| seed)
+        }
+    }
+    pub fn save_chunk(&self, chunk: &Chunk) -> io::Result<()>{
+        // najpierw do pliku tymczasowego, zeby przerwany zapis nie zepsul chunka
+        let path = self.get_chunk_path(chunk.get_chunk_position());
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, chunk.encode())?;
+        fs::rename(temporary, path)
+    }
+    pub fn load_chunk(&self, chunk_position: Coord3) -> io::Result<Option<Chunk>>{
+        let bytes = match fs::read(self.get_chunk_path(chunk_position)) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error)
+        };
+        Chunk::decode(chunk_position, &bytes)
+            .map(Some)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("chunk {chunk_position}: {error}")))
+    }
+    // pozycje wszystkich zapisanych chunkow
+    pub fn list_chunks(&self) -> io::Result<Vec<Coord3>>{
+        let mut chunks = Vec::new();
+        for entry in fs::read_dir(&self.dir)?{
+            let name = entry?.file_name();
+            let Some(stem) = name.to_str().and_then(|name| name.strip_suffix(".chunk")) else {
+                continue;
+            };
+            let coords: Vec<i32> = stem.split('.').filter_map(|value| value.parse().ok()).collect();
+            if let [x, y, z] = coords[..]{
+                chunks.push(Coord3::new(x, y, z));
+            }
+        }
+        chunks.sort_by_key(|position| (position.x, position.y, position.z));
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block::BlockType, test_util::TempDir};
+
+    use super::*;
+
+    #[test]
+    fn chunks_and_seed_survive_reopening(){
+        let dir = TempDir::new("storage");
+        let storage = WorldStorage::open(&dir).unwrap();
+        assert_eq!(storage.load_seed().unwrap(), None);
+        assert_eq!(storage.load_or_save_seed(2137).unwrap(), 2137);
+        assert_eq!(storage.load_or_save_seed(7).unwrap(), 2137);
+
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(Coord3::new(-1, 0, 2));
+        chunk.set_voxel(Coord3::new(1, 2, 3), BlockType::Sand);
+        storage.save_chunk(&chunk).unwrap();
+
+        let reopened = WorldStorage::open(&dir).unwrap();
+        assert_eq!(reopened.load_seed().unwrap(), Some(2137));
+        assert_eq!(reopened.list_chunks().unwrap(), vec![Coord3::new(-1, 0, 2)]);
+        let loaded = reopened.load_chunk(Coord3::new(-1, 0, 2)).unwrap().unwrap();
+        assert_eq!(loaded.get_voxel(Coord3::new(1, 2, 3)), BlockType::Sand);
+        assert!(reopened.load_chunk(Coord3::ZERO).unwrap().is_none());
+    }
+}
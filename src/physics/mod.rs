@@ -0,0 +1,232 @@
+use crate::{block::BlockType, chunk_master::ChunkMaster, math::{Aabb, Coord3, Vec3}};
+
+#[derive(Clone, Copy)]
+pub struct PhysicsSettings{
+    pub gravity: f32,
+    pub jump_speed: f32,
+    pub walk_speed: f32,
+    pub terminal_velocity: f32,
+    // na jaka wysokosc gracz wchodzi bez skakania
+    pub step_height: f32,
+    pub swim_speed: f32,
+    // w wodzie grawitacja jest slabsza, a predkosc pionowa wygasa
+    pub water_gravity: f32,
+    pub water_drag: f32
+}
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        PhysicsSettings{
+            gravity: 30.0,
+            jump_speed: 9.0,
+            walk_speed: 6.0,
+            terminal_velocity: 60.0,
+            step_height: 1.0,
+            swim_speed: 4.0,
+            water_gravity: 6.0,
+            water_drag: 4.0
+        }
+    }
+}
+
+pub struct MoveResult{
+    pub motion: Vec3,
+    // ktore osie (x, y, z) zostaly zatrzymane przez bloki
+    pub blocked: [bool; 3]
+}
+
+const EPSILON: f32 = 1e-4;
+
+fn get_axis(v: Vec3, axis: usize) -> f32{
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z
+    }
+}
+
+fn with_axis(v: Vec3, axis: usize, value: f32) -> Vec3{
+    match axis {
+        0 => Vec3::new(value, v.y, v.z),
+        1 => Vec3::new(v.x, value, v.z),
+        _ => Vec3::new(v.x, v.y, value)
+    }
+}
+
+fn translate(aabb: &Aabb, offset: Vec3) -> Aabb{
+    Aabb::new(aabb.min+offset, aabb.max+offset)
+}
+
+// woksel c zajmuje [c-0.5, c+0.5]
+fn voxel_range(min: f32, max: f32) -> std::ops::RangeInclusive<i32>{
+    (min+0.5).floor() as i32..=(max+0.5).floor() as i32
+}
+
+pub fn get_voxel_aabb(position: Coord3) -> Aabb{
+    let center = Vec3::new(position.x as f32, position.y as f32, position.z as f32);
+    Aabb::from_center(center, Vec3::new(0.5, 0.5, 0.5))
+}
+
+// wszystkie bloki spelniajace warunek, ktore dotykaja aabb
+fn collect_voxels(aabb: &Aabb, world: &impl ChunkMaster, filter: impl Fn(BlockType) -> bool) -> Vec<Coord3>{
+    let mut voxels = Vec::new();
+    for x in voxel_range(aabb.min.x, aabb.max.x){
+        for y in voxel_range(aabb.min.y, aabb.max.y){
+            for z in voxel_range(aabb.min.z, aabb.max.z){
+                let position = Coord3::new(x, y, z);
+                if filter(world.get_voxel(position)){
+                    voxels.push(position);
+                }
+            }
+        }
+    }
+    voxels
+}
+
+pub fn is_in_water(aabb: &Aabb, world: &impl ChunkMaster) -> bool{
+    let shrunk = Aabb::new(aabb.min+Vec3::new(EPSILON, EPSILON, EPSILON), aabb.max-Vec3::new(EPSILON, EPSILON, EPSILON));
+    !collect_voxels(&shrunk, world, |block| block == BlockType::Water).is_empty()
+}
+
+// przycina ruch wzdluz jednej osi tak, zeby aabb nie weszlo w zaden z colliderow
+fn clip_axis(aabb: &Aabb, colliders: &[Aabb], axis: usize, motion: f32) -> f32{
+    let mut motion = motion;
+    for collider in colliders{
+        let overlaps = (0..3).filter(|other| *other != axis).all(|other|
+            get_axis(aabb.min, other) < get_axis(collider.max, other)-EPSILON
+            && get_axis(aabb.max, other) > get_axis(collider.min, other)+EPSILON
+        );
+        if !overlaps{
+            continue;
+        }
+        if motion > 0.0 && get_axis(collider.min, axis) >= get_axis(aabb.max, axis)-EPSILON{
+            motion = motion.min(get_axis(collider.min, axis)-get_axis(aabb.max, axis));
+        }
+        else if motion < 0.0 && get_axis(collider.max, axis) <= get_axis(aabb.min, axis)+EPSILON{
+            motion = motion.max(get_axis(collider.max, axis)-get_axis(aabb.min, axis));
+        }
+    }
+    motion
+}
+
+// przesuwa aabb o motion osia po osi (najpierw y, potem x i z), zatrzymujac je na pelnych blokach
+pub fn move_aabb(aabb: &Aabb, motion: Vec3, world: &impl ChunkMaster) -> MoveResult{
+    let swept = Aabb::new(
+        Vec3::new(aabb.min.x+motion.x.min(0.0), aabb.min.y+motion.y.min(0.0), aabb.min.z+motion.z.min(0.0)),
+        Vec3::new(aabb.max.x+motion.x.max(0.0), aabb.max.y+motion.y.max(0.0), aabb.max.z+motion.z.max(0.0))
+    );
+    let colliders: Vec<Aabb> = collect_voxels(&swept, world, |block| block.is_solid())
+        .into_iter()
+        .map(get_voxel_aabb)
+        .collect();
+    let mut current = *aabb;
+    let mut applied = Vec3::ZERO;
+    let mut blocked = [false; 3];
+    for axis in [1, 0, 2]{
+        let wanted = get_axis(motion, axis);
+        if wanted == 0.0{
+            continue;
+        }
+        let clipped = clip_axis(&current, &colliders, axis, wanted);
+        blocked[axis] = (clipped-wanted).abs() > EPSILON;
+        let offset = with_axis(Vec3::ZERO, axis, clipped);
+        current = translate(&current, offset);
+        applied += offset;
+    }
+    MoveResult{motion: applied, blocked}
+}
+
+// jak move_aabb, ale gdy cialo stoi na ziemi i uderza w sciane, probuje na nia wejsc
+pub fn move_with_step(aabb: &Aabb, motion: Vec3, step_height: f32, on_ground: bool, world: &impl ChunkMaster) -> MoveResult{
+    let result = move_aabb(aabb, motion, world);
+    if !on_ground || step_height <= 0.0 || !(result.blocked[0] || result.blocked[2]){
+        return result;
+    }
+    let up = move_aabb(aabb, Vec3::new(0.0, step_height, 0.0), world);
+    let lifted = translate(aabb, up.motion);
+    let horizontal = move_aabb(&lifted, Vec3::new(motion.x, 0.0, motion.z), world);
+    let moved = translate(&lifted, horizontal.motion);
+    let down = move_aabb(&moved, Vec3::new(0.0, -up.motion.y+motion.y.min(0.0), 0.0), world);
+    let stepped = up.motion+horizontal.motion+down.motion;
+
+    let distance = |v: Vec3| v.x.powi(2)+v.z.powi(2);
+    if distance(stepped) <= distance(result.motion)+EPSILON{
+        return result;
+    }
+    MoveResult{
+        motion: stepped,
+        blocked: [horizontal.blocked[0], down.blocked[1], horizontal.blocked[2]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_master::MemoryWorld;
+
+    use super::*;
+
+    fn floor(y: i32) -> Vec<(Coord3, BlockType)>{
+        let mut blocks = Vec::new();
+        for x in -5..6{
+            for z in -5..6{
+                blocks.push((Coord3::new(x, y, z), BlockType::Stone));
+            }
+        }
+        blocks
+    }
+
+    fn body_at(x: f32, y: f32, z: f32) -> Aabb{
+        Aabb::new(Vec3::new(x-0.3, y, z-0.3), Vec3::new(x+0.3, y+1.8, z+0.3))
+    }
+
+    #[test]
+    fn falling_body_stops_on_floor(){
+        let world = MemoryWorld::with_blocks(floor(0));
+        let result = move_aabb(&body_at(0.0, 3.0, 0.0), Vec3::new(0.0, -10.0, 0.0), &world);
+        assert!(result.blocked[1]);
+        assert!((result.motion.y+2.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn walls_stop_horizontal_motion_but_allow_sliding(){
+        let mut blocks = floor(0);
+        for y in 1..3{
+            for z in -5..6{
+                blocks.push((Coord3::new(2, y, z), BlockType::Stone));
+            }
+        }
+        let world = MemoryWorld::with_blocks(blocks);
+        let result = move_aabb(&body_at(0.0, 0.5, 0.0), Vec3::new(3.0, 0.0, 1.0), &world);
+        assert!(result.blocked[0] && !result.blocked[2]);
+        assert!((result.motion.x-1.2).abs() < 1e-3);
+        assert!((result.motion.z-1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn body_steps_onto_single_block_only(){
+        let mut blocks = floor(0);
+        blocks.push((Coord3::new(1, 1, 0), BlockType::Stone));
+        blocks.push((Coord3::new(0, 1, 2), BlockType::Stone));
+        blocks.push((Coord3::new(0, 2, 2), BlockType::Stone));
+        let world = MemoryWorld::with_blocks(blocks);
+
+        let step = move_with_step(&body_at(0.0, 0.5, 0.0), Vec3::new(1.0, -0.1, 0.0), 1.0, true, &world);
+        assert!((step.motion.y-1.0).abs() < 1e-3);
+        assert!((step.motion.x-1.0).abs() < 1e-3);
+
+        let wall = move_with_step(&body_at(0.0, 0.5, 0.0), Vec3::new(0.0, -0.1, 2.0), 1.0, true, &world);
+        assert!(wall.blocked[2]);
+        assert!(wall.motion.y.abs() < 1e-3);
+
+        let airborne = move_with_step(&body_at(0.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 1.0, false, &world);
+        assert!(airborne.blocked[0]);
+    }
+
+    #[test]
+    fn water_is_detected_but_not_solid(){
+        let world = MemoryWorld::with_blocks([(Coord3::new(0, 1, 0), BlockType::Water)]);
+        assert!(is_in_water(&body_at(0.0, 0.5, 0.0), &world));
+        assert!(!is_in_water(&body_at(0.0, 1.5, 0.0), &world));
+        let result = move_aabb(&body_at(0.0, 3.0, 0.0), Vec3::new(0.0, -2.0, 0.0), &world);
+        assert!(!result.blocked[1]);
+    }
+}
@@ -1,32 +1,134 @@
-use crate::Quat;
-use crate::Vec3;
+use crate::block::BlockType;
+use crate::chunk_master::ChunkMaster;
+use crate::input::{Action, InputState};
+use crate::math::{Aabb, Coord3};
+use crate::physics::{self, PhysicsSettings};
+use crate::math::Quat;
+use crate::math::Vec3;
 
+#[derive(Clone)]
 pub struct Player{
     position: Vec3,
+    // pozycja z poprzedniego kroku symulacji, do interpolacji przy renderowaniu
+    previous_position: Vec3,
     rotation: Quat,
+    velocity: Vec3,
+    on_ground: bool,
+    in_water: bool,
+    // latanie bez kolizji i grawitacji
+    flying: bool,
+    physics: PhysicsSettings
 }
 impl Player {
+    // jak daleko gracz siega przy wskazywaniu blokow
+    pub const REACH: f32 = 8.0;
+    pub const WIDTH: f32 = 0.6;
+    pub const HEIGHT: f32 = 1.8;
+    // pozycja gracza to pozycja oczu (kamery)
+    pub const EYE_HEIGHT: f32 = 1.6;
+    // dluzsze klatki sa dzielone na kroki, zeby gracz nie przelatywal przez bloki
+    const MAX_PHYSICS_STEP: f32 = 1.0/60.0;
     pub fn new() -> Self{
         Player{
             position: Vec3::ZERO,
-            rotation: Quat::IDENTITY
+            previous_position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            velocity: Vec3::ZERO,
+            on_ground: false,
+            in_water: false,
+            flying: true,
+            physics: PhysicsSettings::default()
         }
     }
-    pub fn go(&mut self, w: bool, s: bool, a:bool, d:bool, delta_time: f32){
+    pub fn update(&mut self, input: &InputState, delta_time: f32, world: &impl ChunkMaster){
+        self.previous_position = self.position;
+        if self.flying{
+            self.go(input, delta_time);
+            return;
+        }
+        let mut remaining = delta_time;
+        while remaining > 0.0{
+            let step = remaining.min(Player::MAX_PHYSICS_STEP);
+            self.physics_step(input, step, world);
+            remaining -= step;
+        }
+    }
+    fn physics_step(&mut self, input: &InputState, delta_time: f32, world: &impl ChunkMaster){
+        let settings = self.physics;
+        let look = self.get_rotation().to_direction(Vec3::FORWARD);
+        let mut forward = Vec3::new(look.x, 0.0, look.z);
+        if forward.magnitude() > 1e-6{
+            forward = forward.normalize();
+        }
+        let right = forward.cross(Vec3::UP);
+        let mut wish = Vec3::ZERO;
+        if input.is_held(Action::MoveForward) {wish += forward;}
+        if input.is_held(Action::MoveBack) {wish -= forward;}
+        if input.is_held(Action::MoveLeft) {wish -= right;}
+        if input.is_held(Action::MoveRight) {wish += right;}
+        if wish.magnitude() > 1e-6{
+            wish = wish.normalize();
+        }
+
+        self.in_water = physics::is_in_water(&self.get_aabb(), world);
+        let speed = if self.in_water {settings.swim_speed} else {settings.walk_speed};
+        self.velocity.x = wish.x*speed;
+        self.velocity.z = wish.z*speed;
+        if self.in_water{
+            // wypor: w wodzie gracz opada powoli, a skok pozwala plynac w gore
+            self.velocity.y -= settings.water_gravity*delta_time;
+            if input.is_held(Action::Jump){
+                self.velocity.y += (settings.water_gravity+settings.gravity)*delta_time;
+            }
+            self.velocity.y *= (1.0-settings.water_drag*delta_time).max(0.0);
+            self.velocity.y = self.velocity.y.clamp(-settings.swim_speed, settings.swim_speed);
+        }
+        else{
+            if input.is_held(Action::Jump) && self.on_ground{
+                self.velocity.y = settings.jump_speed;
+            }
+            self.velocity.y = (self.velocity.y-settings.gravity*delta_time).max(-settings.terminal_velocity);
+        }
+
+        let result = physics::move_with_step(&self.get_aabb(), self.velocity*delta_time, settings.step_height, self.on_ground, world);
+        self.position += result.motion;
+        self.on_ground = result.blocked[1] && self.velocity.y < 0.0;
+        if result.blocked[1]{
+            self.velocity.y = 0.0;
+        }
+    }
+    pub fn toggle_flying(&mut self){
+        self.flying = !self.flying;
+        self.velocity = Vec3::ZERO;
+        self.on_ground = false;
+    }
+    pub fn is_flying(&self) -> bool{
+        self.flying
+    }
+    pub fn is_on_ground(&self) -> bool{
+        self.on_ground
+    }
+    pub fn is_in_water(&self) -> bool{
+        self.in_water
+    }
+    pub fn get_velocity(&self) -> Vec3{
+        self.velocity
+    }
+    pub fn go(&mut self, input: &InputState, delta_time: f32){
         let vector_up: Vec3 = Vec3::UP;
         let mut player_velocity: Vec3 = Vec3::ZERO;
         let player_speed: f32 = 30.0;
         let direction: Vec3 = self.get_rotation().to_direction(Vec3::FORWARD).normalize();
-        if w{
+        if input.is_held(Action::MoveForward){
             player_velocity += direction;
         }
-        if s{
+        if input.is_held(Action::MoveBack){
             player_velocity -= direction;
         }
-        if a{
+        if input.is_held(Action::MoveLeft){
             player_velocity -= direction.cross(vector_up).normalize();
         }
-        if d{
+        if input.is_held(Action::MoveRight){
             player_velocity += direction.cross(vector_up).normalize();
         }
         self.position += player_velocity*delta_time*player_speed; 
@@ -43,10 +145,192 @@ impl Player {
     pub fn get_rotation(&self) -> Quat{
         self.rotation
     }
+    pub fn set_rotation(&mut self, rotation: Quat){
+        self.rotation = rotation;
+    }
     pub fn get_position(&self) -> Vec3{
         self.position
     }
     pub fn set_position(&mut self, position: Vec3){
         self.position = position;
+        self.previous_position = position;
+    }
+    // alpha 0 to poprzedni krok, 1 to aktualny
+    pub fn get_interpolated_position(&self, alpha: f32) -> Vec3{
+        self.previous_position+(self.position-self.previous_position)*alpha
+    }
+    pub fn get_aabb(&self) -> Aabb{
+        let min = self.position-Vec3::new(Player::WIDTH/2.0, Player::EYE_HEIGHT, Player::WIDTH/2.0);
+        Aabb::new(min, min+Vec3::new(Player::WIDTH, Player::HEIGHT, Player::WIDTH))
+    }
+    // nie mozna postawic bloku, ktory zachodzi na gracza
+    pub fn can_place_block(&self, block_position: Coord3) -> bool{
+        let center = Vec3::new(block_position.x as f32, block_position.y as f32, block_position.z as f32);
+        !self.get_aabb().intersects(&Aabb::from_center(center, Vec3::new(0.5, 0.5, 0.5)))
+    }
+}
+
+pub struct Hotbar{
+    slots: Vec<BlockType>,
+    selected: usize
+}
+impl Default for Hotbar {
+    fn default() -> Self {
+        Hotbar{
+            slots: vec![BlockType::Stone, BlockType::Dirt, BlockType::Grass, BlockType::Sand, BlockType::Water],
+            selected: 0
+        }
+    }
+}
+impl Hotbar {
+    // pusty pasek nie ma czego wybrac, wiec jest odrzucany
+    pub fn new(slots: Vec<BlockType>) -> Result<Self, String>{
+        if slots.is_empty(){
+            return Err("hotbar needs at least one slot".to_string());
+        }
+        Ok(Hotbar{
+            slots,
+            selected: 0
+        })
     }
-}
\ No newline at end of file
+    pub fn get_slots(&self) -> &Vec<BlockType>{
+        &self.slots
+    }
+    pub fn get_selected_index(&self) -> usize{
+        self.selected
+    }
+    pub fn get_selected(&self) -> BlockType{
+        self.slots[self.selected]
+    }
+    // numery spoza paska sa ignorowane
+    pub fn select(&mut self, slot: usize){
+        if slot < self.slots.len(){
+            self.selected = slot;
+        }
+    }
+    // kolko myszy przewija pasek w kolko
+    pub fn scroll(&mut self, steps: i32){
+        let len = self.slots.len() as i32;
+        self.selected = (self.selected as i32+steps).rem_euclid(len) as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_master::MemoryWorld;
+
+    use super::*;
+
+    // podloga z kamienia na y=0, woda od y=1 do y=3 dla x >= 10
+    fn test_world() -> MemoryWorld{
+        let world = MemoryWorld::with_chunks(Coord3::new(-1, -1, -1), Coord3::ZERO);
+        world.fill(Coord3::new(-20, 0, -5), Coord3::new(19, 0, 4), BlockType::Stone);
+        world.fill(Coord3::new(10, 1, -5), Coord3::new(19, 3, 4), BlockType::Water);
+        world
+    }
+
+    fn walking_player(position: Vec3) -> Player{
+        let mut player = Player::new();
+        player.toggle_flying();
+        player.set_position(position);
+        player
+    }
+
+    fn holding(actions: &[Action]) -> InputState{
+        let mut input = InputState::default();
+        for action in actions{
+            input.set_held(*action, true);
+        }
+        input
+    }
+
+    fn simulate(player: &mut Player, input: &InputState, seconds: f32, world: &MemoryWorld){
+        let mut time = 0.0;
+        while time < seconds{
+            player.update(input, 1.0/60.0, world);
+            time += 1.0/60.0;
+        }
+    }
+
+    #[test]
+    fn player_falls_and_lands_on_ground(){
+        let world = test_world();
+        let mut player = walking_player(Vec3::new(0.0, 6.0, 0.0));
+        simulate(&mut player, &InputState::default(), 2.0, &world);
+        assert!(player.is_on_ground());
+        // stopy na gornej scianie podlogi
+        assert!((player.get_aabb().min.y-0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn jump_only_from_ground(){
+        let world = test_world();
+        let mut player = walking_player(Vec3::new(0.0, 0.5+Player::EYE_HEIGHT, 0.0));
+        simulate(&mut player, &InputState::default(), 0.1, &world);
+        let jump = holding(&[Action::Jump]);
+        player.update(&jump, 1.0/60.0, &world);
+        assert!(!player.is_on_ground());
+        let mut peak: f32 = 0.0;
+        for _ in 0..30{
+            player.update(&jump, 1.0/60.0, &world);
+            peak = peak.max(player.get_aabb().min.y-0.5);
+        }
+        // v^2/2g = 81/60
+        assert!(peak > 1.2 && peak < 1.5, "{peak}");
+    }
+
+    #[test]
+    fn water_slows_fall_and_jump_swims_up(){
+        let world = test_world();
+        let mut player = walking_player(Vec3::new(12.0, 3.0+Player::EYE_HEIGHT, 0.0));
+        player.update(&InputState::default(), 1.0/60.0, &world);
+        assert!(player.is_in_water());
+        simulate(&mut player, &InputState::default(), 0.5, &world);
+        assert!(player.get_velocity().y >= -player.physics.swim_speed);
+        let before = player.get_position().y;
+        simulate(&mut player, &holding(&[Action::Jump]), 0.5, &world);
+        assert!(player.get_position().y > before);
+    }
+
+    #[test]
+    fn flying_ignores_gravity(){
+        let world = test_world();
+        let mut player = Player::new();
+        player.set_position(Vec3::new(0.0, 10.0, 0.0));
+        simulate(&mut player, &InputState::default(), 1.0, &world);
+        assert!(player.is_flying());
+        assert_eq!(player.get_position().y, 10.0);
+    }
+
+    #[test]
+    fn blocks_cannot_be_placed_inside_player(){
+        let mut player = Player::new();
+        player.set_position(Vec3::new(0.0, 1.1, 0.0));
+        // stopy gracza stoja na y=-0.5, czyli na gornej scianie bloku (0,-1,0)
+        assert!(player.can_place_block(Coord3::new(0, -1, 0)));
+        assert!(!player.can_place_block(Coord3::new(0, 0, 0)));
+        assert!(!player.can_place_block(Coord3::new(0, 1, 0)));
+        assert!(player.can_place_block(Coord3::new(0, 2, 0)));
+        assert!(player.can_place_block(Coord3::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn hotbar_scroll_wraps_around(){
+        let mut hotbar = Hotbar::default();
+        hotbar.scroll(-1);
+        assert_eq!(hotbar.get_selected_index(), hotbar.get_slots().len()-1);
+        hotbar.scroll(2);
+        assert_eq!(hotbar.get_selected(), BlockType::Dirt);
+        hotbar.select(100);
+        assert_eq!(hotbar.get_selected(), BlockType::Dirt);
+    }
+
+    #[test]
+    fn hotbar_needs_at_least_one_slot(){
+        assert!(Hotbar::new(Vec::new()).is_err());
+        let mut hotbar = Hotbar::new(vec![BlockType::Sand]).unwrap();
+        hotbar.scroll(-3);
+        hotbar.select(1);
+        assert_eq!(hotbar.get_selected(), BlockType::Sand);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::VecDeque;
+
+use crate::{block::BlockType, chunk_master::ChunkMaster, input::{Action, InputState}, math::{Coord3, Quat, Vec3}, player::Player};
+
+// zmiana bloku pokazana od razu, czeka na odpowiedz serwera
+struct PendingEdit{
+    sequence: u32,
+    position: Coord3,
+    block: BlockType
+}
+
+// klient zmienia swiat od razu, serwer potwierdza zmiane albo odsyla prawdziwy stan bloku
+#[derive(Default)]
+pub struct BlockPrediction{
+    next_sequence: u32,
+    pending: Vec<PendingEdit>
+}
+impl BlockPrediction {
+    pub fn get_pending_count(&self) -> usize{
+        self.pending.len()
+    }
+    // numer zmiany do wyslania, None gdy blok juz taki jest
+    pub fn predict(&mut self, world: &impl ChunkMaster, position: Coord3, block: BlockType) -> Option<u32>{
+        if world.get_voxel(position) == block || !world.set_voxel(position, block){
+            return None;
+        }
+        self.next_sequence += 1;
+        self.pending.push(PendingEdit{sequence: self.next_sequence, position, block});
+        Some(self.next_sequence)
+    }
+    // stan bloku od serwera, numer 0 to zmiana innego gracza; true, gdy blok w swiecie sie zmienil
+    pub fn apply(&mut self, world: &impl ChunkMaster, sequence: u32, position: Coord3, block: BlockType) -> bool{
+        if sequence != 0{
+            // serwer rozstrzyga zmiany gracza po kolei, odpowiedz na starsza zmiane przychodzi po nowszej tylko po przestawieniu pakietow
+            if !self.pending.iter().any(|edit| edit.position == position && edit.sequence <= sequence){
+                return false;
+            }
+            self.pending.retain(|edit| edit.position != position || edit.sequence > sequence);
+        }
+        let shown = self.pending.iter().rev()
+            .find(|edit| edit.position == position)
+            .map_or(block, |edit| edit.block);
+        world.get_voxel(position) != shown && world.set_voxel(position, shown)
+    }
+}
+
+// krok gracza zapamietany do powtorzenia po korekcie z serwera
+struct PredictedStep{
+    sequence: u32,
+    held: Vec<Action>,
+    rotation: Quat,
+    flying: bool,
+    delta_time: f32,
+    // stan gracza po tym kroku
+    player: Player
+}
+
+// gracz rusza sie od razu, a gdy serwer odrzuci ruch, wraca na pozycje z serwera i powtarza pozniejsze kroki
+#[derive(Default)]
+pub struct MovementPrediction{
+    history: VecDeque<PredictedStep>,
+    unsent: Vec<(u32, Vec3)>,
+    last_correction: u32
+}
+impl MovementPrediction {
+    // okolo 4 sekund przy 60 krokach na sekunde
+    const MAX_HISTORY: usize = 256;
+    const MOVEMENT: [Action; 5] = [Action::MoveForward, Action::MoveBack, Action::MoveLeft, Action::MoveRight, Action::Jump];
+
+    pub fn get_history_len(&self) -> usize{
+        self.history.len()
+    }
+    // wywolywane po kazdym kroku gracza, sequence musi rosnac
+    pub fn record(&mut self, sequence: u32, input: &InputState, delta_time: f32, player: &Player){
+        self.history.push_back(PredictedStep{
+            sequence,
+            held: MovementPrediction::MOVEMENT.into_iter().filter(|action| input.is_held(*action)).collect(),
+            rotation: player.get_rotation(),
+            flying: player.is_flying(),
+            delta_time,
+            player: player.clone()
+        });
+        if self.history.len() > MovementPrediction::MAX_HISTORY{
+            self.history.pop_front();
+        }
+        self.unsent.push((sequence, player.get_position()));
+    }
+    // ruchy do wyslania na serwer
+    pub fn take_unsent(&mut self) -> Vec<(u32, Vec3)>{
+        std::mem::take(&mut self.unsent)
+    }
+    // true, gdy gracz zostal cofniety; korekty starsze niz ostatnia sa pomijane
+    pub fn reconcile(&mut self, sequence: u32, position: Vec3, player: &mut Player, world: &impl ChunkMaster) -> bool{
+        if sequence <= self.last_correction{
+            return false;
+        }
+        self.last_correction = sequence;
+        let mut replayed = match self.history.iter().find(|step| step.sequence == sequence) {
+            Some(step) => step.player.clone(),
+            None => {
+                // krok wypadl z historii, nie ma czego powtarzac
+                self.history.clear();
+                player.clone()
+            }
+        };
+        replayed.set_position(position);
+        self.history.retain(|step| step.sequence > sequence);
+        let mut input = InputState::default();
+        for step in self.history.iter_mut(){
+            for action in MovementPrediction::MOVEMENT{
+                input.set_held(action, step.held.contains(&action));
+            }
+            replayed.set_rotation(step.rotation);
+            if replayed.is_flying() != step.flying{
+                replayed.toggle_flying();
+            }
+            replayed.update(&input, step.delta_time, world);
+            step.player = replayed.clone();
+        }
+        replayed.set_rotation(player.get_rotation());
+        if replayed.is_flying() != player.is_flying(){
+            replayed.toggle_flying();
+        }
+        *player = replayed;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{authority::{MoveCheck, PlayerAuthority}, chunk::Chunk, chunk_master::MemoryWorld, math::Rng};
+
+    use super::*;
+
+    // siec w jednym procesie: kazdy pakiet ma opoznienie z losowym rozrzutem, wiec pakiety sie przestawiaja
+    struct SimulatedLink<T>{
+        in_flight: Vec<(u64, T)>,
+        rng: Rng,
+        latency: u64,
+        jitter: i32
+    }
+    impl<T> SimulatedLink<T> {
+        fn new(seed: u64, latency: u64, jitter: i32) -> Self{
+            SimulatedLink{in_flight: Vec::new(), rng: Rng::new(seed), latency, jitter}
+        }
+        fn send(&mut self, now: u64, message: T){
+            let arrival = now+self.latency+self.rng.range(self.jitter+1) as u64;
+            self.in_flight.push((arrival, message));
+        }
+        fn receive(&mut self, now: u64) -> Vec<T>{
+            let (arrived, in_flight) = std::mem::take(&mut self.in_flight).into_iter().partition(|(arrival, _)| *arrival <= now);
+            self.in_flight = in_flight;
+            let mut arrived: Vec<(u64, T)> = arrived;
+            arrived.sort_by_key(|(arrival, _)| *arrival);
+            arrived.into_iter().map(|(_, message)| message).collect()
+        }
+        fn is_empty(&self) -> bool{
+            self.in_flight.is_empty()
+        }
+    }
+
+    fn flat_world() -> MemoryWorld{
+        let world = MemoryWorld::default();
+        let last = Chunk::CHUNK_SIZE as i32-1;
+        world.fill(Coord3::ZERO, Coord3::new(last, 0, last), BlockType::Stone);
+        world
+    }
+
+    struct Session{
+        world: MemoryWorld,
+        player: Player,
+        input: InputState,
+        prediction: MovementPrediction,
+        authority: PlayerAuthority,
+        up: SimulatedLink<(u32, Vec3)>,
+        down: SimulatedLink<(u32, Vec3)>,
+        rollbacks: usize,
+        tick: u64
+    }
+    impl Session {
+        fn new() -> Self{
+            let mut player = Player::new();
+            player.toggle_flying();
+            player.set_position(Vec3::new(25.0, 0.5+Player::EYE_HEIGHT, 25.0));
+            Session{
+                world: flat_world(),
+                authority: PlayerAuthority::new(player.get_position()),
+                player,
+                input: InputState::default(),
+                prediction: MovementPrediction::default(),
+                up: SimulatedLink::new(1, 6, 4),
+                down: SimulatedLink::new(2, 6, 4),
+                rollbacks: 0,
+                tick: 0
+            }
+        }
+        fn step(&mut self){
+            self.tick += 1;
+            let delta_time = 1.0/crate::protocol::MOVE_SEQUENCE_RATE as f32;
+            self.player.update(&self.input, delta_time, &self.world);
+            self.prediction.record(self.tick as u32, &self.input, delta_time, &self.player);
+            for movement in self.prediction.take_unsent(){
+                self.up.send(self.tick, movement);
+            }
+            self.deliver();
+        }
+        fn deliver(&mut self){
+            for (sequence, position) in self.up.receive(self.tick){
+                if self.authority.check_move(sequence, position) == MoveCheck::Rejected{
+                    self.down.send(self.tick, (sequence, self.authority.get_position()));
+                }
+            }
+            for (sequence, position) in self.down.receive(self.tick){
+                if self.prediction.reconcile(sequence, position, &mut self.player, &self.world){
+                    self.rollbacks += 1;
+                }
+            }
+        }
+        // gracz stoi w miejscu, potem siec dostarcza wszystko, co jeszcze leci
+        fn settle(&mut self){
+            self.input.release_all();
+            for _ in 0..60{
+                self.step();
+            }
+            while !self.up.is_empty() || !self.down.is_empty(){
+                self.tick += 1;
+                self.deliver();
+            }
+        }
+    }
+
+    #[test]
+    fn predicted_movement_matches_the_server_despite_latency_and_reordering(){
+        let mut session = Session::new();
+        session.input.set_held(Action::MoveForward, true);
+        for tick in 0..120{
+            session.input.set_held(Action::Jump, tick%40 < 5);
+            session.step();
+        }
+        let walked = session.player.get_position();
+        session.settle();
+        assert_eq!(session.rollbacks, 0);
+        assert!((walked-Vec3::new(25.0, 0.5+Player::EYE_HEIGHT, 25.0)).magnitude() > 5.0);
+        assert_eq!(session.authority.get_position(), session.player.get_position());
+    }
+
+    #[test]
+    fn rejected_moves_are_rolled_back_and_replayed(){
+        let mut session = Session::new();
+        let start = session.player.get_position();
+        session.input.set_held(Action::MoveRight, true);
+        for _ in 0..30{
+            session.step();
+        }
+        // ruch, ktorego serwer nie przyjmie
+        session.player.set_position(session.player.get_position()+Vec3::new(0.0, 0.0, 20.0));
+        for _ in 0..30{
+            session.step();
+        }
+        session.settle();
+        assert!(session.rollbacks > 0);
+        assert!((session.player.get_position().z-start.z).abs() < 1.0);
+        assert!((session.player.get_position()-start).magnitude() > 3.0);
+        assert_eq!(session.authority.get_position(), session.player.get_position());
+    }
+
+    #[test]
+    fn corrections_replay_only_newer_steps(){
+        let world = flat_world();
+        let mut player = Player::new();
+        player.set_position(Vec3::new(10.0, 10.0, 10.0));
+        let mut input = InputState::default();
+        input.set_held(Action::MoveForward, true);
+        let mut prediction = MovementPrediction::default();
+        for sequence in 1..=10{
+            player.update(&input, 0.1, &world);
+            prediction.record(sequence, &input, 0.1, &player);
+        }
+        let moved = player.get_position()-Vec3::new(10.0, 10.0, 10.0);
+        assert!(prediction.reconcile(5, Vec3::new(10.0, 10.0, 10.0), &mut player, &world));
+        // piec powtorzonych krokow to polowa drogi
+        assert!((player.get_position()-(Vec3::new(10.0, 10.0, 10.0)+moved*0.5)).magnitude() < 1e-3);
+        assert_eq!(prediction.get_history_len(), 5);
+        assert!(!prediction.reconcile(4, Vec3::ZERO, &mut player, &world));
+    }
+
+    #[test]
+    fn block_edits_are_confirmed_or_rolled_back(){
+        let client_world = flat_world();
+        let server_world = flat_world();
+        let mut prediction = BlockPrediction::default();
+        let mut authority = PlayerAuthority::new(Vec3::new(25.0, 2.0, 25.0));
+        let mut up: SimulatedLink<(u32, Coord3, BlockType)> = SimulatedLink::new(3, 5, 6);
+        let mut down: SimulatedLink<(u32, Coord3, BlockType)> = SimulatedLink::new(4, 5, 6);
+
+        let near = Coord3::new(26, 1, 25);
+        let far = Coord3::new(45, 1, 45);
+        let mut edits = vec![(near, BlockType::Stone), (far, BlockType::Stone), (near, BlockType::Air), (near, BlockType::Sand)];
+        for x in 20..30{
+            edits.push((Coord3::new(x, 1, 27), BlockType::Dirt));
+        }
+        let mut tick = 0;
+        for (position, block) in edits{
+            tick += 1;
+            let sequence = prediction.predict(&client_world, position, block).unwrap();
+            // przewidywanie widac od razu, bez czekania na serwer
+            assert_eq!(client_world.get_voxel(position), block);
+            up.send(tick, (sequence, position, block));
+        }
+        assert_eq!(prediction.get_pending_count(), 14);
+        while !up.is_empty() || !down.is_empty(){
+            tick += 1;
+            for (sequence, position, block) in up.receive(tick){
+                authority.apply_edit(&server_world, sequence, position, block);
+                down.send(tick, (sequence, position, server_world.get_voxel(position)));
+            }
+            for (sequence, position, block) in down.receive(tick){
+                prediction.apply(&client_world, sequence, position, block);
+            }
+        }
+        assert_eq!(prediction.get_pending_count(), 0);
+        assert_eq!(client_world.get_voxel(far), BlockType::Air);
+        for x in 20..30{
+            let position = Coord3::new(x, 1, 27);
+            assert_eq!(client_world.get_voxel(position), server_world.get_voxel(position));
+        }
+        assert_eq!(client_world.get_voxel(near), server_world.get_voxel(near));
+        // zmiana innego gracza nie nadpisuje niepotwierdzonego przewidywania
+        let sequence = prediction.predict(&client_world, near, BlockType::Grass).unwrap();
+        assert!(!prediction.apply(&client_world, 0, near, BlockType::Dirt));
+        assert_eq!(client_world.get_voxel(near), BlockType::Grass);
+        assert!(prediction.apply(&client_world, sequence, near, BlockType::Dirt));
+        assert_eq!(client_world.get_voxel(near), BlockType::Dirt);
+    }
+}
@@ -0,0 +1,314 @@
+use std::io::{self, Read, Write};
+
+use crate::{block::BlockType, math::{Coord3, Vec3}};
+
+// zmieniac przy kazdej niezgodnej zmianie formatu, klient i serwer musza miec ta sama
+pub const PROTOCOL_VERSION: u16 = 2;
+// sequence w PlayerMove to numer kroku symulacji klienta, krokow jest tyle na sekunde
+pub const MOVE_SEQUENCE_RATE: u32 = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    // klient wysyla z player_id 0, serwer odpowiada nadanym id i miejscem startu
+    Handshake{version: u16, player_id: u32, position: Vec3},
+    // data to chunk zapisany przez Chunk::encode
+    ChunkData{position: Coord3, data: Vec<u8>},
+    // od klienta prosba o zmiane z jej numerem, serwer odsyla ten numer z prawdziwym stanem bloku,
+    // pozostalym graczom wysyla zmiane z numerem 0
+    BlockChange{sequence: u32, position: Coord3, block: BlockType},
+    // serwer odsyla graczowi jego wlasny ruch tylko wtedy, gdy go odrzucil
+    PlayerMove{player_id: u32, sequence: u32, position: Vec3},
+    EntitySpawn{entity_id: u32, position: Vec3, half_extents: Vec3},
+    // player_id 0, gdy serwer odrzuca polaczenie przed nadaniem id
+    Disconnect{player_id: u32, reason: String},
+    // klient wysyla swoj tekst, serwer rozsyla go pozostalym graczom z id autora
+    Chat{player_id: u32, text: String}
+}
+
+// ramka: dlugosc tresci u32, potem bajt rodzaju wiadomosci i jej pola, liczby w little endian,
+// Vec3 jako trzy f32, Coord3 jako trzy i32, napisy jako dlugosc u16 i utf8
+impl Message {
+    pub const MAX_FRAME: usize = 1<<24;
+    const HANDSHAKE: u8 = 0;
+    const CHUNK_DATA: u8 = 1;
+    const BLOCK_CHANGE: u8 = 2;
+    const PLAYER_MOVE: u8 = 3;
+    const ENTITY_SPAWN: u8 = 4;
+    const DISCONNECT: u8 = 5;
+    const CHAT: u8 = 6;
+
+    pub fn handshake(player_id: u32, position: Vec3) -> Message{
+        Message::Handshake{version: PROTOCOL_VERSION, player_id, position}
+    }
+    pub fn encode(&self) -> Vec<u8>{
+        let mut bytes = Vec::new();
+        match self {
+            Message::Handshake{version, player_id, position} => {
+                bytes.push(Message::HANDSHAKE);
+                bytes.extend_from_slice(&version.to_le_bytes());
+                bytes.extend_from_slice(&player_id.to_le_bytes());
+                put_vec3(&mut bytes, *position);
+            }
+            Message::ChunkData{position, data} => {
+                bytes.push(Message::CHUNK_DATA);
+                put_coord(&mut bytes, *position);
+                bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(data);
+            }
+            Message::BlockChange{sequence, position, block} => {
+                bytes.push(Message::BLOCK_CHANGE);
+                bytes.extend_from_slice(&sequence.to_le_bytes());
+                put_coord(&mut bytes, *position);
+                bytes.extend_from_slice(&(*block as u16).to_le_bytes());
+            }
+            Message::PlayerMove{player_id, sequence, position} => {
+                bytes.push(Message::PLAYER_MOVE);
+                bytes.extend_from_slice(&player_id.to_le_bytes());
+                bytes.extend_from_slice(&sequence.to_le_bytes());
+                put_vec3(&mut bytes, *position);
+            }
+            Message::EntitySpawn{entity_id, position, half_extents} => {
+                bytes.push(Message::ENTITY_SPAWN);
+                bytes.extend_from_slice(&entity_id.to_le_bytes());
+                put_vec3(&mut bytes, *position);
+                put_vec3(&mut bytes, *half_extents);
+            }
+            Message::Disconnect{player_id, reason} => {
+                bytes.push(Message::DISCONNECT);
+                bytes.extend_from_slice(&player_id.to_le_bytes());
+                put_string(&mut bytes, reason);
+            }
+            Message::Chat{player_id, text} => {
+                bytes.push(Message::CHAT);
+                bytes.extend_from_slice(&player_id.to_le_bytes());
+                put_string(&mut bytes, text);
+            }
+        }
+        bytes
+    }
+    // wiadomosci o stalej dlugosci musza ja miec dokladnie, reszta jest sprawdzana po polach
+    fn get_fixed_length(kind: u8) -> Option<usize>{
+        match kind {
+            Message::HANDSHAKE => Some(2+4+12),
+            Message::BLOCK_CHANGE => Some(4+12+2),
+            Message::PLAYER_MOVE => Some(4+4+12),
+            Message::ENTITY_SPAWN => Some(4+12+12),
+            _ => None
+        }
+    }
+    pub fn decode(bytes: &[u8]) -> Result<Message, String>{
+        let (&kind, mut body) = bytes.split_first().ok_or("empty message")?;
+        if let Some(length) = Message::get_fixed_length(kind){
+            if body.len() != length{
+                return Err(format!("message {kind} must have {length} bytes, got {}", body.len()));
+            }
+        }
+        let message = match kind {
+            Message::HANDSHAKE => Message::Handshake{
+                version: get_u16(&mut body)?,
+                player_id: get_u32(&mut body)?,
+                position: get_vec3(&mut body)?
+            },
+            Message::CHUNK_DATA => {
+                let position = get_coord(&mut body)?;
+                let length = get_u32(&mut body)? as usize;
+                if body.len() != length{
+                    return Err(format!("chunk data has {} bytes, header says {length}", body.len()));
+                }
+                Message::ChunkData{position, data: take(&mut body, length)?.to_vec()}
+            }
+            Message::BLOCK_CHANGE => {
+                let sequence = get_u32(&mut body)?;
+                let position = get_coord(&mut body)?;
+                let id = get_u16(&mut body)?;
+                Message::BlockChange{sequence, position, block: BlockType::from_id(id).ok_or(format!("unknown block id {id}"))?}
+            }
+            Message::PLAYER_MOVE => Message::PlayerMove{
+                player_id: get_u32(&mut body)?,
+                sequence: get_u32(&mut body)?,
+                position: get_vec3(&mut body)?
+            },
+            Message::ENTITY_SPAWN => Message::EntitySpawn{
+                entity_id: get_u32(&mut body)?,
+                position: get_vec3(&mut body)?,
+                half_extents: get_vec3(&mut body)?
+            },
+            Message::DISCONNECT => {
+                let player_id = get_u32(&mut body)?;
+                Message::Disconnect{player_id, reason: get_string(&mut body)?}
+            }
+            Message::CHAT => {
+                let player_id = get_u32(&mut body)?;
+                Message::Chat{player_id, text: get_string(&mut body)?}
+            }
+            _ => return Err(format!("unknown message kind {kind}"))
+        };
+        if !body.is_empty(){
+            return Err(format!("{} bytes after message", body.len()));
+        }
+        Ok(message)
+    }
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()>{
+        let body = self.encode();
+        if body.len() > Message::MAX_FRAME{
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("message of {} bytes is too long", body.len())));
+        }
+        let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&body);
+        writer.write_all(&frame)
+    }
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Message>{
+        let mut length = [0; 4];
+        reader.read_exact(&mut length)?;
+        let length = u32::from_le_bytes(length) as usize;
+        if length > Message::MAX_FRAME{
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {length} bytes is too long")));
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        Message::decode(&body).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+fn take<'a>(body: &mut &'a [u8], count: usize) -> Result<&'a [u8], String>{
+    if body.len() < count{
+        return Err("message is truncated".to_string());
+    }
+    let (head, tail) = body.split_at(count);
+    *body = tail;
+    Ok(head)
+}
+fn get_u16(body: &mut &[u8]) -> Result<u16, String>{
+    Ok(u16::from_le_bytes(take(body, 2)?.try_into().unwrap()))
+}
+fn get_u32(body: &mut &[u8]) -> Result<u32, String>{
+    Ok(u32::from_le_bytes(take(body, 4)?.try_into().unwrap()))
+}
+fn get_i32(body: &mut &[u8]) -> Result<i32, String>{
+    Ok(i32::from_le_bytes(take(body, 4)?.try_into().unwrap()))
+}
+fn get_f32(body: &mut &[u8]) -> Result<f32, String>{
+    Ok(f32::from_le_bytes(take(body, 4)?.try_into().unwrap()))
+}
+fn get_coord(body: &mut &[u8]) -> Result<Coord3, String>{
+    Ok(Coord3::new(get_i32(body)?, get_i32(body)?, get_i32(body)?))
+}
+fn get_vec3(body: &mut &[u8]) -> Result<Vec3, String>{
+    Ok(Vec3::new(get_f32(body)?, get_f32(body)?, get_f32(body)?))
+}
+fn get_string(body: &mut &[u8]) -> Result<String, String>{
+    let length = get_u16(body)? as usize;
+    String::from_utf8(take(body, length)?.to_vec()).map_err(|_| "text is not utf8".to_string())
+}
+// dluzszy napis jest przycinany do u16::MAX bajtow na granicy znaku
+fn put_string(bytes: &mut Vec<u8>, text: &str){
+    let mut length = text.len().min(u16::MAX as usize);
+    while !text.is_char_boundary(length){
+        length -= 1;
+    }
+    bytes.extend_from_slice(&(length as u16).to_le_bytes());
+    bytes.extend_from_slice(&text.as_bytes()[..length]);
+}
+fn put_coord(bytes: &mut Vec<u8>, value: Coord3){
+    for component in [value.x, value.y, value.z]{
+        bytes.extend_from_slice(&component.to_le_bytes());
+    }
+}
+fn put_vec3(bytes: &mut Vec<u8>, value: Vec3){
+    for component in [value.x, value.y, value.z]{
+        bytes.extend_from_slice(&component.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+
+    use super::*;
+
+    // ramki zapisane dla wersji 2, nie zmieniac bez podniesienia PROTOCOL_VERSION
+    fn fixtures() -> Vec<(&'static [u8], Message)>{
+        vec![
+            (include_bytes!("fixtures/v2_handshake.bin"), Message::Handshake{version: 2, player_id: 7, position: Vec3::new(1.0, 2.5, -3.0)}),
+            (include_bytes!("fixtures/v2_chunk_data.bin"), Message::ChunkData{position: Coord3::new(-1, 0, 4), data: vec![1, 2, 3]}),
+            (include_bytes!("fixtures/v2_block_change.bin"), Message::BlockChange{sequence: 9, position: Coord3::new(10, -20, 30), block: BlockType::Sand}),
+            (include_bytes!("fixtures/v2_player_move.bin"), Message::PlayerMove{player_id: 3, sequence: 1200, position: Vec3::new(0.5, 0.0, 0.25)}),
+            (include_bytes!("fixtures/v2_entity_spawn.bin"), Message::EntitySpawn{entity_id: 42, position: Vec3::new(1.0, 2.0, 3.0), half_extents: Vec3::new(0.25, 0.5, 0.25)}),
+            (include_bytes!("fixtures/v2_disconnect.bin"), Message::Disconnect{player_id: 3, reason: "bye".to_string()}),
+            (include_bytes!("fixtures/v2_chat.bin"), Message::Chat{player_id: 3, text: "hello".to_string()})
+        ]
+    }
+
+    #[test]
+    fn messages_match_recorded_frames(){
+        assert_eq!(PROTOCOL_VERSION, 2);
+        for (frame, message) in fixtures(){
+            let mut written = Vec::new();
+            message.write_to(&mut written).unwrap();
+            assert_eq!(written, frame, "{message:?}");
+            assert_eq!(Message::read_from(&mut &frame[..]).unwrap(), message);
+        }
+    }
+
+    // wersja 1 nie miala numerow sekwencji w BlockChange i PlayerMove, reszta ramek sie nie zmienila
+    #[test]
+    fn version_1_frames_keep_their_layout_or_are_rejected(){
+        let handshake = Message::read_from(&mut &include_bytes!("fixtures/v1_handshake.bin")[..]).unwrap();
+        assert_eq!(handshake, Message::Handshake{version: 1, player_id: 7, position: Vec3::new(1.0, 2.5, -3.0)});
+        assert!(Message::read_from(&mut &include_bytes!("fixtures/v1_block_change.bin")[..]).is_err());
+        assert!(Message::read_from(&mut &include_bytes!("fixtures/v1_player_move.bin")[..]).is_err());
+        assert_eq!(include_bytes!("fixtures/v1_chunk_data.bin"), include_bytes!("fixtures/v2_chunk_data.bin"));
+        assert_eq!(include_bytes!("fixtures/v1_entity_spawn.bin"), include_bytes!("fixtures/v2_entity_spawn.bin"));
+        assert_eq!(include_bytes!("fixtures/v1_disconnect.bin"), include_bytes!("fixtures/v2_disconnect.bin"));
+    }
+
+    #[test]
+    fn recorded_chunk_decodes(){
+        // jeden kamien w rogu chunka i plynaca woda obok
+        let chunk = Chunk::decode(Coord3::ZERO, include_bytes!("fixtures/v1_chunk.bin")).unwrap();
+        assert_eq!(chunk.get_voxel(Coord3::ZERO), BlockType::Stone);
+        assert_eq!(chunk.get_fluid_level(Coord3::new(0, 0, 1)), 3);
+        assert_eq!(chunk.get_voxels().len(), 2);
+        assert_eq!(chunk.encode(), include_bytes!("fixtures/v1_chunk.bin"));
+    }
+
+    #[test]
+    fn frames_with_wrong_lengths_are_rejected(){
+        for (frame, _) in fixtures(){
+            let body = &frame[4..];
+            assert!(Message::decode(&body[..body.len()-1]).is_err());
+            let mut longer = body.to_vec();
+            longer.push(0);
+            assert!(Message::decode(&longer).is_err());
+            assert!(Message::read_from(&mut &frame[..frame.len()-1]).is_err());
+        }
+        assert!(Message::decode(&[]).is_err());
+        assert!(Message::decode(&[9]).is_err());
+        let too_long = ((Message::MAX_FRAME+1) as u32).to_le_bytes();
+        assert!(Message::read_from(&mut &too_long[..]).is_err());
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected(){
+        let mut block = Message::BlockChange{sequence: 1, position: Coord3::ZERO, block: BlockType::Stone}.encode();
+        *block.last_mut().unwrap() = 0xff;
+        assert!(Message::decode(&block).is_err());
+        let mut reason = Message::Disconnect{player_id: 1, reason: "ab".to_string()}.encode();
+        *reason.last_mut().unwrap() = 0xff;
+        assert!(Message::decode(&reason).is_err());
+    }
+
+    #[test]
+    fn long_text_is_cut_on_a_char_boundary(){
+        // dwubajtowe znaki, wiec u16::MAX wypada w srodku znaku
+        let reason = "ą".repeat(40000);
+        let decoded = Message::decode(&Message::Disconnect{player_id: 1, reason: reason.clone()}.encode()).unwrap();
+        let Message::Disconnect{reason: cut, ..} = decoded else {
+            panic!("expected disconnect, got {decoded:?}");
+        };
+        assert_eq!(cut.len(), u16::MAX as usize-1);
+        assert!(reason.starts_with(&cut));
+        let exact = "a".repeat(u16::MAX as usize);
+        assert_eq!(Message::decode(&Message::Disconnect{player_id: 1, reason: exact.clone()}.encode()), Ok(Message::Disconnect{player_id: 1, reason: exact}));
+    }
+}
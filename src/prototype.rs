@@ -1,17 +1,40 @@
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
 use std::time::Instant;
 
 use gl;
-use glutin::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use glutin::event::{ElementState, Event, KeyboardInput, MouseScrollDelta, VirtualKeyCode, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
 use glutin::window::WindowBuilder;
 use glutin::ContextBuilder;
 use glutin::dpi::{LogicalSize, PhysicalPosition};
-use nalgebra::Point3;
 
+use nalgebra::{Matrix4, Vector3};
+
+use crate::block::BlockType;
 use crate::camera::Camera;
-use crate::player::Player;
-use crate::Vec3;
+use crate::chunk::{Chunk, ChunkVisibility};
+use crate::chunk_loader::ChunkLoader;
+use crate::world_generator::WorldGenerator;
+use crate::lod::{LodBuilder, LodSettings};
+use crate::chunk_master::{get_affected_chunks, ChunkMaster};
+use crate::culling::{find_visible_chunks, DrawStats};
+use crate::client::Client;
+use crate::console::Console;
+use crate::font::{get_glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::entity::{Entity, EntityId, EntityStore};
+use crate::random_tick::RandomTicker;
+use crate::mob::{Behaviour, MobController};
+use crate::game_loop::{GameLoop, GameState};
+use crate::input::{Action, Bindings, InputKey, InputState};
+use crate::player::{Hotbar, Player};
+use crate::persistence::WorldStorage;
+use crate::prediction::MovementPrediction;
+use crate::math::{Aabb, Coord3, RayHit};
+use crate::mesh::{ChunkMesh, ChunkMesher};
+use crate::settings::Settings;
+use crate::shadow::{ShadowMap, ShadowSettings};
+use crate::math::Vec3;
 
 // Vertex shader w GLSL
 const VERTEX_SHADER: &str = r#"
@@ -27,6 +50,7 @@ const VERTEX_SHADER: &str = r#"
     out vec3 normal;
     out vec3 fragPos;
     out vec4 color;
+    out float viewDepth;
 
     void main() {
         float yo = 0.0f;
@@ -43,24 +67,59 @@ const VERTEX_SHADER: &str = r#"
         else
             color = vec4(aColor, 1.0f);
         normal = aNormal;
-        vec4 ret = projection * view * vec4(position.x, position.y+yo, position.z, 1.0);
-        gl_Position = ret;
+        vec4 viewPos = view * vec4(position.x, position.y+yo, position.z, 1.0);
+        gl_Position = projection * viewPos;
         fragPos = vec3(position.x, position.y+yo, position.z);
+        viewDepth = -viewPos.z;
     }
 "#;
 
 // Fragment shader w GLSL
 const FRAGMENT_SHADER: &str = r#"
     #version 330 core
+    #define MAX_CASCADES 4
     in vec4 color;
     in vec3 normal;
     in vec3 fragPos;
+    in float viewDepth;
 
     uniform vec3 viewVector;
-    uniform vec3 lightPos;
+    uniform vec3 sunDirection;
     uniform vec3 lightColor;
+
+    uniform sampler2DArrayShadow shadowMap;
+    uniform mat4 lightSpaceMatrices[MAX_CASCADES];
+    uniform float cascadeSplits[MAX_CASCADES];
+    uniform int cascadeCount;
     
     out vec4 FragColor;
+
+    float calculateShadow(vec3 n, vec3 lightDir) {
+        if (viewDepth > cascadeSplits[cascadeCount - 1])
+            return 0.0;
+        int cascade = cascadeCount - 1;
+        for (int i = 0; i < cascadeCount; i++) {
+            if (viewDepth < cascadeSplits[i]) {
+                cascade = i;
+                break;
+            }
+        }
+        vec4 lightSpacePos = lightSpaceMatrices[cascade] * vec4(fragPos + n * 0.05, 1.0);
+        vec3 projected = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+        if (projected.z > 1.0)
+            return 0.0;
+        float bias = max(0.002 * (1.0 - dot(n, lightDir)), 0.0005) / float(cascade + 1);
+        vec2 texelSize = 1.0 / vec2(textureSize(shadowMap, 0).xy);
+        float lit = 0.0;
+        for (int x = -1; x <= 1; x++) {
+            for (int y = -1; y <= 1; y++) {
+                vec2 uv = projected.xy + vec2(x, y) * texelSize;
+                lit += texture(shadowMap, vec4(uv, float(cascade), projected.z - bias));
+            }
+        }
+        return 1.0 - lit / 9.0;
+    }
+
     void main() {
         FragColor = color;
 
@@ -68,8 +127,11 @@ const FRAGMENT_SHADER: &str = r#"
         float ambientIntensity = 4;
         ambientLight = ambientLight * ambientIntensity;
         
-        vec3 lightDir = normalize(lightPos - fragPos);
-        float diff = max(dot(normalize(normal), lightDir), 0.0);
+        vec3 n = normalize(normal);
+        vec3 lightDir = normalize(-sunDirection);
+        float diff = max(dot(n, lightDir), 0.0);
+        if (diff > 0.0)
+            diff *= 1.0 - calculateShadow(n, lightDir);
         diff = (log2(diff+1)+1.0)/2.0;
 
         vec3 diffuse = ambientLight * diff * lightColor * color.xyz;
@@ -77,25 +139,386 @@ const FRAGMENT_SHADER: &str = r#"
     }
 "#;
 
-pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
-    let event_loop = EventLoop::new();
-    let window_builder = WindowBuilder::new()
-        .with_title("OpenGL game")
-        .with_inner_size(LogicalSize::new(800.0, 600.0));
+const OUTLINE_VERTEX_SHADER: &str = r#"
+    #version 330 core
+    layout (location = 0) in vec3 position;
 
-    let gl_window: glutin::ContextWrapper<glutin::NotCurrent, glutin::window::Window> = ContextBuilder::new()
-        .with_vsync(false)
-        .build_windowed(window_builder, &event_loop)
-        .unwrap();
+    uniform vec3 blockPosition;
+    uniform mat4 view;
+    uniform mat4 projection;
 
-    let gl_window = unsafe { gl_window.make_current().unwrap() };
+    void main() {
+        gl_Position = projection * view * vec4(position + blockPosition, 1.0);
+    }
+"#;
 
-    gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
+const OUTLINE_FRAGMENT_SHADER: &str = r#"
+    #version 330 core
+    out vec4 FragColor;
+    void main() {
+        FragColor = vec4(0.05, 0.05, 0.05, 1.0);
+    }
+"#;
+
+// obrys bloku, na ktory patrzy gracz
+struct BlockOutline{
+    vao: u32,
+    program: u32,
+    block_position_location: i32,
+    view_location: i32,
+    projection_location: i32
+}
+impl BlockOutline {
+    fn new() -> Self{
+        // troche wiekszy niz blok, zeby linie nie znikaly w scianach
+        let size = 0.502;
+        let mut vertices: Vec<f32> = Vec::new();
+        for axis in 0..3{
+            for a in [-size, size]{
+                for b in [-size, size]{
+                    for c in [-size, size]{
+                        let corner = match axis {
+                            0 => [c, a, b],
+                            1 => [a, c, b],
+                            _ => [a, b, c]
+                        };
+                        vertices.extend_from_slice(&corner);
+                    }
+                }
+            }
+        }
+        let mut outline = BlockOutline{
+            vao: 0,
+            program: compile_shader_program(OUTLINE_VERTEX_SHADER, OUTLINE_FRAGMENT_SHADER),
+            block_position_location: 0,
+            view_location: 0,
+            projection_location: 0
+        };
+        unsafe {
+            let mut vbo = 0;
+            gl::GenVertexArrays(1, &mut outline.vao);
+            gl::BindVertexArray(outline.vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertices.as_slice()) as gl::types::GLsizeiptr,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, (3 * std::mem::size_of::<f32>()) as i32, std::ptr::null());
+            outline.block_position_location = gl::GetUniformLocation(outline.program, CString::new("blockPosition").unwrap().as_ptr());
+            outline.view_location = gl::GetUniformLocation(outline.program, CString::new("view").unwrap().as_ptr());
+            outline.projection_location = gl::GetUniformLocation(outline.program, CString::new("projection").unwrap().as_ptr());
+        }
+        outline
+    }
+    fn draw(&self, position: Coord3, view: &Matrix4<f32>, projection: &Matrix4<f32>){
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::Uniform3f(self.block_position_location, position.x as f32, position.y as f32, position.z as f32);
+            gl::UniformMatrix4fv(self.view_location, 1, gl::FALSE, view.as_ptr());
+            gl::UniformMatrix4fv(self.projection_location, 1, gl::FALSE, projection.as_ptr());
+            gl::BindVertexArray(self.vao);
+            gl::LineWidth(2.0);
+            gl::DrawArrays(gl::LINES, 0, 24);
+        }
+    }
+}
+
+const ENTITY_VERTEX_SHADER: &str = r#"
+    #version 330 core
+    layout (location = 0) in vec3 position;
+    layout (location = 1) in vec3 aNormal;
+
+    uniform mat4 model;
+    uniform mat4 view;
+    uniform mat4 projection;
+
+    out vec3 normal;
+
+    void main() {
+        normal = mat3(model) * aNormal;
+        gl_Position = projection * view * model * vec4(position, 1.0);
+    }
+"#;
+
+const ENTITY_FRAGMENT_SHADER: &str = r#"
+    #version 330 core
+    in vec3 normal;
+
+    uniform vec3 color;
+    uniform vec3 sunDirection;
+
+    out vec4 FragColor;
+    void main() {
+        float diff = max(dot(normalize(normal), normalize(-sunDirection)), 0.0);
+        FragColor = vec4(color * (0.4 + 0.6 * diff), 1.0);
+    }
+"#;
+
+// obiekty rysowane jako jednostkowe szesciany przeskalowane do swojego aabb
+struct EntityRenderer{
+    vao: u32,
+    program: u32,
+    model_location: i32,
+    view_location: i32,
+    projection_location: i32,
+    color_location: i32,
+    sun_direction_location: i32
+}
+impl EntityRenderer {
+    fn new() -> Self{
+        // dla kazdej sciany: normalna i dwie pozostale osie
+        let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+            ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0])
+        ];
+        let mut vertices: Vec<f32> = Vec::new();
+        for (normal, u, v) in faces{
+            for (a, b) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]{
+                for axis in 0..3{
+                    vertices.push(0.5*(normal[axis]+a*u[axis]+b*v[axis]));
+                }
+                vertices.extend_from_slice(&normal);
+            }
+        }
+        let program = compile_shader_program(ENTITY_VERTEX_SHADER, ENTITY_FRAGMENT_SHADER);
+        let mut renderer = EntityRenderer{
+            vao: 0,
+            program,
+            model_location: 0,
+            view_location: 0,
+            projection_location: 0,
+            color_location: 0,
+            sun_direction_location: 0
+        };
+        let stride = (6 * std::mem::size_of::<f32>()) as i32;
+        unsafe {
+            let mut vbo = 0;
+            gl::GenVertexArrays(1, &mut renderer.vao);
+            gl::BindVertexArray(renderer.vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertices.as_slice()) as gl::types::GLsizeiptr,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, (3 * std::mem::size_of::<f32>()) as *const () as *const _);
+            let location = |name: &str| gl::GetUniformLocation(program, CString::new(name).unwrap().as_ptr());
+            renderer.model_location = location("model");
+            renderer.view_location = location("view");
+            renderer.projection_location = location("projection");
+            renderer.color_location = location("color");
+            renderer.sun_direction_location = location("sunDirection");
+        }
+        renderer
+    }
+    fn draw(&self, entities: &EntityStore, alpha: f32, sun_direction: Vec3, view: &Matrix4<f32>, projection: &Matrix4<f32>){
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::Enable(gl::CULL_FACE);
+            gl::UniformMatrix4fv(self.view_location, 1, gl::FALSE, view.as_ptr());
+            gl::UniformMatrix4fv(self.projection_location, 1, gl::FALSE, projection.as_ptr());
+            gl::Uniform3f(self.sun_direction_location, sun_direction.x, sun_direction.y, sun_direction.z);
+            gl::BindVertexArray(self.vao);
+            for (_, entity) in entities.iter(){
+                let mut transform = entity.transform;
+                transform.position = entity.get_interpolated_position(alpha);
+                transform.scale = Vec3::new(
+                    transform.scale.x*entity.half_extents.x*2.0,
+                    transform.scale.y*entity.half_extents.y*2.0,
+                    transform.scale.z*entity.half_extents.z*2.0
+                );
+                gl::UniformMatrix4fv(self.model_location, 1, gl::FALSE, transform.get_model_matrix().as_ptr());
+                gl::Uniform3f(self.color_location, entity.color.x, entity.color.y, entity.color.z);
+                gl::DrawArrays(gl::TRIANGLES, 0, 36);
+            }
+        }
+    }
+}
+
+const HUD_VERTEX_SHADER: &str = r#"
+    #version 330 core
+    layout (location = 0) in vec2 position;
+    layout (location = 1) in vec3 aColor;
+
+    out vec3 color;
+
+    void main() {
+        color = aColor;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+"#;
+
+const HUD_FRAGMENT_SHADER: &str = r#"
+    #version 330 core
+    in vec3 color;
+    out vec4 FragColor;
+    void main() {
+        FragColor = vec4(color, 1.0);
+    }
+"#;
+
+// pasek wyboru blokow na dole ekranu, prostokaty rysowane bezposrednio we wspolrzednych ekranu
+struct HotbarRenderer{
+    vao: u32,
+    vbo: u32,
+    program: u32
+}
+impl HotbarRenderer {
+    const SLOT_SIZE: f32 = 60.0;
+    const MARGIN: f32 = 8.0;
+    const TEXT_SCALE: f32 = 2.0;
+
+    fn new() -> Self{
+        let mut renderer = HotbarRenderer{
+            vao: 0,
+            vbo: 0,
+            program: compile_shader_program(HUD_VERTEX_SHADER, HUD_FRAGMENT_SHADER)
+        };
+        let stride = (5 * std::mem::size_of::<f32>()) as i32;
+        unsafe {
+            gl::GenVertexArrays(1, &mut renderer.vao);
+            gl::BindVertexArray(renderer.vao);
+            gl::GenBuffers(1, &mut renderer.vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, renderer.vbo);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, (2 * std::mem::size_of::<f32>()) as *const () as *const _);
+        }
+        renderer
+    }
+    // prostokat w pikselach, (0,0) to lewy dolny rog okna
+    fn add_rect(vertices: &mut Vec<f32>, min: (f32, f32), max: (f32, f32), width: f32, height: f32, color: [f32; 3]){
+        let (x0, y0) = (min.0/width*2.0-1.0, min.1/height*2.0-1.0);
+        let (x1, y1) = (max.0/width*2.0-1.0, max.1/height*2.0-1.0);
+        for (x, y) in [(x0, y0), (x1, y0), (x1, y1), (x0, y0), (x1, y1), (x0, y1)]{
+            vertices.extend_from_slice(&[x, y, color[0], color[1], color[2]]);
+        }
+    }
+    fn draw(&self, hotbar: &Hotbar, width: f32, height: f32){
+        let size = HotbarRenderer::SLOT_SIZE;
+        let margin = HotbarRenderer::MARGIN;
+        let slots = hotbar.get_slots();
+        let start_x = (width-slots.len() as f32*(size+margin)+margin)/2.0;
+        let mut vertices: Vec<f32> = Vec::new();
+        for (i, block) in slots.iter().enumerate(){
+            let x = start_x+i as f32*(size+margin);
+            let frame = if i == hotbar.get_selected_index() {[0.9, 0.9, 0.9]} else {[0.1, 0.1, 0.1]};
+            HotbarRenderer::add_rect(&mut vertices, (x, margin), (x+size, margin+size), width, height, frame);
+            let color = block.get_color();
+            HotbarRenderer::add_rect(&mut vertices, (x+4.0, margin+4.0), (x+size-4.0, margin+size-4.0), width, height, [color.x, color.y, color.z]);
+        }
+        // celownik
+        let (cx, cy) = (width/2.0, height/2.0);
+        HotbarRenderer::add_rect(&mut vertices, (cx-8.0, cy-1.0), (cx+8.0, cy+1.0), width, height, [0.9, 0.9, 0.9]);
+        HotbarRenderer::add_rect(&mut vertices, (cx-1.0, cy-8.0), (cx+1.0, cy+8.0), width, height, [0.9, 0.9, 0.9]);
+        self.draw_vertices(&vertices);
+    }
+    // otwarta konsola nad paskiem: ostatnie linie i wpisywany tekst
+    fn draw_console<W: ChunkMaster>(&self, console: &Console<W>, width: f32, height: f32){
+        if !console.is_open(){
+            return;
+        }
+        let scale = HotbarRenderer::TEXT_SCALE;
+        let line_height = (GLYPH_HEIGHT+2) as f32*scale;
+        let bottom = HotbarRenderer::MARGIN*2.0+HotbarRenderer::SLOT_SIZE;
+        let lines = console.get_lines().len()+1;
+        let mut vertices: Vec<f32> = Vec::new();
+        HotbarRenderer::add_rect(&mut vertices, (0.0, bottom), (width, bottom+lines as f32*line_height+scale), width, height, [0.05, 0.05, 0.05]);
+        let input = format!("> {}_", console.get_input());
+        for (i, text) in [input.as_str()].into_iter().chain(console.get_lines().iter().rev().map(|line| line.as_str())).enumerate(){
+            let y = bottom+scale+i as f32*line_height;
+            HotbarRenderer::add_text(&mut vertices, text, (HotbarRenderer::MARGIN, y), width, height);
+        }
+        self.draw_vertices(&vertices);
+    }
+    // kazdy zapalony piksel znaku to osobny prostokat
+    fn add_text(vertices: &mut Vec<f32>, text: &str, start: (f32, f32), width: f32, height: f32){
+        let scale = HotbarRenderer::TEXT_SCALE;
+        for (i, c) in text.chars().enumerate(){
+            let x = start.0+i as f32*(GLYPH_WIDTH+1) as f32*scale;
+            for (row, bits) in get_glyph(c).into_iter().enumerate(){
+                let y = start.1+(GLYPH_HEIGHT-1-row) as f32*scale;
+                for column in (0..GLYPH_WIDTH).filter(|column| bits >> (GLYPH_WIDTH-1-column) & 1 == 1){
+                    let px = x+column as f32*scale;
+                    HotbarRenderer::add_rect(vertices, (px, y), (px+scale, y+scale), width, height, [0.9, 0.9, 0.9]);
+                }
+            }
+        }
+    }
+    fn draw_vertices(&self, vertices: &[f32]){
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertices) as gl::types::GLsizeiptr,
+                vertices.as_ptr() as *const _,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::DrawArrays(gl::TRIANGLES, 0, (vertices.len()/5) as i32);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
+
+struct GpuChunkMesh{
+    chunk_position: Coord3,
+    aabb: Aabb,
+    vao: u32,
+    vbo: u32,
+    vertex_count: i32,
+    vao_water: u32,
+    vbo_water: u32,
+    water_vertex_count: i32
+}
+impl GpuChunkMesh {
+    fn new(mesh: &ChunkMesh) -> Self{
+        let (vao, vbo) = upload_vertices(mesh.get_vertices());
+        let (vao_water, vbo_water) = upload_vertices(mesh.get_vertices_water());
+        GpuChunkMesh{
+            chunk_position: mesh.get_chunk_position(),
+            aabb: mesh.get_aabb(),
+            vao,
+            vbo,
+            vertex_count: (mesh.get_vertices().len()/ChunkMesher::VERTEX_SIZE) as i32,
+            vao_water,
+            vbo_water,
+            water_vertex_count: (mesh.get_vertices_water().len()/ChunkMesher::VERTEX_SIZE) as i32
+        }
+    }
+}
+impl Drop for GpuChunkMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.vbo_water);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteVertexArrays(1, &self.vao_water);
+        }
+    }
+}
 
-    // Tworzenie VBO i VAO
+fn upload_vertices(vertices: &[f32]) -> (u32, u32){
     let mut vbo = 0;
     let mut vao = 0;
-    
+    let stride = (ChunkMesher::VERTEX_SIZE * std::mem::size_of::<f32>()) as i32;
     unsafe {
         // Generowanie VAO
         gl::GenVertexArrays(1, &mut vao);
@@ -106,7 +529,7 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
         gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
         gl::BufferData(
             gl::ARRAY_BUFFER,
-            (vertices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+            std::mem::size_of_val(vertices) as gl::types::GLsizeiptr,
             vertices.as_ptr() as *const _,
             gl::STATIC_DRAW,
         );
@@ -118,7 +541,7 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
             3,
             gl::FLOAT,
             gl::FALSE,
-            (9 * std::mem::size_of::<f32>()) as i32,
+            stride,
             std::ptr::null(),
         );
 
@@ -128,7 +551,7 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
             3,
             gl::FLOAT,
             gl::FALSE,
-            (9 * std::mem::size_of::<f32>()) as i32,
+            stride,
             (3 * std::mem::size_of::<f32>()) as *const () as *const _,
         );
 
@@ -138,67 +561,135 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
             3,
             gl::FLOAT,
             gl::FALSE,
-            (9 * std::mem::size_of::<f32>()) as i32,
+            stride,
             (6 * std::mem::size_of::<f32>()) as *const () as *const _,
         );
     }
+    (vao, vbo)
+}
 
-    let mut vbo2 = 0;
-    let mut vao2 = 0;
-
-    unsafe {
-        // Generowanie VAO
-        gl::GenVertexArrays(1, &mut vao2);
-        gl::BindVertexArray(vao2);
+// przebudowuje siatki chunkow, na ktore wplywaja zmienione woksele
+fn remesh_changed(
+    changed: &[Coord3],
+    chunk_map: &impl ChunkMaster,
+    mesher: &ChunkMesher,
+    gpu_meshes: &mut HashMap<Coord3, GpuChunkMesh>,
+    visibility_map: &mut HashMap<Coord3, ChunkVisibility>
+){
+    let chunks: HashSet<Coord3> = changed.iter().flat_map(|position| get_affected_chunks(*position)).collect();
+    remesh_chunks(chunks, chunk_map, mesher, gpu_meshes, visibility_map);
+}
 
-        // Generowanie VBO
-        gl::GenBuffers(1, &mut vbo2);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo2);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (vertices_water.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
-            vertices_water.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
+// nowy chunk zmienia tez sciany na granicach sasiadow
+fn get_chunks_with_neighbours(chunks: &[Coord3]) -> HashSet<Coord3>{
+    chunks.iter()
+        .flat_map(|position| Coord3::neighbors_into_iter().map(move |offset| *position+offset).chain([*position]))
+        .collect()
+}
 
-        // Ustawianie atrybutów wierzchołków
-        gl::EnableVertexAttribArray(0);
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (9 * std::mem::size_of::<f32>()) as i32,
-            std::ptr::null(),
-        );
+fn update_lod_tiles(builder: &LodBuilder, (center, load_distance): (Coord3, i32), lod_tiles: &mut HashMap<Coord3, (Vec<Option<i32>>, Option<GpuChunkMesh>)>){
+    let keys: HashMap<Coord3, Vec<Option<i32>>> = builder.get_tile_columns(center, load_distance).iter()
+        .map(|(column, _)| (*column, builder.get_tile_key(*column, center, load_distance)))
+        .collect();
+    lod_tiles.retain(|column, (key, _)| keys.get(column) == Some(key));
+    let changed: Vec<Coord3> = keys.keys().filter(|column| !lod_tiles.contains_key(column)).copied().collect();
+    for mesh in builder.build_tiles(&changed, center, load_distance).expect("cannot read chunk"){
+        let column = mesh.get_chunk_position();
+        let gpu_mesh = (!mesh.is_empty()).then(|| GpuChunkMesh::new(&mesh));
+        lod_tiles.insert(column, (keys[&column].clone(), gpu_mesh));
+    }
+}
 
-        gl::EnableVertexAttribArray(1);
-        gl::VertexAttribPointer(
-            1,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (9 * std::mem::size_of::<f32>()) as i32,
-            (3 * std::mem::size_of::<f32>()) as *const () as *const _,
-        );
+fn remesh_chunks(
+    chunks: HashSet<Coord3>,
+    chunk_map: &impl ChunkMaster,
+    mesher: &ChunkMesher,
+    gpu_meshes: &mut HashMap<Coord3, GpuChunkMesh>,
+    visibility_map: &mut HashMap<Coord3, ChunkVisibility>
+){
+    for chunk_position in chunks{
+        let mesh = match chunk_map.get_chunk(chunk_position) {
+            Some(chunk) => mesher.build_mesh(&chunk, chunk_map),
+            None => continue
+        };
+        visibility_map.insert(chunk_position, mesh.get_visibility());
+        if mesh.is_empty(){
+            gpu_meshes.remove(&chunk_position);
+        }
+        else{
+            gpu_meshes.insert(chunk_position, GpuChunkMesh::new(&mesh));
+        }
+    }
+}
 
-        gl::EnableVertexAttribArray(2);
-        gl::VertexAttribPointer(
-            2,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (9 * std::mem::size_of::<f32>()) as i32,
-            (6 * std::mem::size_of::<f32>()) as *const () as *const _,
-        );
+// gracze z serwera jako obiekty bez grawitacji, pozycja gracza to jego oczy
+fn sync_remote_players(client: &Client, entities: &mut EntityStore, remote_players: &mut HashMap<u32, EntityId>){
+    let half_extents = Vec3::new(Player::WIDTH/2.0, Player::HEIGHT/2.0, Player::WIDTH/2.0);
+    remote_players.retain(|id, entity|{
+        let connected = client.get_players().contains_key(id);
+        if !connected{
+            entities.despawn(*entity);
+        }
+        connected
+    });
+    for (id, position) in client.get_players(){
+        let center = *position-Vec3::new(0.0, Player::EYE_HEIGHT-Player::HEIGHT/2.0, 0.0);
+        let entity_id = *remote_players.entry(*id).or_insert_with(||{
+            let mut entity = Entity::new(center, half_extents);
+            entity.gravity = false;
+            entity.color = Vector3::new(0.2, 0.3, 0.8);
+            entities.spawn(entity)
+        });
+        if let Some(entity) = entities.get_mut(entity_id){
+            entity.transform.position = center;
+        }
     }
+}
+
+// z klientem swiat i zmiany blokow przychodza z serwera
+pub fn draw<W: ChunkMaster + 'static>(chunk_map: W, meshes: Vec<ChunkMesh>, shadow_settings: ShadowSettings, settings: &Settings, storage: Option<WorldStorage>, mut client: Option<Client>) {
+    let event_loop = EventLoop::new();
+    let window_builder = WindowBuilder::new()
+        .with_title("OpenGL game")
+        .with_inner_size(LogicalSize::new(settings.width as f64, settings.height as f64));
+
+    let gl_window: glutin::ContextWrapper<glutin::NotCurrent, glutin::window::Window> = ContextBuilder::new()
+        .with_vsync(settings.vsync)
+        .build_windowed(window_builder, &event_loop)
+        .unwrap();
+
+    let gl_window = unsafe { gl_window.make_current().unwrap() };
+
+    gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
+
+    // Tworzenie VBO i VAO dla kazdego chunka
+    let mut visibility_map: HashMap<Coord3, ChunkVisibility> = meshes.iter()
+        .map(|mesh| (mesh.get_chunk_position(), mesh.get_visibility()))
+        .collect();
+    let mut gpu_meshes: HashMap<Coord3, GpuChunkMesh> = meshes.iter()
+        .filter(|mesh| !mesh.is_empty())
+        .map(|mesh| (mesh.get_chunk_position(), GpuChunkMesh::new(mesh)))
+        .collect();
+    drop(meshes);
+    let mesher = ChunkMesher::new();
+    // tyle chunkow na klatke jest wczytywanych albo generowanych przy ruchu gracza
+    const MAX_STREAMED_CHUNKS: usize = 2;
+    let generator = WorldGenerator::new(settings.seed);
+    // kafelki LOD wokol chunka gracza z kluczami, po przejsciu do innego chunka albo zmianie zasiegu
+    // budowane sa od nowa tylko te, ktorych klucz sie zmienil; puste kafelki nie maja siatki na GPU
+    let mut lod_tiles: HashMap<Coord3, (Vec<Option<i32>>, Option<GpuChunkMesh>)> = HashMap::new();
+    let mut lod_view: Option<(Coord3, i32)> = None;
 
     // Kompilacja shaderów
     let shader_program = compile_shader_program(VERTEX_SHADER, FRAGMENT_SHADER);
+    let mut shadow_map = ShadowMap::new(shadow_settings);
+    let block_outline = BlockOutline::new();
+    let hotbar_renderer = HotbarRenderer::new();
+    let entity_renderer = EntityRenderer::new();
                 
                 let start_time: Instant = Instant::now();
-                let mut frame_cnt = 0;
-                let mut delta = start_time.elapsed().as_millis();
+                let mut last_frame = Instant::now();
+                let mut game_loop = GameLoop::default();
 
                 let mut time_location = 0;
                 unsafe {
@@ -209,10 +700,6 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
                     projection_location = gl::GetUniformLocation(shader_program, CString::new("projection").unwrap().as_ptr());
                 };
                 
-                let mut lpos = 0;
-                unsafe {
-                    lpos = gl::GetUniformLocation(shader_program, CString::new("lightPos").unwrap().as_ptr());
-                };
                 let mut vpos = 0;
                 unsafe {
                     vpos = gl::GetUniformLocation(shader_program, CString::new("viewVector").unwrap().as_ptr());
@@ -233,106 +720,304 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
                     gl::Enable(gl::CULL_FACE);
                     gl::CullFace(gl::BACK);
                 }
-                let mut player = Player::new();
                 let mut blocked = false;
-                let (mut w, mut s, mut a, mut d) = (false, false, false, false);
+                let mut occlusion_culling = true;
+                let mut hotbar = Hotbar::default();
+                let mut target: Option<RayHit> = None;
+                let bindings = Bindings::load_or_default(Bindings::FILE);
+                let mut game = GameState::new(chunk_map, Player::new(), InputState::new(bindings));
+                // ziarno zna tylko serwer
+                if client.is_none(){
+                    game.set_seed(settings.seed);
+                }
+                game.set_render_distance(settings.view_distance);
+                // chunki zmienione od startu, zapisywane przy zamknieciu
+                let mut dirty_chunks: HashSet<Coord3> = HashSet::new();
+                // pozostali gracze sa rysowani jako obiekty
+                let mut remote_players: HashMap<u32, EntityId> = HashMap::new();
+                let mut console = Console::default();
+                if let Some(client) = &client{
+                    game.player.set_position(client.get_spawn());
+                    // swiat zmienia tylko serwer
+                    game.random_ticks = RandomTicker::new(RandomTicker::DEFAULT_SEED, 0);
+                    game.prediction = Some(MovementPrediction::default());
+                }
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::LoopDestroyed => return,
+            Event::LoopDestroyed => {
+                if let Some(storage) = &storage{
+                    for position in dirty_chunks.iter(){
+                        if let Some(chunk) = game.world.get_chunk(*position){
+                            storage.save_chunk(&chunk).expect("cannot save chunk");
+                        }
+                    }
+                    println!("saved {} chunks to {}", dirty_chunks.len(), storage.get_dir().display());
+                }
+            },
             Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
                 *control_flow = ControlFlow::Exit
             },
             Event::MainEventsCleared => {
                 unsafe {
-                    gl::ClearColor(0.2, 0.3, 0.3, 1.0);
-                    gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-                    gl::UseProgram(shader_program);
-                    
-                            let interval = start_time.elapsed().as_micros()-delta;
-                            frame_cnt+=1;
-                            if frame_cnt%100 == 0 {
-                                println!("{interval} {}", 1000000/interval);
+                            let frame_time = last_frame.elapsed().as_secs_f32();
+                            last_frame = Instant::now();
+                            for action in game.input.take_triggered(){
+                                match action {
+                                    Action::ToggleCursor => {
+                                        blocked=!blocked;
+                                        let window = gl_window.window();
+                                        let size = window.inner_size();
+                                        let center_x = size.width as f64 / 2.0;
+                                        let center_y = size.height as f64 / 2.0;
+
+                                        window.set_cursor_position(glutin::dpi::PhysicalPosition::new(center_x, center_y))
+                                        .expect("Nie można ustawić pozycji kursora");
+                                        gl_window.window().set_cursor_visible(!blocked);
+                                    }
+                                    Action::ToggleFlying => {
+                                        game.player.toggle_flying();
+                                        println!("flying: {}", game.player.is_flying());
+                                    }
+                                    Action::ToggleOcclusionCulling => {
+                                        occlusion_culling=!occlusion_culling;
+                                        println!("occlusion culling: {occlusion_culling}");
+                                    }
+                                    Action::SelectSlot(slot) => hotbar.select(slot),
+                                    // klawisz otwierajacy jest juz obsluzony, wiec jego znak nie trafia do konsoli
+                                    Action::OpenConsole => {
+                                        console.open();
+                                        game.input.release_all();
+                                    }
+                                    Action::SpawnEntity => {
+                                        let direction = game.player.get_rotation().to_direction(Vec3::FORWARD);
+                                        let mut entity = Entity::new(game.player.get_position()+direction*2.0, Vec3::new(0.3, 0.3, 0.3));
+                                        entity.velocity = direction*8.0;
+                                        game.entities.spawn(entity);
+                                    }
+                                    // stwor idacy za graczem, stawiany na bloku, na ktory patrzy gracz
+                                    Action::SpawnMob => {
+                                        if let Some(hit) = target{
+                                            let feet = hit.position+hit.normal;
+                                            let position = Vec3::new(feet.x as f32, feet.y as f32-0.5+MobController::HALF_EXTENTS.y, feet.z as f32);
+                                            game.mobs.spawn(&mut game.entities, position, Behaviour::Follow{distance: 2});
+                                        }
+                                    }
+                                    Action::Break | Action::Place => {
+                                        if let Some(hit) = target.filter(|_| blocked){
+                                            let (position, block) = if action == Action::Break {
+                                                (hit.position, BlockType::Air)
+                                            } else {
+                                                (hit.position+hit.normal, hotbar.get_selected())
+                                            };
+                                            let placeable = block == BlockType::Air || (hit.normal != Coord3::ZERO
+                                                && game.player.can_place_block(position)
+                                                && game.world.get_voxel(position).is_transparent());
+                                            if placeable{
+                                                match &mut client {
+                                                    Some(client) => {
+                                                        if client.request_block(&game.world, position, block){
+                                                            game.mark_changed(position);
+                                                        }
+                                                    }
+                                                    None => {
+                                                        game.set_block(position, block);
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            let alpha = game_loop.advance(frame_time, &mut game);
+                            let mut changed = game.take_changed_blocks();
+                            if let Some(client) = &mut client{
+                                let prediction = game.prediction.as_mut().unwrap();
+                                for (sequence, position) in prediction.take_unsent(){
+                                    client.send_position(sequence, position);
+                                }
+                                let update = client.poll(&game.world);
+                                for (sequence, position) in update.corrections{
+                                    prediction.reconcile(sequence, position, &mut game.player, &game.world);
+                                }
+                                changed.extend(update.blocks);
+                                if !update.chunks.is_empty(){
+                                    remesh_chunks(get_chunks_with_neighbours(&update.chunks), &game.world, &mesher, &mut gpu_meshes, &mut visibility_map);
+                                }
+                                for remote in update.entities{
+                                    game.entities.spawn(Entity::new(remote.position, remote.half_extents));
+                                }
+                                for text in console.take_chat(){
+                                    client.send_chat(&text);
+                                }
+                                for (player_id, text) in update.chat{
+                                    console.add_line(format!("<player {player_id}> {text}"));
+                                }
+                                sync_remote_players(client, &mut game.entities, &mut remote_players);
+                            }
+                            if storage.is_some(){
+                                dirty_chunks.extend(changed.iter().map(|position| position.div_euclid(Chunk::CHUNK_SIZE as i32)));
+                            }
+                            if !changed.is_empty(){
+                                remesh_changed(&changed, &game.world, &mesher, &mut gpu_meshes, &mut visibility_map);
                             }
-                            delta = start_time.elapsed().as_micros();
-                            player.go(w, s, a, d, interval as f32*1e-6);
-                            let d = delta as f32/1e6/21.0;
+                            // lokalny swiat laduje sie wokol gracza w zasiegu /renderdistance
+                            if client.is_none(){
+                                let (loaded, unloaded) = game.stream_chunks(&generator, storage.as_ref(), MAX_STREAMED_CHUNKS).expect("cannot read chunk");
+                                let mut unloaded_positions = Vec::with_capacity(unloaded.len());
+                                for chunk in unloaded{
+                                    let position = chunk.get_chunk_position();
+                                    if let Some(storage) = &storage{
+                                        if dirty_chunks.remove(&position){
+                                            storage.save_chunk(&chunk).expect("cannot save chunk");
+                                        }
+                                    }
+                                    gpu_meshes.remove(&position);
+                                    visibility_map.remove(&position);
+                                    unloaded_positions.push(position);
+                                }
+                                // chunki na nowej krawedzi dostaja sciany od strony kafelkow LOD
+                                if !unloaded_positions.is_empty(){
+                                    remesh_chunks(get_chunks_with_neighbours(&unloaded_positions), &game.world, &mesher, &mut gpu_meshes, &mut visibility_map);
+                                }
+                                if !loaded.is_empty(){
+                                    remesh_chunks(get_chunks_with_neighbours(&loaded), &game.world, &mesher, &mut gpu_meshes, &mut visibility_map);
+                                }
+                                let view = (game.get_player_chunk(), game.render_distance);
+                                if lod_view != Some(view){
+                                    lod_view = Some(view);
+                                    let builder = LodBuilder::new(&generator, LodSettings::default());
+                                    let builder = match &storage {
+                                        Some(storage) => builder.with_storage(storage).expect("cannot read world"),
+                                        None => builder
+                                    };
+                                    update_lod_tiles(&builder, view, &mut lod_tiles);
+                                }
+                            }
+                            if game_loop.get_frames()%100 == 0 {
+                                println!("frame: {:.2} ms, ticks: {}", frame_time*1e3, game_loop.get_tick());
+                            }
+                            let d = start_time.elapsed().as_secs_f32()/21.0;
                             //let r = 120.0;
                             //let camera_position = Vec3::new(r*d.sin(), 30.25, r*d.cos());
-                            camera.set_camera_position(player.get_position());
+                            camera.set_camera_position(game.player.get_interpolated_position(alpha));
                             ///println!("{}", camera.get_camera_position());
-                            let view_vector = player.get_rotation().to_direction(Vec3::FORWARD);
+                            let view_vector = game.player.get_rotation().to_direction(Vec3::FORWARD);
                             camera.set_look_at(view_vector+camera.get_camera_position());
-                            let ratio = gl_window.window().inner_size().width as f32/gl_window.window().inner_size().height as f32;
+                            let window_size = gl_window.window().inner_size();
+                            let ratio = window_size.width as f32/window_size.height as f32;
+
+                    shadow_map.set_sun_direction(game.get_sun_direction());
+                    let (light_space_matrices, cascade_splits) = shadow_map.calculate_cascades(&camera, ratio);
+                    shadow_map.render(&light_space_matrices, |light_frustum|{
+                        for mesh in gpu_meshes.values().chain(lod_tiles.values().filter_map(|(_, mesh)| mesh.as_ref())){
+                            if mesh.vertex_count > 0 && light_frustum.intersects_aabb(&mesh.aabb){
+                                gl::BindVertexArray(mesh.vao);
+                                gl::DrawArrays(gl::TRIANGLES, 0, mesh.vertex_count);
+                            }
+                        }
+                    });
+                    gl::Viewport(0, 0, window_size.width as i32, window_size.height as i32);
+
+                    gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+                    gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                    gl::UseProgram(shader_program);
+                    shadow_map.bind(shader_program, 0, &light_space_matrices, &cascade_splits);
 
                     gl::Uniform1f(time_location, d);
                     
-                    gl::Uniform3f(lpos, 0.0, 135.0, 0.0);
                     gl::Uniform3f(lcol, 0.2, 0.2, 0.15);
                     gl::Uniform3f(vpos, view_vector.x, view_vector.y, view_vector.z);
 
                     gl::UniformMatrix4fv(projection_location, 1, gl::FALSE, camera.get_projection_matrix(ratio).as_ptr());
                     gl::UniformMatrix4fv(view_location, 1, gl::FALSE, camera.get_view_matrix().as_ptr());
 
-                    gl::BindVertexArray(vao);
+                    let frustum = camera.get_frustum(ratio);
+                    let mut stats = DrawStats{total: gpu_meshes.len(), ..Default::default()};
+                    let camera_chunk = camera.get_camera_position().to_voxel_coord().div_euclid(Chunk::CHUNK_SIZE as i32);
+                    let in_frustum: Vec<&GpuChunkMesh> = gpu_meshes.values()
+                        .filter(|mesh| mesh.chunk_position.distance2(camera_chunk) <= game.render_distance*game.render_distance)
+                        .filter(|mesh| frustum.intersects_aabb(&mesh.aabb))
+                        .collect();
+                    stats.frustum_culled = stats.total-in_frustum.len();
+                    let visible: Vec<&GpuChunkMesh> = if occlusion_culling && visibility_map.contains_key(&camera_chunk){
+                        let reachable = find_visible_chunks(
+                            camera_chunk,
+                            &frustum,
+                            ChunkLoader::MAX_LOAD_DISTANCE*2,
+                            |position| visibility_map.get(&position).copied()
+                        );
+                        in_frustum.into_iter().filter(|mesh| reachable.contains(&mesh.chunk_position)).collect()
+                    }
+                    else{
+                        in_frustum
+                    };
+                    stats.drawn = visible.len();
+                    stats.occlusion_culled = stats.total-stats.frustum_culled-stats.drawn;
+                    let visible_lod: Vec<&GpuChunkMesh> = lod_tiles.values().filter_map(|(_, mesh)| mesh.as_ref())
+                        .filter(|mesh| frustum.intersects_aabb(&mesh.aabb))
+                        .collect();
+                    stats.lod_drawn = visible_lod.len();
+                    let visible: Vec<&GpuChunkMesh> = visible.into_iter().chain(visible_lod).collect();
+                    if game_loop.get_frames()%100 == 0 {
+                        println!("{stats}");
+                    }
+
                     gl::Disable(gl::BLEND);
                     gl::Enable(gl::CULL_FACE);
-                    gl::DrawArrays(gl::TRIANGLES, 0, (vertices.len()/9) as i32);
+                    for mesh in visible.iter().filter(|mesh| mesh.vertex_count > 0){
+                        gl::BindVertexArray(mesh.vao);
+                        gl::DrawArrays(gl::TRIANGLES, 0, mesh.vertex_count);
+                    }
 
-                    gl::BindVertexArray(vao2);
                     gl::Enable(gl::BLEND);
                     gl::Disable(gl::CULL_FACE);
                     gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-                    gl::DrawArrays(gl::TRIANGLES, 0, (vertices_water.len()/9) as i32);   
+                    for mesh in visible.iter().filter(|mesh| mesh.water_vertex_count > 0){
+                        gl::BindVertexArray(mesh.vao_water);
+                        gl::DrawArrays(gl::TRIANGLES, 0, mesh.water_vertex_count);
+                    }
+
+                    entity_renderer.draw(&game.entities, alpha, game.get_sun_direction(), &camera.get_view_matrix(), &camera.get_projection_matrix(ratio));
+
+                    target = game.world.raycast(camera.get_camera_position(), view_vector, Player::REACH);
+                    gl::Disable(gl::BLEND);
+                    if let Some(hit) = target{
+                        block_outline.draw(hit.position, &camera.get_view_matrix(), &camera.get_projection_matrix(ratio));
+                    }
+                    hotbar_renderer.draw(&hotbar, window_size.width as f32, window_size.height as f32);
+                    hotbar_renderer.draw_console(&console, window_size.width as f32, window_size.height as f32);
                 }
 
                 gl_window.swap_buffers().unwrap();
             },
             Event::WindowEvent { event: WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode, .. }, .. }, .. } => {
-                if let Some(keycode) = virtual_keycode {
-                    match (keycode, state) {
-                        (VirtualKeyCode::W, ElementState::Pressed) => {
-                            w=true;
-                        }
-                        (VirtualKeyCode::S, ElementState::Pressed) => {
-                            s=true;
-                        }
-                        (VirtualKeyCode::A, ElementState::Pressed) => {
-                            a=true;
-                        }
-                        (VirtualKeyCode::D, ElementState::Pressed) => {
-                            d=true;
-                        }
-                        (VirtualKeyCode::W, ElementState::Released) => {
-                            w=false;
-                        }
-                        (VirtualKeyCode::S, ElementState::Released) => {
-                            s=false;
-                        }
-                        (VirtualKeyCode::A, ElementState::Released) => {
-                            a=false;
-                        }
-                        (VirtualKeyCode::D, ElementState::Released) => {
-                            d=false;
-                        }
-
-                        (VirtualKeyCode::E, ElementState::Pressed) => {
-                            blocked=!blocked;
-                            let window = gl_window.window();
-                            let size = window.inner_size();
-                            let center_x = size.width as f64 / 2.0;
-                            let center_y = size.height as f64 / 2.0;
-
-                            window.set_cursor_position(glutin::dpi::PhysicalPosition::new(center_x, center_y))
-                            .expect("Nie można ustawić pozycji kursora");
-                            gl_window.window().set_cursor_visible(!blocked);
+                match virtual_keycode {
+                    // otwarta konsola zabiera klawiature grze
+                    Some(keycode) if console.is_open() && state == ElementState::Pressed => match keycode {
+                        VirtualKeyCode::Return => {
+                            if let Some(output) = console.submit(&mut game){
+                                println!("{output}");
+                            }
                         }
+                        VirtualKeyCode::Back => console.backspace(),
+                        VirtualKeyCode::Escape => console.close(),
                         _ => {}
-                    }
+                    },
+                    Some(_) if console.is_open() => {}
+                    Some(keycode) => game.input.handle(InputKey::Key(keycode), state == ElementState::Pressed),
+                    None => {}
                 }
             },
+            Event::WindowEvent { event: WindowEvent::ReceivedCharacter(c), .. } if console.is_open() => {
+                console.type_char(c);
+            },
+            Event::WindowEvent { event: WindowEvent::MouseInput { state, button, .. }, .. } => {
+                game.input.handle(InputKey::Mouse(button), state == ElementState::Pressed);
+            },
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
                 ..
@@ -357,18 +1042,29 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
                     let delta_y = position.y - center_y;
                     
                     let mouse_sensivity= 0.005;
-                    player.rotate(delta_x as f32, delta_y as f32, mouse_sensivity);
+                    game.player.rotate(delta_x as f32, delta_y as f32, mouse_sensivity);
                 
                     window.set_cursor_position(glutin::dpi::PhysicalPosition::new(center_x, center_y))
                     .expect("Nie można ustawić pozycji kursora");
                 }
             },
+            Event::WindowEvent { event: WindowEvent::MouseWheel { delta, .. }, .. } => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32
+                };
+                if steps != 0.0{
+                    // kolko w gore wybiera poprzedni slot
+                    hotbar.scroll(-steps.signum() as i32);
+                }
+            },
             Event::WindowEvent {
                 event: WindowEvent::Focused(focused),
                 ..
             } => {
                 // Ustaw flagę w zależności od stanu fokusu
                 if !focused{
+                    game.input.release_all();
                     blocked=false;
                     gl_window.window().set_cursor_visible(!blocked);
                 }
@@ -378,7 +1074,7 @@ pub fn draw(mut vertices: Vec<f32>, mut vertices_water: Vec<f32>) {
     });
 }
 
-fn compile_shader_program(vertex_shader_source: &str, fragment_shader_source: &str) -> gl::types::GLuint {
+pub fn compile_shader_program(vertex_shader_source: &str, fragment_shader_source: &str) -> gl::types::GLuint {
     unsafe {
         let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
         let c_str_vert = std::ffi::CString::new(vertex_shader_source.as_bytes()).unwrap();
@@ -0,0 +1,162 @@
+use crate::{block::BlockType, chunk::Chunk, chunk_master::ChunkMaster, math::{Coord3, Rng}};
+
+// co tick losuje kilka wokseli w kazdym zaladowanym chunku i wywoluje zachowanie ich bloku,
+// generator z ziarnem, zeby przy tym samym swiecie wynik byl powtarzalny
+pub struct RandomTicker{
+    rng: Rng,
+    ticks_per_chunk: usize
+}
+impl Default for RandomTicker {
+    fn default() -> Self {
+        RandomTicker::new(RandomTicker::DEFAULT_SEED, RandomTicker::DEFAULT_TICKS_PER_CHUNK)
+    }
+}
+impl RandomTicker {
+    pub const DEFAULT_SEED: u64 = 2137;
+    pub const DEFAULT_TICKS_PER_CHUNK: usize = 64;
+
+    pub fn new(seed: u64, ticks_per_chunk: usize) -> Self{
+        RandomTicker{rng: Rng::new(seed), ticks_per_chunk}
+    }
+    // ziarno swiata, zeby rozne swiaty nie mialy tych samych losowan
+    pub fn set_seed(&mut self, seed: u64){
+        self.rng = Rng::new(seed);
+    }
+    fn random_offset(&mut self) -> Coord3{
+        Coord3::new(self.rng.range(3)-1, self.rng.range(3)-1, self.rng.range(3)-1)
+    }
+    // jeden krok, zwraca zmienione woksele
+    pub fn tick(&mut self, world: &impl ChunkMaster) -> Vec<Coord3>{
+        // kolejnosc chunkow w swiecie nie jest stala, wiec sa sortowane
+        let mut chunks: Vec<Coord3> = world.get_chunk_positions().into_iter()
+            .filter(|position| world.get_chunk(*position).is_some_and(|chunk| !chunk.is_empty()))
+            .collect();
+        chunks.sort_by_key(|position| (position.x, position.y, position.z));
+
+        let size = Chunk::CHUNK_SIZE as i32;
+        let mut changed = Vec::new();
+        for chunk_position in chunks{
+            for _ in 0..self.ticks_per_chunk{
+                let local = Coord3::new(self.rng.range(size), self.rng.range(size), self.rng.range(size));
+                let position = chunk_position*size+local;
+                if let Some(changed_position) = self.tick_block(position, world){
+                    changed.push(changed_position);
+                }
+            }
+        }
+        changed
+    }
+    // zachowania poszczegolnych blokow, zwraca zmieniony woksel
+    fn tick_block(&mut self, position: Coord3, world: &impl ChunkMaster) -> Option<Coord3>{
+        match world.get_voxel(position) {
+            BlockType::Grass => self.tick_grass(position, world),
+            _ => None
+        }
+    }
+    // przykryta trawa zamienia sie w ziemie, odkryta rozrasta sie na sasiednia ziemie
+    fn tick_grass(&mut self, position: Coord3, world: &impl ChunkMaster) -> Option<Coord3>{
+        if world.get_voxel(position.upper()).is_solid(){
+            world.set_voxel(position, BlockType::Dirt);
+            return Some(position);
+        }
+        let target = position+self.random_offset();
+        if world.get_voxel(target) == BlockType::Dirt
+            && world.get_voxel(target.upper()) == BlockType::Air
+            && world.set_voxel(target, BlockType::Grass){
+            return Some(target);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_master::MemoryWorld;
+
+    use super::*;
+
+    // pole ziemi 5x5 na y=0 z trawa w rogu i ziemia przykryta kamieniem
+    fn test_world() -> MemoryWorld{
+        let world = MemoryWorld::default();
+        world.fill(Coord3::ZERO, Coord3::new(4, 0, 4), BlockType::Dirt);
+        world.set_voxel(Coord3::new(0, 0, 0), BlockType::Grass);
+        world.set_voxel(Coord3::new(2, 1, 2), BlockType::Stone);
+        world
+    }
+
+    fn run(ticker: &mut RandomTicker, world: &MemoryWorld, ticks: usize) -> Vec<Coord3>{
+        let mut changed = Vec::new();
+        for _ in 0..ticks{
+            changed.extend(ticker.tick(world));
+        }
+        changed
+    }
+
+    fn snapshot(world: &MemoryWorld) -> Vec<BlockType>{
+        let mut blocks = Vec::new();
+        for x in 0..5{
+            for z in 0..5{
+                blocks.push(world.get_voxel(Coord3::new(x, 0, z)));
+            }
+        }
+        blocks
+    }
+
+    // caly spod chunka na przemian z trawy i ziemi, zeby losowanie czesto trafialo w trawe
+    fn checkerboard_world() -> MemoryWorld{
+        let world = test_world();
+        for x in 0..Chunk::CHUNK_SIZE as i32{
+            for z in 0..Chunk::CHUNK_SIZE as i32{
+                let block = if (x+z)%2 == 0 {BlockType::Grass} else {BlockType::Dirt};
+                world.set_voxel(Coord3::new(x, 0, z), block);
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn grass_spreads_only_to_exposed_dirt(){
+        let world = test_world();
+        let mut ticker = RandomTicker::new(7, 0);
+        for _ in 0..200{
+            for x in 0..5{
+                for z in 0..5{
+                    ticker.tick_block(Coord3::new(x, 0, z), &world);
+                }
+            }
+        }
+        let grass = snapshot(&world).iter().filter(|block| **block == BlockType::Grass).count();
+        assert_eq!(grass, 24);
+        assert_eq!(world.get_voxel(Coord3::new(2, 0, 2)), BlockType::Dirt);
+    }
+
+    #[test]
+    fn covered_grass_turns_into_dirt(){
+        let world = test_world();
+        world.set_voxel(Coord3::new(0, 1, 0), BlockType::Stone);
+        let mut ticker = RandomTicker::default();
+        assert_eq!(ticker.tick_block(Coord3::new(0, 0, 0), &world), Some(Coord3::new(0, 0, 0)));
+        assert_eq!(world.get_voxel(Coord3::new(0, 0, 0)), BlockType::Dirt);
+        assert_eq!(ticker.tick_block(Coord3::new(0, 0, 0), &world), None);
+    }
+
+    #[test]
+    fn same_seed_gives_same_world(){
+        let first = checkerboard_world();
+        let second = checkerboard_world();
+        let first_changed = run(&mut RandomTicker::new(42, 20_000), &first, 3);
+        let second_changed = run(&mut RandomTicker::new(42, 20_000), &second, 3);
+        assert!(!first_changed.is_empty());
+        assert_eq!(first_changed, second_changed);
+        assert_eq!(snapshot(&first), snapshot(&second));
+    }
+
+    #[test]
+    fn world_seed_changes_the_ticks(){
+        let mut reseeded = RandomTicker::new(RandomTicker::DEFAULT_SEED, 20_000);
+        reseeded.set_seed(42);
+        let reseeded_changed = run(&mut reseeded, &checkerboard_world(), 3);
+        assert_eq!(reseeded_changed, run(&mut RandomTicker::new(42, 20_000), &checkerboard_world(), 3));
+        assert_ne!(reseeded_changed, run(&mut RandomTicker::new(43, 20_000), &checkerboard_world(), 3));
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use gl;
-use glutin::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use glutin::event::{ElementState, Event, KeyboardInput, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
 use glutin::window::{Window, WindowBuilder};
 use glutin::{ContextBuilder, ContextWrapper, PossiblyCurrent};
@@ -11,8 +11,9 @@ use glutin::dpi::{LogicalSize, PhysicalPosition};
 use nalgebra::Point3;
 
 use crate::camera::Camera;
+use crate::input::{Action, Bindings, InputKey, InputState};
 use crate::player::{self, Player};
-use crate::{Coord3, Vec3};
+use crate::math::Vec3;
 
 // Vertex shader w GLSL
 const VERTEX_SHADER: &str = r#"
@@ -187,7 +188,7 @@ impl AppWraper {
             gl::DepthFunc(gl::LESS);
         }
         let mut blocked = false;
-        let (mut w, mut s, mut a, mut d) = (false, false, false, false);
+        let mut input = InputState::new(Bindings::load_or_default(Bindings::FILE));
         
         event_loop.run(move |event, _, control_flow| {
                 *control_flow = ControlFlow::Poll;
@@ -203,7 +204,20 @@ impl AppWraper {
                         if frame_cnt%100 == 0 {
                             println!("{interval} {}", 1000000/interval);
                         }
-                        player.go(w, s, a, d, interval as f32*1e-6);
+                        for action in input.take_triggered(){
+                            if action == Action::ToggleCursor{
+                                blocked=!blocked;
+                                let window = gl_window.window();
+                                let size = window.inner_size();
+                                let center_x = size.width as f64 / 2.0;
+                                let center_y = size.height as f64 / 2.0;
+        
+                                window.set_cursor_position(glutin::dpi::PhysicalPosition::new(center_x, center_y))
+                                .expect("Nie można ustawić pozycji kursora");
+                                gl_window.window().set_cursor_visible(!blocked);
+                            }
+                        }
+                        player.go(&input, interval as f32*1e-6);
 
                         camera.set_camera_position(player.get_position());    
                         camera.set_look_at(player.get_rotation().to_direction(Vec3::FORWARD)+camera.get_camera_position());
@@ -223,45 +237,7 @@ impl AppWraper {
                     },
                     Event::WindowEvent { event: WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode, .. }, .. }, .. } => { 
                         if let Some(keycode) = virtual_keycode {
-                            match (keycode, state) {
-                                (VirtualKeyCode::W, ElementState::Pressed) => {
-                                    w=true;
-                                }
-                                (VirtualKeyCode::S, ElementState::Pressed) => {
-                                    s=true;
-                                }
-                                (VirtualKeyCode::A, ElementState::Pressed) => {
-                                    a=true;
-                                }
-                                (VirtualKeyCode::D, ElementState::Pressed) => {
-                                    d=true;
-                                }
-                                (VirtualKeyCode::W, ElementState::Released) => {
-                                    w=false;
-                                }
-                                (VirtualKeyCode::S, ElementState::Released) => {
-                                    s=false;
-                                }
-                                (VirtualKeyCode::A, ElementState::Released) => {
-                                    a=false;
-                                }
-                                (VirtualKeyCode::D, ElementState::Released) => {
-                                    d=false;
-                                }
-        
-                                (VirtualKeyCode::E, ElementState::Pressed) => {
-                                    blocked=!blocked;
-                                    let window = gl_window.window();
-                                    let size = window.inner_size();
-                                    let center_x = size.width as f64 / 2.0;
-                                    let center_y = size.height as f64 / 2.0;
-        
-                                    window.set_cursor_position(glutin::dpi::PhysicalPosition::new(center_x, center_y))
-                                    .expect("Nie można ustawić pozycji kursora");
-                                    gl_window.window().set_cursor_visible(!blocked);
-                                }
-                                _ => {}
-                            }
+                            input.handle(InputKey::Key(keycode), state == ElementState::Pressed);
                         }
                     },
                     Event::WindowEvent { event: WindowEvent::Resized(size), ..} => {  
@@ -282,6 +258,7 @@ impl AppWraper {
                     },
                     Event::WindowEvent { event: WindowEvent::Focused(focused), ..} => {
                         if !focused{
+                            input.release_all();
                             blocked=false;
                             gl_window.window().set_cursor_visible(!blocked);
                         }
@@ -0,0 +1,506 @@
+use std::{collections::{HashMap, HashSet}, io, net::{SocketAddr, TcpListener}, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::{Duration, Instant}};
+
+use dashmap::DashMap;
+
+use crate::{authority::{MoveCheck, PlayerAuthority}, block::BlockType, chunk::Chunk, chunk_master::ChunkMaster, math::{Coord3, Vec3}, net::Connection, persistence::WorldStorage, protocol::{Message, PROTOCOL_VERSION}, world_generator::WorldGenerator};
+
+#[derive(Clone)]
+pub struct ServerSettings{
+    pub seed: u32,
+    // promien w chunkach, w ktorym gracze dostaja swiat
+    pub view_distance: i32,
+    pub tick_rate: u32,
+    // bez katalogu swiat nie jest zapisywany
+    pub world_dir: Option<PathBuf>,
+    pub save_interval_ticks: u64
+}
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings{
+            seed: 2137,
+            view_distance: 4,
+            tick_rate: 20,
+            world_dir: None,
+            save_interval_ticks: 20*60
+        }
+    }
+}
+
+struct RemotePlayer{
+    connection: Connection,
+    authority: PlayerAuthority,
+    sent_chunks: HashSet<Coord3>
+}
+impl RemotePlayer {
+    fn get_chunk(&self) -> Coord3{
+        self.authority.get_position().to_voxel_coord().div_euclid(Chunk::CHUNK_SIZE as i32)
+    }
+}
+
+// serwer jest jedynym wlascicielem swiata, klienci dostaja chunki i prosza o zmiany blokow
+pub struct Server<W: ChunkMaster = DashMap<Coord3, Chunk>>{
+    listener: TcpListener,
+    settings: ServerSettings,
+    world: W,
+    seed: u32,
+    generator: WorldGenerator,
+    storage: Option<WorldStorage>,
+    // chunki zmienione od ostatniego zapisu
+    dirty: HashSet<Coord3>,
+    // polaczenia, ktore jeszcze nie wyslaly Handshake
+    pending: Vec<Connection>,
+    players: HashMap<u32, RemotePlayer>,
+    next_player_id: u32,
+    tick: u64,
+    // ustawiona z innego watku konczy run po zapisaniu swiata
+    stop: Arc<AtomicBool>
+}
+impl Server {
+    pub const DEFAULT_PORT: u16 = 25500;
+
+    pub fn bind(address: &str, settings: ServerSettings) -> io::Result<Server>{
+        Server::with_world(address, settings, DashMap::new())
+    }
+}
+impl<W: ChunkMaster> Server<W> {
+    const MAX_CHUNKS_PER_TICK: usize = 8;
+
+    // swiat moze byc juz czesciowo wypelniony, brakujace chunki sa wczytywane albo generowane
+    pub fn with_world(address: &str, settings: ServerSettings, world: W) -> io::Result<Server<W>>{
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let mut seed = settings.seed;
+        let storage = match &settings.world_dir {
+            Some(dir) => {
+                let storage = WorldStorage::open(dir)?;
+                seed = storage.load_or_save_seed(seed)?;
+                Some(storage)
+            }
+            None => None
+        };
+        Ok(Server{
+            listener,
+            settings,
+            world,
+            seed,
+            generator: WorldGenerator::new(seed),
+            storage,
+            dirty: HashSet::new(),
+            pending: Vec::new(),
+            players: HashMap::new(),
+            next_player_id: 1,
+            tick: 0,
+            stop: Arc::new(AtomicBool::new(false))
+        })
+    }
+    pub fn local_addr(&self) -> io::Result<SocketAddr>{
+        self.listener.local_addr()
+    }
+    pub fn get_world(&self) -> &W{
+        &self.world
+    }
+    pub fn get_seed(&self) -> u32{
+        self.seed
+    }
+    pub fn get_player_count(&self) -> usize{
+        self.players.len()
+    }
+    pub fn get_stop_flag(&self) -> Arc<AtomicBool>{
+        self.stop.clone()
+    }
+    pub fn get_spawn(&self) -> Vec3{
+        Vec3::new(0.0, self.generator.get_terrein_height(Coord3::ZERO) as f32+3.0, 0.0)
+    }
+    // chunk z pamieci, z dysku albo nowo wygenerowany
+    fn load_chunk(&self, chunk_position: Coord3) -> io::Result<()>{
+        if self.world.chunk_exists(chunk_position){
+            return Ok(());
+        }
+        let stored = match &self.storage {
+            Some(storage) => storage.load_chunk(chunk_position)?,
+            None => None
+        };
+        let chunk = stored.unwrap_or_else(||{
+            let mut chunk = Chunk::default();
+            chunk.set_chunk_position(chunk_position);
+            self.generator.generate_chunk(&mut chunk);
+            chunk
+        });
+        self.world.insert_chunk(chunk);
+        Ok(())
+    }
+    pub fn save(&mut self) -> io::Result<()>{
+        let Some(storage) = &self.storage else {
+            return Ok(());
+        };
+        for chunk_position in self.dirty.drain(){
+            if let Some(chunk) = self.world.get_chunk(chunk_position){
+                storage.save_chunk(&chunk)?;
+            }
+        }
+        Ok(())
+    }
+    fn broadcast(&mut self, message: &Message, except: Option<u32>){
+        for (id, player) in self.players.iter_mut(){
+            if Some(*id) != except{
+                let _ = player.connection.send(message);
+            }
+        }
+    }
+    fn accept_connections(&mut self){
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    let connected = stream.set_nonblocking(false).and_then(|_| Connection::new(stream));
+                    match connected {
+                        Ok(connection) => self.pending.push(connection),
+                        Err(error) => println!("connection from {address} failed: {error}")
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    println!("accept failed: {error}");
+                    break;
+                }
+            }
+        }
+    }
+    fn handle_handshakes(&mut self){
+        let mut waiting = Vec::new();
+        for mut connection in std::mem::take(&mut self.pending){
+            let version = connection.receive().into_iter().find_map(|message| match message {
+                Message::Handshake{version, ..} => Some(version),
+                _ => None
+            });
+            let Some(version) = version else {
+                if !connection.is_closed(){
+                    waiting.push(connection);
+                }
+                continue;
+            };
+            if version != PROTOCOL_VERSION{
+                let reason = format!("protocol version {version} is not supported, server uses {PROTOCOL_VERSION}");
+                let _ = connection.send(&Message::Disconnect{player_id: 0, reason});
+                connection.close();
+                continue;
+            }
+            let id = self.next_player_id;
+            self.next_player_id += 1;
+            let spawn = self.get_spawn();
+            let _ = connection.send(&Message::handshake(id, spawn));
+            for (other_id, other) in self.players.iter(){
+                let _ = connection.send(&Message::PlayerMove{player_id: *other_id, sequence: 0, position: other.authority.get_position()});
+            }
+            self.broadcast(&Message::PlayerMove{player_id: id, sequence: 0, position: spawn}, None);
+            self.players.insert(id, RemotePlayer{connection, authority: PlayerAuthority::new(spawn), sent_chunks: HashSet::new()});
+            println!("player {id} joined");
+        }
+        self.pending = waiting;
+    }
+    fn handle_messages(&mut self){
+        let ids: Vec<u32> = self.players.keys().copied().collect();
+        for id in ids{
+            let messages = self.players.get_mut(&id).unwrap().connection.receive();
+            for message in messages{
+                match message {
+                    Message::PlayerMove{sequence, position, ..} => self.handle_move(id, sequence, position),
+                    Message::BlockChange{sequence, position, block} => self.handle_block_change(id, sequence, position, block),
+                    Message::Disconnect{..} => self.players.get_mut(&id).unwrap().connection.close(),
+                    Message::Chat{text, ..} => {
+                        println!("<player {id}> {text}");
+                        self.broadcast(&Message::Chat{player_id: id, text}, Some(id));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let left: Vec<u32> = self.players.iter()
+            .filter(|(_, player)| player.connection.is_closed())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in left{
+            self.players.remove(&id);
+            self.broadcast(&Message::Disconnect{player_id: id, reason: "left".to_string()}, None);
+            println!("player {id} left");
+        }
+    }
+    fn handle_move(&mut self, id: u32, sequence: u32, position: Vec3){
+        let player = self.players.get_mut(&id).unwrap();
+        match player.authority.check_move(sequence, position) {
+            MoveCheck::Accepted => self.broadcast(&Message::PlayerMove{player_id: id, sequence: 0, position}, Some(id)),
+            MoveCheck::Rejected => {
+                let position = player.authority.get_position();
+                let _ = player.connection.send(&Message::PlayerMove{player_id: id, sequence, position});
+            }
+            MoveCheck::Stale => {}
+        }
+    }
+    fn handle_block_change(&mut self, id: u32, sequence: u32, position: Coord3, block: BlockType){
+        let player = self.players.get_mut(&id).unwrap();
+        let changed = player.authority.apply_edit(&self.world, sequence, position, block);
+        // gracz dostaje odpowiedz z numerem swojej zmiany, nawet odrzuconej
+        let _ = player.connection.send(&Message::BlockChange{sequence, position, block: self.world.get_voxel(position)});
+        if changed{
+            self.dirty.insert(position.div_euclid(Chunk::CHUNK_SIZE as i32));
+            self.broadcast(&Message::BlockChange{sequence: 0, position, block}, Some(id));
+        }
+    }
+    // najblizsze chunki, ktorych gracz jeszcze nie dostal
+    fn stream_chunks(&mut self) -> io::Result<()>{
+        let distance = self.settings.view_distance;
+        let ids: Vec<u32> = self.players.keys().copied().collect();
+        for id in ids{
+            let player = &self.players[&id];
+            let center = player.get_chunk();
+            let mut wanted: Vec<Coord3> = Vec::new();
+            for x in -distance..=distance{
+                for y in -distance..=distance{
+                    for z in -distance..=distance{
+                        let offset = Coord3::new(x, y, z);
+                        if offset.magnitude2() <= distance.pow(2) && !player.sent_chunks.contains(&(center+offset)){
+                            wanted.push(center+offset);
+                        }
+                    }
+                }
+            }
+            wanted.sort_by_key(|position| position.distance2(center));
+            for chunk_position in wanted.into_iter().take(Self::MAX_CHUNKS_PER_TICK){
+                self.load_chunk(chunk_position)?;
+                let data = self.world.get_chunk(chunk_position).unwrap().encode();
+                let player = self.players.get_mut(&id).unwrap();
+                let _ = player.connection.send(&Message::ChunkData{position: chunk_position, data});
+                player.sent_chunks.insert(chunk_position);
+            }
+        }
+        Ok(())
+    }
+    // chunki dalej niz view_distance+1 od wszystkich graczy sa zapisywane i usuwane z pamieci,
+    // zapas jednego chunka chroni przed ciaglym wczytywaniem przy chodzeniu po granicy
+    fn unload_chunks(&mut self) -> io::Result<()>{
+        let keep = (self.settings.view_distance+1).pow(2);
+        let centers: Vec<Coord3> = self.players.values().map(|player| player.get_chunk()).collect();
+        // gracz, ktory wroci, dostanie te chunki jeszcze raz
+        for player in self.players.values_mut(){
+            let center = player.get_chunk();
+            player.sent_chunks.retain(|position| position.distance2(center) <= keep);
+        }
+        for chunk_position in self.world.get_chunk_positions(){
+            if centers.iter().any(|center| chunk_position.distance2(*center) <= keep){
+                continue;
+            }
+            if self.dirty.contains(&chunk_position){
+                // bez katalogu swiata zmiany zostaja w pamieci
+                let Some(storage) = &self.storage else {
+                    continue;
+                };
+                if let Some(chunk) = self.world.get_chunk(chunk_position){
+                    storage.save_chunk(&chunk)?;
+                }
+                self.dirty.remove(&chunk_position);
+            }
+            self.world.remove_chunk(chunk_position);
+        }
+        Ok(())
+    }
+    pub fn tick(&mut self) -> io::Result<()>{
+        self.accept_connections();
+        self.handle_handshakes();
+        self.handle_messages();
+        self.stream_chunks()?;
+        self.unload_chunks()?;
+        self.tick += 1;
+        if self.tick.is_multiple_of(self.settings.save_interval_ticks.max(1)){
+            self.save()?;
+        }
+        Ok(())
+    }
+    // glowna petla serwera, konczy sie po ustawieniu flagi stop albo przy bledzie;
+    // w obu przypadkach zmiany od ostatniego zapisu sa zapisywane
+    pub fn run(&mut self) -> io::Result<()>{
+        let tick_duration = Duration::from_secs_f32(1.0/self.settings.tick_rate.max(1) as f32);
+        let result = loop {
+            if self.stop.load(Ordering::Relaxed){
+                break Ok(());
+            }
+            let start = Instant::now();
+            if let Err(error) = self.tick(){
+                break Err(error);
+            }
+            if let Some(rest) = tick_duration.checked_sub(start.elapsed()){
+                thread::sleep(rest);
+            }
+        };
+        for player in self.players.values_mut(){
+            player.connection.close();
+        }
+        result.and(self.save())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk_master::MemoryWorld, client::Client, test_util::TempDir};
+
+    use super::*;
+
+    // obie strony w jednym procesie, serwer tyka az warunek zostanie spelniony
+    fn tick_until(server: &mut Server, clients: &mut [&mut Client], world: &MemoryWorld, condition: impl Fn(&Server, &[&mut Client]) -> bool){
+        let start = Instant::now();
+        while !condition(server, clients){
+            assert!(start.elapsed() < Duration::from_secs(20), "timed out");
+            server.tick().unwrap();
+            for client in clients.iter_mut(){
+                client.poll(world);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn clients_receive_chunks_edits_and_other_players(){
+        let settings = ServerSettings{view_distance: 0, ..Default::default()};
+        let mut server = Server::bind("127.0.0.1:0", settings).unwrap();
+        let address = server.local_addr().unwrap().to_string();
+
+        let first_world = MemoryWorld::default();
+        let second_world = MemoryWorld::default();
+        let connecting = thread::spawn(move || Client::connect(&address).map(|first| (first, Client::connect(&address))));
+        let start = Instant::now();
+        while !connecting.is_finished(){
+            assert!(start.elapsed() < Duration::from_secs(20), "timed out");
+            server.tick().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        let (mut first, second) = connecting.join().unwrap().unwrap();
+        let mut second = second.unwrap();
+        assert_ne!(first.get_player_id(), second.get_player_id());
+
+        // obaj gracze stoja w chunku (0,0,0)
+        let spawn_chunk = first.get_spawn().to_voxel_coord().div_euclid(Chunk::CHUNK_SIZE as i32);
+        first.poll(&first_world);
+        tick_until(&mut server, &mut [&mut first], &first_world, |_, _| first_world.chunk_exists(spawn_chunk));
+        let second_id = second.get_player_id();
+        second.send_position(60, Vec3::new(1.0, 20.0, 2.0));
+        tick_until(&mut server, &mut [&mut first, &mut second], &second_world, |_, clients|
+            clients[0].get_players().get(&second_id) == Some(&Vec3::new(1.0, 20.0, 2.0))
+        );
+
+        // zmiana bloku przechodzi przez serwer do obu klientow
+        let position = first.get_spawn().to_voxel_coord();
+        assert!(first.request_block(&first_world, position, BlockType::Sand));
+        tick_until(&mut server, &mut [&mut first], &first_world, |server, clients|
+            clients[0].get_pending_edits() == 0 && server.get_world().get_voxel(position) == BlockType::Sand
+        );
+        assert_eq!(first_world.get_voxel(position), BlockType::Sand);
+        // zbyt daleka zmiana jest cofana przez serwer
+        let distant = position+Coord3::new(20, 0, 0);
+        assert!(first.request_block(&first_world, distant, BlockType::Sand));
+        tick_until(&mut server, &mut [&mut first], &first_world, |_, clients| clients[0].get_pending_edits() == 0);
+        assert_eq!(first_world.get_voxel(distant), server.get_world().get_voxel(distant));
+        assert_ne!(server.get_world().get_voxel(distant), BlockType::Sand);
+
+        // czat przechodzi przez serwer do drugiego gracza z id autora
+        first.send_chat("hello");
+        let start = Instant::now();
+        let chat = loop {
+            assert!(start.elapsed() < Duration::from_secs(20), "timed out");
+            server.tick().unwrap();
+            if let Some(chat) = second.poll(&second_world).chat.pop(){
+                break chat;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(chat, (first.get_player_id(), "hello".to_string()));
+
+        drop(second);
+        tick_until(&mut server, &mut [&mut first], &first_world, |server, clients|
+            server.get_player_count() == 1 && !clients[0].get_players().contains_key(&second_id)
+        );
+    }
+
+    // odpowiedz serwera na pierwsza ramke wyslana przez klienta
+    fn handshake_reply(server: &mut Server, frame: &[u8]) -> Message{
+        let mut stream = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        std::io::Write::write_all(&mut stream, frame).unwrap();
+        let mut connection = Connection::new(stream).unwrap();
+        let start = Instant::now();
+        loop {
+            assert!(start.elapsed() < Duration::from_secs(20), "timed out");
+            server.tick().unwrap();
+            if let Some(message) = connection.receive().pop(){
+                return message;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn clients_with_another_protocol_version_are_refused(){
+        let mut server = Server::bind("127.0.0.1:0", ServerSettings{view_distance: 0, ..Default::default()}).unwrap();
+        let mut newer = Vec::new();
+        Message::Handshake{version: PROTOCOL_VERSION+1, player_id: 0, position: Vec3::ZERO}.write_to(&mut newer).unwrap();
+        let reply = handshake_reply(&mut server, &newer);
+        assert!(matches!(reply, Message::Disconnect{player_id: 0, ..}));
+        // nagrany klient w wersji 1 dostaje czytelny powod
+        let reply = handshake_reply(&mut server, include_bytes!("../protocol/fixtures/v1_handshake.bin"));
+        assert_eq!(reply, Message::Disconnect{player_id: 0, reason: format!("protocol version 1 is not supported, server uses {PROTOCOL_VERSION}")});
+        assert_eq!(server.get_player_count(), 0);
+    }
+
+    #[test]
+    fn edits_are_saved_and_loaded_with_the_world(){
+        let dir = TempDir::new("server");
+        let settings = ServerSettings{view_distance: 0, seed: 7, world_dir: Some(dir.get_path().to_path_buf()), ..Default::default()};
+        let mut server = Server::bind("127.0.0.1:0", settings.clone()).unwrap();
+        server.load_chunk(Coord3::ZERO).unwrap();
+        server.world.set_voxel(Coord3::new(1, 2, 3), BlockType::Sand);
+        server.dirty.insert(Coord3::ZERO);
+        server.save().unwrap();
+        drop(server);
+
+        // ziarno z katalogu swiata ma pierwszenstwo przed ustawieniami
+        let reopened = Server::with_world("127.0.0.1:0", ServerSettings{seed: 99, ..settings}, MemoryWorld::default()).unwrap();
+        assert_eq!(reopened.get_seed(), 7);
+        reopened.load_chunk(Coord3::ZERO).unwrap();
+        assert_eq!(reopened.get_world().get_voxel(Coord3::new(1, 2, 3)), BlockType::Sand);
+    }
+
+    #[test]
+    fn stopping_the_server_saves_the_world(){
+        let dir = TempDir::new("server_stop");
+        let settings = ServerSettings{view_distance: 0, world_dir: Some(dir.get_path().to_path_buf()), save_interval_ticks: u64::MAX, ..Default::default()};
+        let mut server = Server::bind("127.0.0.1:0", settings).unwrap();
+        server.load_chunk(Coord3::ZERO).unwrap();
+        server.world.set_voxel(Coord3::new(1, 2, 3), BlockType::Sand);
+        server.dirty.insert(Coord3::ZERO);
+        server.get_stop_flag().store(true, Ordering::Relaxed);
+        server.run().unwrap();
+        let saved = WorldStorage::open(&dir).unwrap().load_chunk(Coord3::ZERO).unwrap().unwrap();
+        assert_eq!(saved.get_voxel(Coord3::new(1, 2, 3)), BlockType::Sand);
+    }
+
+    #[test]
+    fn chunks_without_players_nearby_are_unloaded(){
+        let dir = TempDir::new("server_unload");
+        let settings = ServerSettings{view_distance: 0, world_dir: Some(dir.get_path().to_path_buf()), save_interval_ticks: u64::MAX, ..Default::default()};
+        let mut server = Server::bind("127.0.0.1:0", settings).unwrap();
+        let edited = Coord3::new(3, 0, 0);
+        server.load_chunk(Coord3::ZERO).unwrap();
+        server.load_chunk(edited).unwrap();
+        server.world.set_voxel(edited*Chunk::CHUNK_SIZE as i32, BlockType::Sand);
+        server.dirty.insert(edited);
+        server.tick().unwrap();
+        assert!(server.get_world().get_chunk_positions().is_empty());
+        assert!(server.dirty.is_empty());
+        let saved = server.storage.as_ref().unwrap().load_chunk(edited).unwrap().unwrap();
+        assert_eq!(saved.get_voxel(Coord3::ZERO), BlockType::Sand);
+
+        // bez katalogu swiata zmieniony chunk zostaje w pamieci
+        let mut server = Server::bind("127.0.0.1:0", ServerSettings{view_distance: 0, ..Default::default()}).unwrap();
+        server.load_chunk(Coord3::ZERO).unwrap();
+        server.load_chunk(edited).unwrap();
+        server.dirty.insert(edited);
+        server.tick().unwrap();
+        assert_eq!(server.get_world().get_chunk_positions(), vec![edited]);
+    }
+}
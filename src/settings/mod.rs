@@ -0,0 +1,202 @@
+use std::{fmt::Display, fs, path::PathBuf, str::FromStr};
+
+use crate::{chunk_loader::ChunkLoader, server::Server};
+
+// co robi program po wczytaniu ustawien, wybierane tylko z linii polecen
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    Window,
+    // symulacja bez okna, liczba krokow
+    Headless(u64),
+    // serwer bez okna na podanym porcie
+    Server(u16),
+    Connect(String)
+}
+
+// ustawienia gry: najpierw plik, potem argumenty, ktore go nadpisuja
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings{
+    pub seed: u32,
+    // promien w chunkach
+    pub view_distance: i32,
+    // bez katalogu swiat nie jest zapisywany
+    pub world_dir: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+    pub vsync: bool,
+    pub mode: Mode
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings{
+            seed: 2137,
+            view_distance: 13,
+            world_dir: None,
+            width: 800,
+            height: 600,
+            vsync: false,
+            mode: Mode::Window
+        }
+    }
+}
+impl Settings {
+    pub const FILE: &'static str = "settings.txt";
+    pub const DEFAULT_HEADLESS_TICKS: u64 = 600;
+    pub const USAGE: &'static str = "usage: my_opengl_game [options]
+  --seed N             world seed
+  --view-distance N    view distance in chunks
+  --world DIR          load and save the world in DIR
+  --width N            window width
+  --height N           window height
+  --vsync, --no-vsync  vertical sync
+  --headless [TICKS]   run the simulation without a window
+  --server [PORT]      run a server without a window
+  --connect ADDRESS    play on a server
+  --help               show this message
+settings are read from settings.txt first, options override them";
+
+    // nazwy jak w pliku, w argumentach z '-' zamiast '_'
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String>{
+        match name {
+            "seed" => self.seed = parse_value(name, value)?,
+            "view_distance" => {
+                let distance: i32 = parse_value(name, value)?;
+                if !(1..=ChunkLoader::MAX_LOAD_DISTANCE).contains(&distance){
+                    return Err(format!("view_distance must be between 1 and {}", ChunkLoader::MAX_LOAD_DISTANCE));
+                }
+                self.view_distance = distance;
+            }
+            "world" => self.world_dir = Some(PathBuf::from(value)),
+            "width" | "height" => {
+                let size: u32 = parse_value(name, value)?;
+                if size == 0{
+                    return Err(format!("{name} must be positive"));
+                }
+                if name == "width" {self.width = size} else {self.height = size}
+            }
+            "vsync" => self.vsync = parse_value(name, value)?,
+            _ => return Err(format!("unknown setting '{name}'"))
+        }
+        Ok(())
+    }
+    // linie "nazwa = wartosc", # zaczyna komentarz
+    pub fn parse(text: &str) -> Result<Settings, String>{
+        let mut settings = Settings::default();
+        for (number, line) in text.lines().enumerate(){
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty(){
+                continue;
+            }
+            let (name, value) = line.split_once('=')
+                .ok_or(format!("line {}: expected 'name = value'", number+1))?;
+            settings.set(name.trim(), value.trim()).map_err(|error| format!("line {}: {error}", number+1))?;
+        }
+        Ok(settings)
+    }
+    // brak pliku to domyslne ustawienia, bledny plik jest zglaszany
+    pub fn load(path: &str) -> Result<Settings, String>{
+        match fs::read_to_string(path) {
+            Ok(text) => Settings::parse(&text).map_err(|error| format!("{path}: {error}")),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(format!("{path}: {error}"))
+        }
+    }
+    pub fn apply_args(&mut self, args: &[String]) -> Result<(), String>{
+        let mut mode: Option<Mode> = None;
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next(){
+            let name = arg.strip_prefix("--").ok_or(format!("unexpected argument '{arg}'"))?;
+            // opcjonalna wartosc to nastepny argument, o ile nie jest opcja
+            let mut optional = || args.next_if(|value| !value.starts_with("--")).cloned();
+            let selected = match name {
+                "vsync" => {self.vsync = true; None}
+                "no-vsync" => {self.vsync = false; None}
+                "headless" => Some(Mode::Headless(optional().map(|ticks| parse_value("headless", &ticks)).transpose()?.unwrap_or(Settings::DEFAULT_HEADLESS_TICKS))),
+                "server" => Some(Mode::Server(optional().map(|port| parse_value("server", &port)).transpose()?.unwrap_or(Server::DEFAULT_PORT))),
+                "connect" => Some(Mode::Connect(optional().ok_or("--connect needs an address")?)),
+                _ => {
+                    let value = optional().ok_or(format!("--{name} needs a value"))?;
+                    self.set(&name.replace('-', "_"), &value).map_err(|error| error.replace('_', "-"))?;
+                    None
+                }
+            };
+            if let Some(selected) = selected{
+                if mode.is_some(){
+                    return Err("only one of --headless, --server and --connect can be used".to_string());
+                }
+                mode = Some(selected);
+            }
+        }
+        if let Some(mode) = mode{
+            self.mode = mode;
+        }
+        Ok(())
+    }
+}
+impl Display for Settings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "seed = {}", self.seed)?;
+        writeln!(f, "view_distance = {}", self.view_distance)?;
+        if let Some(dir) = &self.world_dir{
+            writeln!(f, "world = {}", dir.display())?;
+        }
+        writeln!(f, "width = {}", self.width)?;
+        writeln!(f, "height = {}", self.height)?;
+        writeln!(f, "vsync = {}", self.vsync)
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T, String>{
+    value.parse().map_err(|_| format!("invalid value '{value}' for {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String>{
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn shipped_file_matches_defaults(){
+        let shipped = Settings::parse(include_str!("../../settings.txt")).unwrap();
+        assert_eq!(shipped, Settings::default());
+        let mut settings = Settings{world_dir: Some(PathBuf::from("saves/a")), seed: 7, vsync: true, ..Default::default()};
+        settings.width = 1024;
+        assert_eq!(Settings::parse(&settings.to_string()), Ok(settings));
+    }
+
+    #[test]
+    fn arguments_override_the_file(){
+        let mut settings = Settings::parse("seed = 5 # komentarz\nview_distance = 4\nvsync = true\nwidth = 1280\n").unwrap();
+        settings.apply_args(&args("--seed 9 --no-vsync --world saves/test --height 720 --headless")).unwrap();
+        assert_eq!(settings, Settings{
+            seed: 9,
+            view_distance: 4,
+            world_dir: Some(PathBuf::from("saves/test")),
+            width: 1280,
+            height: 720,
+            vsync: false,
+            mode: Mode::Headless(Settings::DEFAULT_HEADLESS_TICKS)
+        });
+        settings.apply_args(&args("--server 4000 --view-distance 2")).unwrap();
+        assert_eq!((settings.mode.clone(), settings.view_distance), (Mode::Server(4000), 2));
+        settings.apply_args(&args("--connect 127.0.0.1:25500")).unwrap();
+        assert_eq!(settings.mode, Mode::Connect("127.0.0.1:25500".to_string()));
+    }
+
+    #[test]
+    fn invalid_settings_are_reported(){
+        assert!(Settings::parse("seed 5").unwrap_err().starts_with("line 1"));
+        assert!(Settings::parse("\ncolour = red").unwrap_err().contains("line 2: unknown setting 'colour'"));
+        assert!(Settings::parse("view_distance = 0").is_err());
+        let mut settings = Settings::default();
+        assert!(settings.apply_args(&args("--width")).is_err());
+        assert!(settings.apply_args(&args("--view-distance 1000")).unwrap_err().contains("view-distance"));
+        assert!(settings.apply_args(&args("--headless 10 --connect host")).is_err());
+        assert!(settings.apply_args(&args("seed")).is_err());
+        assert!(settings.apply_args(&args("--fov 90")).is_err());
+        assert_eq!(settings, Settings::default());
+    }
+}
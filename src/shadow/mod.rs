@@ -0,0 +1,251 @@
+use std::ffi::CString;
+
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::camera::Camera;
+use crate::math::Frustum;
+use crate::math::Vec3;
+
+const SHADOW_VERTEX_SHADER: &str = r#"
+    #version 330 core
+    layout (location = 0) in vec3 position;
+
+    uniform mat4 lightSpaceMatrix;
+
+    void main() {
+        gl_Position = lightSpaceMatrix * vec4(position, 1.0);
+    }
+"#;
+
+const SHADOW_FRAGMENT_SHADER: &str = r#"
+    #version 330 core
+    void main() {
+    }
+"#;
+
+#[derive(Clone, Copy)]
+pub struct ShadowSettings{
+    pub cascade_count: usize,
+    pub resolution: i32,
+    pub sun_direction: Vec3,
+    // dalej od kamery cienie nie sa rysowane
+    pub max_distance: f32,
+    // 0 - rowne odstepy, 1 - logarytmiczne
+    pub split_lambda: f32
+}
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings{
+            cascade_count: 3,
+            resolution: 2048,
+            sun_direction: Vec3::new(-0.4, -1.0, 0.3).normalize(),
+            max_distance: 400.0,
+            split_lambda: 0.75
+        }
+    }
+}
+
+pub struct ShadowMap{
+    settings: ShadowSettings,
+    fbo: u32,
+    depth_texture: u32,
+    program: u32,
+    light_space_location: i32
+}
+
+impl ShadowMap {
+    pub const MAX_CASCADES: usize = 4;
+    // jak daleko za frustum kamery moga lezec obiekty rzucajace cien
+    const CASTER_DISTANCE: f32 = 200.0;
+
+    pub fn new(settings: ShadowSettings) -> Self{
+        let settings = ShadowSettings{
+            cascade_count: settings.cascade_count.clamp(1, ShadowMap::MAX_CASCADES),
+            resolution: settings.resolution.max(1),
+            ..settings
+        };
+        let mut shadow_map = ShadowMap{
+            settings,
+            fbo: 0,
+            depth_texture: 0,
+            program: 0,
+            light_space_location: 0
+        };
+        unsafe {
+            gl::GenTextures(1, &mut shadow_map.depth_texture);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, shadow_map.depth_texture);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::DEPTH_COMPONENT32F as i32,
+                settings.resolution,
+                settings.resolution,
+                settings.cascade_count as i32,
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+            let border = [1.0f32, 1.0, 1.0, 1.0];
+            gl::TexParameterfv(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+
+            gl::GenFramebuffers(1, &mut shadow_map.fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, shadow_map.fbo);
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, shadow_map.depth_texture, 0, 0);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE{
+                println!("Shadow framebuffer is not complete");
+            }
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            shadow_map.program = crate::prototype::compile_shader_program(SHADOW_VERTEX_SHADER, SHADOW_FRAGMENT_SHADER);
+            shadow_map.light_space_location = gl::GetUniformLocation(shadow_map.program, CString::new("lightSpaceMatrix").unwrap().as_ptr());
+        }
+        shadow_map
+    }
+
+    // kierunek swiatla zmienia sie z pora dnia
+    pub fn set_sun_direction(&mut self, sun_direction: Vec3){
+        self.settings.sun_direction = sun_direction.normalize();
+    }
+
+    // granice kaskad (odleglosci od kamery), cascade_count+1 wartosci od near do far
+    pub fn calculate_cascade_splits(near: f32, far: f32, cascade_count: usize, lambda: f32) -> Vec<f32>{
+        (0..cascade_count+1).map(|i|{
+            // konce ustawione wprost, zeby powf nie przesunal ich o blad zaokraglenia
+            if i == 0{
+                return near;
+            }
+            if i == cascade_count{
+                return far;
+            }
+            let p = i as f32/cascade_count as f32;
+            let logarithmic = near*(far/near).powf(p);
+            let uniform = near+(far-near)*p;
+            lambda*logarithmic+(1.0-lambda)*uniform
+        }).collect()
+    }
+
+    pub fn calculate_light_space_matrix(&self, camera: &Camera, ratio: f32, near: f32, far: f32) -> Matrix4<f32>{
+        let inverse = (camera.get_projection_matrix_range(ratio, near, far)*camera.get_view_matrix())
+            .try_inverse()
+            .unwrap_or(Matrix4::identity());
+        let mut corners: Vec<Vector3<f32>> = Vec::with_capacity(8);
+        for x in [-1.0, 1.0]{
+            for y in [-1.0, 1.0]{
+                for z in [-1.0, 1.0]{
+                    let corner = inverse*Vector4::new(x, y, z, 1.0);
+                    corners.push(corner.xyz()/corner.w);
+                }
+            }
+        }
+        let center = corners.iter().fold(Vector3::zeros(), |sum, corner| sum+corner)/corners.len() as f32;
+        let radius = corners.iter().map(|corner| (corner-center).norm()).fold(0.0f32, f32::max).ceil();
+
+        let sun = self.settings.sun_direction;
+        let direction = Vector3::new(sun.x, sun.y, sun.z).normalize();
+        let up = if direction.y.abs() > 0.99 {Vector3::z()} else {Vector3::y()};
+        let eye = center-direction*(radius+ShadowMap::CASTER_DISTANCE);
+        let light_view = Matrix4::look_at_rh(&Point3::from(eye), &Point3::from(center), &up);
+        let light_projection = Matrix4::new_orthographic(
+            -radius, radius, -radius, radius,
+            0.0, 2.0*radius+ShadowMap::CASTER_DISTANCE
+        );
+
+        // przyciaganie do siatki tekseli, zeby cienie nie migotaly przy ruchu kamery
+        let mut light_space = light_projection*light_view;
+        let origin = light_space*Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let texel = self.settings.resolution as f32/2.0;
+        let offset_x = (origin.x*texel).round()/texel-origin.x;
+        let offset_y = (origin.y*texel).round()/texel-origin.y;
+        light_space = Matrix4::new_translation(&Vector3::new(offset_x, offset_y, 0.0))*light_space;
+        light_space
+    }
+
+    pub fn calculate_cascades(&self, camera: &Camera, ratio: f32) -> (Vec<Matrix4<f32>>, Vec<f32>){
+        let splits = ShadowMap::calculate_cascade_splits(
+            Camera::NEAR, self.settings.max_distance.min(Camera::FAR), self.settings.cascade_count, self.settings.split_lambda
+        );
+        let matrices = splits.windows(2)
+            .map(|range| self.calculate_light_space_matrix(camera, ratio, range[0], range[1]))
+            .collect();
+        // shader dostaje tylko dalekie granice kaskad
+        (matrices, splits[1..].to_vec())
+    }
+
+    // rysuje scene do kazdej warstwy mapy cieni, draw_scene dostaje frustum swiatla do odrzucania chunkow
+    pub fn render(&self, light_space_matrices: &[Matrix4<f32>], draw_scene: impl Fn(&Frustum)){
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::Viewport(0, 0, self.settings.resolution, self.settings.resolution);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Enable(gl::CULL_FACE);
+            gl::CullFace(gl::FRONT);
+            gl::Disable(gl::BLEND);
+            for (layer, matrix) in light_space_matrices.iter().enumerate(){
+                gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, self.depth_texture, 0, layer as i32);
+                gl::Clear(gl::DEPTH_BUFFER_BIT);
+                gl::UniformMatrix4fv(self.light_space_location, 1, gl::FALSE, matrix.as_ptr());
+                draw_scene(&Frustum::from_matrix(matrix));
+            }
+            gl::CullFace(gl::BACK);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    // ustawia uniformy cieni w programie sceny, program musi byc aktywny
+    pub fn bind(&self, program: u32, texture_unit: u32, light_space_matrices: &[Matrix4<f32>], splits: &[f32]){
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0+texture_unit);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.depth_texture);
+            gl::Uniform1i(uniform_location(program, "shadowMap"), texture_unit as i32);
+            gl::Uniform1i(uniform_location(program, "cascadeCount"), light_space_matrices.len() as i32);
+            let sun = self.settings.sun_direction;
+            gl::Uniform3f(uniform_location(program, "sunDirection"), sun.x, sun.y, sun.z);
+            for (i, matrix) in light_space_matrices.iter().enumerate(){
+                gl::UniformMatrix4fv(uniform_location(program, &format!("lightSpaceMatrices[{i}]")), 1, gl::FALSE, matrix.as_ptr());
+            }
+            for (i, split) in splits.iter().enumerate(){
+                gl::Uniform1f(uniform_location(program, &format!("cascadeSplits[{i}]")), *split);
+            }
+        }
+    }
+}
+
+fn uniform_location(program: u32, name: &str) -> i32{
+    unsafe { gl::GetUniformLocation(program, CString::new(name).unwrap().as_ptr()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascade_splits_go_from_near_to_far(){
+        let (near, far) = (0.5, 200.0);
+        for lambda in [0.0, 0.5, 0.75, 1.0]{
+            let splits = ShadowMap::calculate_cascade_splits(near, far, 4, lambda);
+            assert_eq!(splits.len(), 5);
+            assert_eq!(splits[0], near);
+            assert_eq!(splits[4], far);
+            assert!(splits.windows(2).all(|range| range[0] < range[1]), "{lambda}: {splits:?}");
+        }
+        let linear = ShadowMap::calculate_cascade_splits(near, far, 4, 0.0);
+        for (i, split) in linear.iter().enumerate(){
+            assert!((split-(near+(far-near)*i as f32/4.0)).abs() < 1e-3, "{linear:?}");
+        }
+        // przy lambda=1 kolejne granice rosna o ten sam czynnik
+        let logarithmic = ShadowMap::calculate_cascade_splits(near, far, 4, 1.0);
+        let ratio = (far/near).powf(0.25);
+        for range in logarithmic.windows(2){
+            assert!((range[1]/range[0]-ratio).abs() < 1e-3, "{logarithmic:?}");
+        }
+    }
+}
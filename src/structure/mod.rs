@@ -0,0 +1,284 @@
+use std::{fs, path::Path};
+
+use crate::{block::BlockType, chunk_master::ChunkMaster, math::Coord3};
+
+// obrot wokol osi y o wielokrotnosc 90 stopni
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters
+}
+impl Rotation {
+    // w stopniach: 0, 90, 180 albo 270
+    pub fn parse(text: &str) -> Option<Rotation>{
+        match text.trim().parse::<i32>().ok()?.rem_euclid(360) {
+            0 => Some(Rotation::None),
+            90 => Some(Rotation::Quarter),
+            180 => Some(Rotation::Half),
+            270 => Some(Rotation::ThreeQuarters),
+            _ => None
+        }
+    }
+    pub fn apply(self, position: Coord3) -> Coord3{
+        match self {
+            Rotation::None => position,
+            Rotation::Quarter => Coord3::new(position.z, position.y, -position.x),
+            Rotation::Half => Coord3::new(-position.x, position.y, -position.z),
+            Rotation::ThreeQuarters => Coord3::new(-position.z, position.y, position.x)
+        }
+    }
+}
+
+// odbicie wzgledem plaszczyzny prostopadlej do osi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+    X,
+    Y,
+    Z
+}
+impl Mirror {
+    pub fn parse(text: &str) -> Option<Mirror>{
+        match text.trim().to_ascii_lowercase().as_str() {
+            "x" => Some(Mirror::X),
+            "y" => Some(Mirror::Y),
+            "z" => Some(Mirror::Z),
+            _ => None
+        }
+    }
+    pub fn apply(self, position: Coord3) -> Coord3{
+        match self {
+            Mirror::X => Coord3::new(-position.x, position.y, position.z),
+            Mirror::Y => Coord3::new(position.x, -position.y, position.z),
+            Mirror::Z => Coord3::new(position.x, position.y, -position.z)
+        }
+    }
+}
+
+// rzadki zbior blokow jak z WorldGenerator::generate_world, najmniejszy rog w (0, 0, 0);
+// powietrze tez jest blokiem i przy wklejaniu wycina teren
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure{
+    blocks: Vec<(Coord3, BlockType)>
+}
+impl Structure {
+    const VOX_VERSION: u32 = 150;
+
+    pub fn new(blocks: Vec<(Coord3, BlockType)>) -> Self{
+        let mut structure = Structure{blocks};
+        structure.normalize();
+        structure
+    }
+    fn normalize(&mut self){
+        let Some(min) = self.blocks.iter().map(|(position, _)| *position).reduce(|min, p| Coord3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z))) else {
+            return;
+        };
+        for (position, _) in self.blocks.iter_mut(){
+            *position = *position-min;
+        }
+        self.blocks.sort_by_key(|(position, _)| (position.y, position.z, position.x));
+    }
+    pub fn get_blocks(&self) -> &[(Coord3, BlockType)]{
+        &self.blocks
+    }
+    pub fn get_size(&self) -> Coord3{
+        self.blocks.iter()
+            .map(|(position, _)| *position+Coord3::new(1, 1, 1))
+            .reduce(|max, p| Coord3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)))
+            .unwrap_or(Coord3::ZERO)
+    }
+    pub fn rotated(&self, rotation: Rotation) -> Structure{
+        Structure::new(self.blocks.iter().map(|(position, block)| (rotation.apply(*position), *block)).collect())
+    }
+    pub fn mirrored(&self, mirror: Mirror) -> Structure{
+        Structure::new(self.blocks.iter().map(|(position, block)| (mirror.apply(*position), *block)).collect())
+    }
+    // zwraca pozycje, ktore naprawde sie zmienily, np. do przebudowy siatek
+    pub fn paste(&self, world: &impl ChunkMaster, origin: Coord3, rotation: Rotation) -> Vec<Coord3>{
+        let mut changed = Vec::new();
+        for (position, block) in self.rotated(rotation).blocks{
+            let position = origin+position;
+            if world.get_voxel(position) != block && world.set_voxel(position, block){
+                changed.push(position);
+            }
+        }
+        changed
+    }
+
+    // schemat: linie "x y z Blok" jak z eksportu xyz, # zaczyna komentarz
+    pub fn parse_schematic(text: &str) -> Result<Structure, String>{
+        let mut blocks = Vec::new();
+        for (number, line) in text.lines().enumerate(){
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty(){
+                continue;
+            }
+            let [x, y, z, block] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                return Err(format!("line {}: expected 'x y z block'", number+1));
+            };
+            let coord = |value: &str| value.parse::<i32>().map_err(|_| format!("line {}: '{value}' is not a number", number+1));
+            let block = BlockType::parse(block).ok_or(format!("line {}: unknown block '{block}'", number+1))?;
+            blocks.push((Coord3::new(coord(x)?, coord(y)?, coord(z)?), block));
+        }
+        Ok(Structure::new(blocks))
+    }
+    pub fn to_schematic(&self) -> String{
+        let mut text = String::from("# x y z block\n");
+        for (position, block) in self.blocks.iter(){
+            text += &format!("{} {} {} {:?}\n", position.x, position.y, position.z, block);
+        }
+        text
+    }
+
+    // MagicaVoxel .vox: pierwszy model, os z pliku to nasza os y;
+    // kolory palety sa zamieniane na blok o najblizszym kolorze, bez palety indeks to id bloku
+    pub fn parse_vox(bytes: &[u8]) -> Result<Structure, String>{
+        let read_u32 = |offset: usize| -> Result<u32, String>{
+            bytes.get(offset..offset+4).map(|value| u32::from_le_bytes(value.try_into().unwrap())).ok_or("vox file is truncated".to_string())
+        };
+        if bytes.get(0..4) != Some(b"VOX "){
+            return Err("not a vox file".to_string());
+        }
+        if read_u32(4)? != Structure::VOX_VERSION{
+            return Err(format!("unsupported vox version {}", read_u32(4)?));
+        }
+        if bytes.get(8..12) != Some(b"MAIN"){
+            return Err("vox file has no MAIN chunk".to_string());
+        }
+        let (mut size, mut voxels, mut palette): (Option<Coord3>, Option<&[u8]>, Option<&[u8]>) = (None, None, None);
+        let mut offset = 20+read_u32(12)? as usize;
+        while offset < bytes.len(){
+            let id = bytes.get(offset..offset+4).ok_or("vox file is truncated")?;
+            let length = read_u32(offset+4)? as usize;
+            let content = bytes.get(offset+12..offset+12+length).ok_or("vox chunk is truncated")?;
+            match id {
+                b"SIZE" if size.is_none() => {
+                    let read = |i: usize| read_u32(offset+12+i*4).map(|value| value as i32);
+                    size = Some(Coord3::new(read(0)?, read(1)?, read(2)?));
+                }
+                b"XYZI" if voxels.is_none() => {
+                    let count = read_u32(offset+12)? as usize;
+                    voxels = Some(content.get(4..4+count*4).ok_or("vox voxels are truncated")?);
+                }
+                b"RGBA" => palette = Some(content),
+                // pozostale chunki (sceny, materialy) sa pomijane
+                _ => {}
+            }
+            offset += 12+length+read_u32(offset+8)? as usize;
+        }
+        let size = size.ok_or("vox file has no SIZE chunk")?;
+        let voxels = voxels.ok_or("vox file has no XYZI chunk")?;
+        let mut blocks = Vec::new();
+        for voxel in voxels.chunks(4){
+            let [x, y, z, color] = [voxel[0], voxel[1], voxel[2], voxel[3]].map(i32::from);
+            if x >= size.x || y >= size.y || z >= size.z{
+                return Err(format!("voxel {x} {y} {z} is outside the model"));
+            }
+            let block = match palette {
+                Some(palette) => {
+                    let entry = (color as usize).saturating_sub(1)*4;
+                    let rgb = palette.get(entry..entry+3).ok_or("vox palette is truncated")?;
+                    nearest_block([rgb[0], rgb[1], rgb[2]])
+                }
+                None => BlockType::from_id(color as u16).filter(|block| *block != BlockType::Air).unwrap_or(BlockType::Stone)
+            };
+            // odwrotnie niz w eksporcie: y z pliku rosnie w glab
+            blocks.push((Coord3::new(x, z, size.y-1-y), block));
+        }
+        Ok(Structure::new(blocks))
+    }
+
+    // po rozszerzeniu: .vox albo schemat tekstowy
+    pub fn load(path: &Path) -> Result<Structure, String>{
+        let is_vox = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("vox"));
+        let result = if is_vox {
+            fs::read(path).map_err(|error| error.to_string()).and_then(|bytes| Structure::parse_vox(&bytes))
+        } else {
+            fs::read_to_string(path).map_err(|error| error.to_string()).and_then(|text| Structure::parse_schematic(&text))
+        };
+        result.map_err(|error| format!("{}: {error}", path.display()))
+    }
+}
+
+// blok o kolorze najblizszym kolorowi z palety
+fn nearest_block(rgb: [u8; 3]) -> BlockType{
+    BlockType::ALL.into_iter()
+        .filter(|block| *block != BlockType::Air)
+        .min_by(|a, b|{
+            let distance = |block: &BlockType|{
+                let color = block.get_color()*255.0;
+                (color.x-rgb[0] as f32).powi(2)+(color.y-rgb[1] as f32).powi(2)+(color.z-rgb[2] as f32).powi(2)
+            };
+            distance(a).total_cmp(&distance(b))
+        })
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk_master::MemoryWorld, export::{write_vox, Region}};
+
+    use super::*;
+
+    fn house() -> Structure{
+        Structure::new(vec![
+            (Coord3::new(10, 5, 7), BlockType::Stone),
+            (Coord3::new(11, 5, 7), BlockType::Stone),
+            (Coord3::new(10, 6, 7), BlockType::Sand),
+            (Coord3::new(10, 5, 9), BlockType::Dirt)
+        ])
+    }
+
+    #[test]
+    fn structures_are_normalized_and_rotated(){
+        let house = house();
+        assert_eq!(house.get_size(), Coord3::new(2, 2, 3));
+        assert!(house.get_blocks().contains(&(Coord3::new(0, 1, 0), BlockType::Sand)));
+        let quarter = house.rotated(Rotation::Quarter);
+        assert_eq!(quarter.get_size(), Coord3::new(3, 2, 2));
+        let full = [Rotation::Quarter, Rotation::Half].into_iter().fold(quarter, |structure, rotation| structure.rotated(rotation));
+        assert_eq!(full, house);
+        assert_eq!(house.rotated(Rotation::Half).rotated(Rotation::Half), house);
+        assert_eq!(Rotation::parse("-90"), Some(Rotation::ThreeQuarters));
+        assert_eq!(Rotation::parse("45"), None);
+        let mirrored = house.mirrored(Mirror::X);
+        assert!(mirrored.get_blocks().contains(&(Coord3::new(1, 1, 0), BlockType::Sand)));
+        assert_eq!(mirrored.mirrored(Mirror::X), house);
+        assert_eq!(house.mirrored(Mirror::X).mirrored(Mirror::Z), house.rotated(Rotation::Half));
+    }
+
+    #[test]
+    fn schematics_round_trip_and_report_errors(){
+        let house = house();
+        assert_eq!(Structure::parse_schematic(&house.to_schematic()), Ok(house));
+        assert!(Structure::parse_schematic("1 2 3 Stone\n1 2").unwrap_err().starts_with("line 2"));
+        assert!(Structure::parse_schematic("1 2 3 Cheese").is_err());
+        let air = Structure::parse_schematic("5 5 5 air # wyciecie").unwrap();
+        assert_eq!(air.get_blocks(), &[(Coord3::ZERO, BlockType::Air)]);
+    }
+
+    #[test]
+    fn exported_vox_imports_back_with_matching_blocks(){
+        let world = MemoryWorld::with_blocks(house().get_blocks().iter().map(|(position, block)| (*position+Coord3::new(3, 4, 5), *block)));
+        let mut bytes = Vec::new();
+        write_vox(&world, &Region::new(Coord3::new(3, 4, 5), Coord3::new(4, 5, 7)), &mut bytes).unwrap();
+        assert_eq!(Structure::parse_vox(&bytes), Ok(house()));
+        assert!(Structure::parse_vox(&bytes[..bytes.len()-10]).is_err());
+        assert!(Structure::parse_vox(b"VOX \x96\0\0\0").is_err());
+        assert_eq!(nearest_block([180, 128, 20]), BlockType::Sand);
+    }
+
+    #[test]
+    fn paste_rotates_around_the_origin_and_reports_changes(){
+        let world = MemoryWorld::with_chunks(Coord3::ZERO, Coord3::ZERO);
+        let origin = Coord3::new(20, 20, 20);
+        let changed = house().paste(&world, origin, Rotation::Quarter);
+        assert_eq!(changed.len(), 4);
+        for (position, block) in house().rotated(Rotation::Quarter).get_blocks(){
+            assert_eq!(world.get_voxel(origin+*position), *block);
+        }
+        assert!(house().paste(&world, origin, Rotation::Quarter).is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+use std::{fs, path::{Path, PathBuf}};
+
+// katalog w temp dla testow, usuwany przy drop, takze gdy asercja sie nie powiedzie
+pub struct TempDir{
+    path: PathBuf
+}
+impl TempDir {
+    // nazwa musi byc rozna w kazdym tescie, testy ida rownolegle
+    pub fn new(name: &str) -> TempDir{
+        let path = std::env::temp_dir().join(format!("my_opengl_game_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        TempDir{path}
+    }
+    pub fn get_path(&self) -> &Path{
+        &self.path
+    }
+}
+impl AsRef<Path> for TempDir{
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+impl Drop for TempDir{
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_is_removed_when_the_test_panics(){
+        let path = std::panic::catch_unwind(||{
+            let dir = TempDir::new("test_util");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.get_path().join("file.txt"), "text").unwrap();
+            panic!("{}", dir.get_path().display());
+        }).unwrap_err();
+        let path = PathBuf::from(path.downcast_ref::<String>().unwrap());
+        assert!(!path.exists());
+    }
+}
@@ -1,6 +1,58 @@
-use crate::{Quat, Vec3};
+use nalgebra::Matrix4;
 
-struct transform{
-    translation: Vec3,
-    rotation: Quat
-}
\ No newline at end of file
+use crate::math::{Quat, Vec3};
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Transform{
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3
+}
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::new(Vec3::ZERO)
+    }
+}
+impl Transform {
+    pub fn new(position: Vec3) -> Self{
+        Transform{
+            position,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::new(1.0, 1.0, 1.0)
+        }
+    }
+    // kolumny to obrocone i przeskalowane osie, ostatnia to przesuniecie
+    pub fn get_model_matrix(&self) -> Matrix4<f32>{
+        let x = self.rotation.to_direction(Vec3::RIGHT)*self.scale.x;
+        let y = self.rotation.to_direction(Vec3::UP)*self.scale.y;
+        let z = self.rotation.to_direction(Vec3::FORWARD)*self.scale.z;
+        let p = self.position;
+        Matrix4::new(
+            x.x, y.x, z.x, p.x,
+            x.y, y.y, z.y, p.y,
+            x.z, y.z, z.z, p.z,
+            0.0, 0.0, 0.0, 1.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector4;
+
+    use super::*;
+
+    #[test]
+    fn model_matrix_scales_rotates_and_translates(){
+        let transform = Transform{
+            position: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation(std::f32::consts::FRAC_PI_2, Vec3::UP),
+            scale: Vec3::new(2.0, 1.0, 1.0)
+        };
+        let point = transform.get_model_matrix()*Vector4::new(1.0, 0.0, 0.0, 1.0);
+        // obrot o 90 stopni wokol y przenosi +x na -z
+        assert!((point.x-1.0).abs() < 1e-4);
+        assert!((point.y-2.0).abs() < 1e-4);
+        assert!((point.z-1.0).abs() < 1e-4);
+    }
+}
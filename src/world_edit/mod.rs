@@ -0,0 +1,232 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{block::BlockType, chunk::Chunk, chunk_master::ChunkMaster, export::Region, math::Coord3, structure::Structure};
+
+// edycja wiekszych obszarow: ksztalty, schowek i historia zmian do cofania
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Cuboid(Region),
+    Sphere{center: Coord3, radius: i32},
+    // pionowy walec, center to srodek podstawy
+    Cylinder{center: Coord3, radius: i32, height: i32}
+}
+impl Shape {
+    pub fn get_bounds(&self) -> Region{
+        match *self {
+            Shape::Cuboid(region) => region,
+            Shape::Sphere{center, radius} => Region::new(center-Coord3::new(radius, radius, radius), center+Coord3::new(radius, radius, radius)),
+            Shape::Cylinder{center, radius, height} => Region::new(center-Coord3::new(radius, 0, radius), center+Coord3::new(radius, height.max(1)-1, radius))
+        }
+    }
+    pub fn contains(&self, position: Coord3) -> bool{
+        match *self {
+            Shape::Cuboid(region) => region.contains(position),
+            Shape::Sphere{center, radius} => position.distance2(center) <= radius.pow(2),
+            Shape::Cylinder{center, radius, ..} => {
+                let offset = position-center;
+                self.get_bounds().contains(position) && offset.x.pow(2)+offset.z.pow(2) <= radius.pow(2)
+            }
+        }
+    }
+    pub fn positions(&self) -> impl Iterator<Item = Coord3> + '_{
+        let bounds = self.get_bounds();
+        (bounds.min.x..=bounds.max.x).flat_map(move |x| (bounds.min.y..=bounds.max.y)
+            .flat_map(move |y| (bounds.min.z..=bounds.max.z).map(move |z| Coord3::new(x, y, z))))
+            .filter(|position| self.contains(*position))
+    }
+    // liczba blokow obejmujacego prostopadloscianu, do limitow przed edycja
+    pub fn get_volume(&self) -> i64{
+        let size = self.get_bounds().get_size();
+        size.x as i64*size.y as i64*size.z as i64
+    }
+}
+
+// zmiany jednej operacji pogrupowane po chunkach: pozycja, blok przed i po
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Edit{
+    chunks: HashMap<Coord3, Vec<(Coord3, BlockType, BlockType)>>
+}
+impl Edit {
+    // zapisuje tylko prawdziwe zmiany w zaladowanych chunkach
+    pub fn set(&mut self, world: &impl ChunkMaster, position: Coord3, block: BlockType) -> bool{
+        let before = world.get_voxel(position);
+        if before == block || !world.set_voxel(position, block){
+            return false;
+        }
+        self.chunks.entry(position.div_euclid(Chunk::CHUNK_SIZE as i32)).or_default().push((position, before, block));
+        true
+    }
+    pub fn is_empty(&self) -> bool{
+        self.chunks.is_empty()
+    }
+    pub fn len(&self) -> usize{
+        self.chunks.values().map(|changes| changes.len()).sum()
+    }
+    pub fn get_changed_chunks(&self) -> Vec<Coord3>{
+        self.chunks.keys().copied().collect()
+    }
+    pub fn get_changed_positions(&self) -> Vec<Coord3>{
+        self.chunks.values().flatten().map(|(position, _, _)| *position).collect()
+    }
+    // przy cofaniu zmiany sa odwracane od konca, zeby ten sam blok zmieniony dwa razy wrocil do poczatku
+    fn apply(&self, world: &impl ChunkMaster, undo: bool){
+        for changes in self.chunks.values(){
+            if undo {
+                for (position, before, _) in changes.iter().rev(){
+                    world.set_voxel(*position, *before);
+                }
+            } else {
+                for (position, _, after) in changes.iter(){
+                    world.set_voxel(*position, *after);
+                }
+            }
+        }
+    }
+}
+
+pub fn fill(world: &impl ChunkMaster, shape: &Shape, block: BlockType) -> Edit{
+    let mut edit = Edit::default();
+    for position in shape.positions(){
+        edit.set(world, position, block);
+    }
+    edit
+}
+pub fn replace(world: &impl ChunkMaster, shape: &Shape, from: BlockType, to: BlockType) -> Edit{
+    let mut edit = Edit::default();
+    for position in shape.positions().filter(|position| world.get_voxel(*position) == from){
+        edit.set(world, position, to);
+    }
+    edit
+}
+// do schowka trafia tez powietrze, zeby wklejenie odtworzylo caly obszar
+pub fn copy(world: &impl ChunkMaster, region: &Region) -> Structure{
+    Structure::new(region.positions().map(|position| (position, world.get_voxel(position))).collect())
+}
+// obrot i odbicie robi sie na schowku przed wklejeniem
+pub fn paste(world: &impl ChunkMaster, clipboard: &Structure, origin: Coord3) -> Edit{
+    let mut edit = Edit::default();
+    for (position, block) in clipboard.get_blocks(){
+        edit.set(world, origin+*position, *block);
+    }
+    edit
+}
+
+// cofanie i ponawianie, nowa edycja kasuje ponawianie
+#[derive(Debug, Default)]
+pub struct History{
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>
+}
+impl History {
+    pub const MAX_EDITS: usize = 32;
+
+    pub fn push(&mut self, edit: Edit){
+        if edit.is_empty(){
+            return;
+        }
+        self.redo.clear();
+        self.undo.push_back(edit);
+        if self.undo.len() > History::MAX_EDITS{
+            self.undo.pop_front();
+        }
+    }
+    // zwraca cofnieta edycje, jej pozycje trzeba przebudowac
+    pub fn undo(&mut self, world: &impl ChunkMaster) -> Option<&Edit>{
+        let edit = self.undo.pop_back()?;
+        edit.apply(world, true);
+        self.redo.push(edit);
+        self.redo.last()
+    }
+    pub fn redo(&mut self, world: &impl ChunkMaster) -> Option<&Edit>{
+        let edit = self.redo.pop()?;
+        edit.apply(world, false);
+        self.undo.push_back(edit);
+        self.undo.back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk_master::MemoryWorld, structure::{Mirror, Rotation}};
+
+    use super::*;
+
+    fn empty_world() -> MemoryWorld{
+        MemoryWorld::with_chunks(Coord3::ZERO, Coord3::ZERO)
+    }
+
+    #[test]
+    fn shapes_cover_expected_blocks(){
+        let cuboid = Shape::Cuboid(Region::new(Coord3::new(2, 2, 2), Coord3::new(0, 0, 0)));
+        assert_eq!(cuboid.positions().count(), 27);
+        let sphere = Shape::Sphere{center: Coord3::new(10, 10, 10), radius: 1};
+        assert_eq!(sphere.positions().count(), 7);
+        assert_eq!(sphere.get_volume(), 27);
+        let cylinder = Shape::Cylinder{center: Coord3::new(10, 10, 10), radius: 1, height: 3};
+        assert_eq!(cylinder.positions().count(), 15);
+        assert!(cylinder.contains(Coord3::new(10, 12, 11)));
+        assert!(!cylinder.contains(Coord3::new(10, 13, 10)));
+    }
+
+    #[test]
+    fn fill_and_replace_record_only_changes(){
+        let world = empty_world();
+        let shape = Shape::Cuboid(Region::new(Coord3::new(1, 1, 1), Coord3::new(3, 1, 3)));
+        world.set_voxel(Coord3::new(2, 1, 2), BlockType::Stone);
+        let edit = fill(&world, &shape, BlockType::Stone);
+        assert_eq!(edit.len(), 8);
+        assert_eq!(edit.get_changed_chunks(), vec![Coord3::ZERO]);
+        let edit = replace(&world, &Shape::Sphere{center: Coord3::new(2, 1, 2), radius: 1}, BlockType::Stone, BlockType::Sand);
+        assert_eq!(edit.len(), 5);
+        assert_eq!(world.get_voxel(Coord3::new(1, 1, 1)), BlockType::Stone);
+        assert_eq!(world.get_voxel(Coord3::new(1, 1, 2)), BlockType::Sand);
+        // poza zaladowanymi chunkami nic sie nie zmienia
+        assert!(fill(&world, &Shape::Cuboid(Region::new(Coord3::new(-2, 0, 0), Coord3::new(-1, 0, 0))), BlockType::Dirt).is_empty());
+    }
+
+    #[test]
+    fn clipboard_pastes_rotated_and_mirrored(){
+        let world = empty_world();
+        world.set_voxel(Coord3::new(1, 1, 1), BlockType::Stone);
+        world.set_voxel(Coord3::new(2, 1, 1), BlockType::Sand);
+        let clipboard = copy(&world, &Region::new(Coord3::new(1, 1, 1), Coord3::new(2, 1, 1)));
+        assert_eq!(clipboard.get_size(), Coord3::new(2, 1, 1));
+        let origin = Coord3::new(10, 1, 10);
+        assert_eq!(paste(&world, &clipboard.mirrored(Mirror::X), origin).len(), 2);
+        assert_eq!(world.get_voxel(origin), BlockType::Sand);
+        assert_eq!(world.get_voxel(origin+Coord3::new(1, 0, 0)), BlockType::Stone);
+        paste(&world, &clipboard.rotated(Rotation::Quarter), origin+Coord3::new(0, 5, 0));
+        assert_eq!(world.get_voxel(origin+Coord3::new(0, 5, 1)), BlockType::Stone);
+        assert_eq!(world.get_voxel(origin+Coord3::new(0, 5, 0)), BlockType::Sand);
+    }
+
+    #[test]
+    fn history_undoes_and_redoes_edits(){
+        let world = empty_world();
+        let mut history = History::default();
+        let position = Coord3::new(5, 5, 5);
+        let shape = Shape::Cuboid(Region::new(position, position));
+        history.push(fill(&world, &shape, BlockType::Stone));
+        history.push(fill(&world, &shape, BlockType::Sand));
+        history.push(fill(&world, &shape, BlockType::Sand));
+        assert_eq!(history.undo(&world).unwrap().get_changed_positions(), vec![position]);
+        assert_eq!(world.get_voxel(position), BlockType::Stone);
+        history.undo(&world);
+        assert_eq!(world.get_voxel(position), BlockType::Air);
+        assert!(history.undo(&world).is_none());
+        history.redo(&world);
+        assert_eq!(world.get_voxel(position), BlockType::Stone);
+        history.push(fill(&world, &shape, BlockType::Dirt));
+        assert!(history.redo(&world).is_none());
+        for _ in 0..History::MAX_EDITS+5{
+            let block = if world.get_voxel(position) == BlockType::Dirt { BlockType::Sand } else { BlockType::Dirt };
+            history.push(fill(&world, &shape, block));
+        }
+        let mut undone = 0;
+        while history.undo(&world).is_some(){
+            undone += 1;
+        }
+        assert_eq!(undone, History::MAX_EDITS);
+    }
+}
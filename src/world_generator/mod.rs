@@ -4,7 +4,7 @@ use nalgebra::Vector3;
 use noise::{NoiseFn, Perlin};
 use rayon::range;
 
-use crate::{block::BlockType, math::Coord3, Chunk, ChunkCoordsIterator};
+use crate::{block::BlockType, chunk::Chunk, math::Coord3};
 
 pub struct WorldGenerator{
     seed: u32,
@@ -14,13 +14,17 @@ const SEED: u32 = 2137;
 impl WorldGenerator {
     const STONE_LAYER: i32 = 5;
     const WATER_LEVEL: i32 = 4;
-    const RANGE: i32 = 300;
+    // zasieg generate_world; teren generate_chunk i get_voxel_type nie ma granicy
+    pub const RANGE: i32 = 300;
     pub fn new(seed: u32) -> WorldGenerator{
         WorldGenerator{
             seed,
             perlin: Perlin::new(seed)
         }
     }
+    pub fn get_seed(&self) -> u32{
+        self.seed
+    }
     #[inline(always)]
     pub fn get_terrein_height(&self, world_position: Coord3) -> i32{
         let frequency: f64 = 0.015;
@@ -44,13 +48,14 @@ impl WorldGenerator {
     }
     #[inline(always)]
     pub fn get_voxel_type(&self, world_position: Coord3) -> BlockType{
+        self.get_voxel_type_with_height(world_position, self.get_terrein_height(world_position))
+    }
+    // th - wysokosc terenu w kolumnie world_position, pozwala liczyc szum raz na kolumne
+    #[inline(always)]
+    pub fn get_voxel_type_with_height(&self, world_position: Coord3, th: i32) -> BlockType{
         let wy = world_position.y;
-        let th = self.get_terrein_height(world_position);
 
-        if world_position.distance2(Coord3::ZERO) > Self::RANGE.pow(2){
-            return BlockType::Air;
-        }     
-        else if wy == th && wy > WorldGenerator::WATER_LEVEL+1{
+        if wy == th && wy > WorldGenerator::WATER_LEVEL+1{
             return BlockType::Grass
         }
         else if wy==th || (wy==th-1 && th<WorldGenerator::WATER_LEVEL) {
@@ -68,17 +73,8 @@ impl WorldGenerator {
         BlockType::Air
     }
     pub fn generate_chunk(&self, chunk: &mut Chunk){
-        let chunk_range = WorldGenerator::RANGE/Chunk::CHUNK_SIZE as i32+3;
-        if chunk.get_chunk_position().distance2(Coord3::ZERO)>(chunk_range).pow(2){
-            return;
-        }
         for x in 0..Chunk::CHUNK_SIZE{
             for z in 0..Chunk::CHUNK_SIZE{  
-                let mut world_position = chunk.get_world_position(Coord3::new(x as i32, 0, z as i32)); 
-                world_position.y = 0;
-                if world_position.distance2(Coord3::ZERO)>Self::RANGE.pow(2){
-                    //continue;
-                }
                 for y in 0..Chunk::CHUNK_SIZE{
                     let local_position = Coord3::new(x as i32, y as i32, z as i32); 
                     let world_position = chunk.get_world_position(local_position); 
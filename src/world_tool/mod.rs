@@ -0,0 +1,269 @@
+use std::{fmt::Display, io};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{block::BlockType, chunk::Chunk, chunk_master::{ChunkMaster, MemoryWorld}, export::Region, math::Coord3, persistence::WorldStorage, structure::{Rotation, Structure}, world_generator::WorldGenerator};
+
+// operacje na zapisanym swiecie bez okna i OpenGL, uzywane przez voxel-tool
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PregenStats{
+    pub generated: usize,
+    // chunki, ktore juz byly na dysku
+    pub skipped: usize
+}
+
+// chunki w kuli o promieniu w chunkach (jak w ChunkLoader), zapisane chunki nie sa nadpisywane
+pub fn pregen(storage: &WorldStorage, generator: &WorldGenerator, center: Coord3, radius: i32) -> io::Result<PregenStats>{
+    let mut positions = Vec::new();
+    for x in -radius..=radius{
+        for y in -radius..=radius{
+            for z in -radius..=radius{
+                let offset = Coord3::new(x, y, z);
+                if offset.magnitude2() <= radius.pow(2){
+                    positions.push(center+offset);
+                }
+            }
+        }
+    }
+    let stored = storage.list_chunks()?;
+    let generated: Vec<bool> = positions.par_iter().map(|position| -> io::Result<bool>{
+        if stored.contains(position){
+            return Ok(false);
+        }
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(*position);
+        generator.generate_chunk(&mut chunk);
+        storage.save_chunk(&chunk)?;
+        Ok(true)
+    }).collect::<io::Result<Vec<bool>>>()?;
+    let count = generated.iter().filter(|generated| **generated).count();
+    Ok(PregenStats{generated: count, skipped: generated.len()-count})
+}
+
+// FNV-1a z zapisu chunka, taki sam dla takich samych blokow i wody
+pub fn checksum(chunk: &Chunk) -> u64{
+    chunk.encode().iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+pub struct ChunkInfo{
+    pub position: Coord3,
+    // tylko bloki, ktore wystepuja w chunku, w kolejnosci BlockType::ALL
+    pub histogram: Vec<(BlockType, usize)>,
+    pub checksum: u64
+}
+impl ChunkInfo {
+    pub fn new(chunk: &Chunk) -> Self{
+        let mut counts = [0; BlockType::ALL.len()];
+        for index in 0..Chunk::CHUNK_SIZE.pow(3){
+            counts[chunk.get_voxel_from_index(index) as usize] += 1;
+        }
+        ChunkInfo{
+            position: chunk.get_chunk_position(),
+            histogram: BlockType::ALL.into_iter().zip(counts).filter(|(_, count)| *count > 0).collect(),
+            checksum: checksum(chunk)
+        }
+    }
+}
+impl Display for ChunkInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "chunk {}", self.position)?;
+        writeln!(f, "checksum: {:016x}", self.checksum)?;
+        for (block, count) in self.histogram.iter(){
+            writeln!(f, "{block:?}: {count} ({:.2}%)", *count as f32/Chunk::CHUNK_SIZE.pow(3) as f32*100.0)?;
+        }
+        Ok(())
+    }
+}
+
+// pozycja chunka albo bloku jako "x,y,z"
+pub fn parse_position(text: &str) -> Result<Coord3, String>{
+    let coords: Vec<i32> = text.split(',').map(|value| value.trim().parse().map_err(|_| format!("'{text}' is not a position x,y,z"))).collect::<Result<_, _>>()?;
+    match coords[..] {
+        [x, y, z] => Ok(Coord3::new(x, y, z)),
+        _ => Err(format!("'{text}' is not a position x,y,z"))
+    }
+}
+
+// zapisane chunki z obszaru, brakujace sa pomijane i czytane jako powietrze
+pub fn load_region<W: ChunkMaster + Default>(storage: &WorldStorage, region: &Region) -> io::Result<W>{
+    let world = W::default();
+    for position in region.get_chunks(){
+        if let Some(chunk) = storage.load_chunk(position)?{
+            world.insert_chunk(chunk);
+        }
+    }
+    Ok(world)
+}
+
+// obszar obejmujacy wszystkie zapisane chunki
+pub fn get_saved_region(storage: &WorldStorage) -> io::Result<Option<Region>>{
+    let chunks = storage.list_chunks()?;
+    let Some(first) = chunks.first() else {
+        return Ok(None);
+    };
+    let region = chunks.iter().fold(Region::from_chunk(*first), |region, position|{
+        let chunk = Region::from_chunk(*position);
+        Region::new(
+            Coord3::new(region.min.x.min(chunk.min.x), region.min.y.min(chunk.min.y), region.min.z.min(chunk.min.z)),
+            Coord3::new(region.max.x.max(chunk.max.x), region.max.y.max(chunk.max.y), region.max.z.max(chunk.max.z))
+        )
+    });
+    Ok(Some(region))
+}
+
+// niezapisane chunki sa najpierw generowane, inaczej zostalaby w nich sama struktura;
+// zwraca liczbe zmienionych blokow
+pub fn paste_structure(storage: &WorldStorage, generator: &WorldGenerator, structure: &Structure, origin: Coord3, rotation: Rotation) -> io::Result<usize>{
+    let size = structure.rotated(rotation).get_size();
+    if size == Coord3::ZERO{
+        return Ok(0);
+    }
+    let region = Region::new(origin, origin+size-Coord3::new(1, 1, 1));
+    let world: MemoryWorld = load_region(storage, &region)?;
+    for position in region.get_chunks().into_iter().filter(|position| !world.chunk_exists(*position)){
+        let mut chunk = Chunk::default();
+        chunk.set_chunk_position(position);
+        generator.generate_chunk(&mut chunk);
+        world.insert_chunk(chunk);
+    }
+    let changed = structure.paste(&world, origin, rotation);
+    let mut chunks: Vec<Coord3> = changed.iter().map(|position| position.div_euclid(Chunk::CHUNK_SIZE as i32)).collect();
+    chunks.sort_by_key(|position| (position.x, position.y, position.z));
+    chunks.dedup();
+    for position in chunks{
+        storage.save_chunk(&world.get_chunk(position).unwrap())?;
+    }
+    Ok(changed.len())
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct WorldDiff{
+    pub seeds: (Option<u32>, Option<u32>),
+    pub only_in_a: Vec<Coord3>,
+    pub only_in_b: Vec<Coord3>,
+    // chunki zapisane w obu swiatach i liczba roznych wokseli
+    pub changed: Vec<(Coord3, usize)>
+}
+impl WorldDiff {
+    pub fn is_empty(&self) -> bool{
+        self.seeds.0 == self.seeds.1 && self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.changed.is_empty()
+    }
+}
+impl Display for WorldDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.seeds.0 != self.seeds.1{
+            writeln!(f, "seed: {:?} != {:?}", self.seeds.0, self.seeds.1)?;
+        }
+        for position in self.only_in_a.iter(){
+            writeln!(f, "only in a: {position}")?;
+        }
+        for position in self.only_in_b.iter(){
+            writeln!(f, "only in b: {position}")?;
+        }
+        for (position, count) in self.changed.iter(){
+            writeln!(f, "changed: {position}, {count} voxels")?;
+        }
+        Ok(())
+    }
+}
+
+// roznica woksela to inny blok albo inny poziom wody
+pub fn count_differences(a: &Chunk, b: &Chunk) -> usize{
+    (0..Chunk::CHUNK_SIZE.pow(3)).filter(|index|{
+        let local = Chunk::get_local_position_from_index(*index);
+        a.get_voxel_from_index(*index) != b.get_voxel_from_index(*index) || a.get_fluid_level(local) != b.get_fluid_level(local)
+    }).count()
+}
+
+pub fn diff(a: &WorldStorage, b: &WorldStorage) -> io::Result<WorldDiff>{
+    let (chunks_a, chunks_b) = (a.list_chunks()?, b.list_chunks()?);
+    let mut diff = WorldDiff{
+        seeds: (a.load_seed()?, b.load_seed()?),
+        only_in_a: chunks_a.iter().filter(|position| !chunks_b.contains(position)).copied().collect(),
+        only_in_b: chunks_b.iter().filter(|position| !chunks_a.contains(position)).copied().collect(),
+        changed: Vec::new()
+    };
+    for position in chunks_a.iter().filter(|position| chunks_b.contains(position)){
+        if let (Some(chunk_a), Some(chunk_b)) = (a.load_chunk(*position)?, b.load_chunk(*position)?){
+            if checksum(&chunk_a) != checksum(&chunk_b){
+                diff.changed.push((*position, count_differences(&chunk_a, &chunk_b)));
+            }
+        }
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk_master::ChunkMaster, test_util::TempDir};
+
+    use super::*;
+
+    #[test]
+    fn pregen_generates_missing_chunks_only(){
+        let dir = TempDir::new("tool_pregen");
+        let storage = WorldStorage::open(&dir).unwrap();
+        let generator = WorldGenerator::new(2137);
+        assert_eq!(pregen(&storage, &generator, Coord3::ZERO, 1).unwrap(), PregenStats{generated: 7, skipped: 0});
+        assert_eq!(pregen(&storage, &generator, Coord3::new(1, 0, 0), 1).unwrap(), PregenStats{generated: 5, skipped: 2});
+        let chunk = storage.load_chunk(Coord3::ZERO).unwrap().unwrap();
+        let info = ChunkInfo::new(&chunk);
+        assert_eq!(info.histogram.iter().map(|(_, count)| count).sum::<usize>(), Chunk::CHUNK_SIZE.pow(3));
+        assert!(info.histogram.iter().any(|(block, _)| *block == BlockType::Stone));
+        let mut expected = Chunk::default();
+        generator.generate_chunk(&mut expected);
+        assert_eq!(info.checksum, checksum(&expected));
+        assert_eq!(parse_position("-1, 2,3"), Ok(Coord3::new(-1, 2, 3)));
+        assert!(parse_position("1,2").is_err());
+    }
+
+    #[test]
+    fn diff_reports_chunks_and_voxels(){
+        let (dir_a, dir_b) = (TempDir::new("tool_diff_a"), TempDir::new("tool_diff_b"));
+        let (a, b) = (WorldStorage::open(&dir_a).unwrap(), WorldStorage::open(&dir_b).unwrap());
+        a.save_seed(1).unwrap();
+        b.save_seed(1).unwrap();
+        let mut chunk = Chunk::default();
+        chunk.set_voxel(Coord3::new(1, 2, 3), BlockType::Sand);
+        a.save_chunk(&chunk).unwrap();
+        b.save_chunk(&chunk).unwrap();
+        assert!(diff(&a, &b).unwrap().is_empty());
+
+        chunk.set_voxel(Coord3::new(4, 5, 6), BlockType::Stone);
+        chunk.set_voxel(Coord3::new(1, 2, 3), BlockType::Air);
+        b.save_chunk(&chunk).unwrap();
+        chunk.set_chunk_position(Coord3::new(0, -1, 0));
+        a.save_chunk(&chunk).unwrap();
+        let result = diff(&a, &b).unwrap();
+        assert_eq!(result, WorldDiff{
+            seeds: (Some(1), Some(1)),
+            only_in_a: vec![Coord3::new(0, -1, 0)],
+            only_in_b: Vec::new(),
+            changed: vec![(Coord3::ZERO, 2)]
+        });
+        assert_eq!(result.to_string(), "only in a: x:0, y:-1 z:0\nchanged: x:0, y:0 z:0, 2 voxels\n");
+
+        let region = get_saved_region(&a).unwrap().unwrap();
+        assert_eq!(region, Region::new(Coord3::new(0, -50, 0), Coord3::new(49, 49, 49)));
+        let loaded: MemoryWorld = load_region(&b, &Region::new(Coord3::new(4, 5, 6), Coord3::new(4, 5, 60))).unwrap();
+        assert_eq!(loaded.get_chunk_positions(), vec![Coord3::ZERO]);
+        assert_eq!(loaded.get_voxel(Coord3::new(4, 5, 6)), BlockType::Stone);
+    }
+
+    #[test]
+    fn pasted_structures_land_in_generated_chunks(){
+        let dir = TempDir::new("tool_paste");
+        let storage = WorldStorage::open(&dir).unwrap();
+        let generator = WorldGenerator::new(2137);
+        let structure = Structure::new(vec![(Coord3::ZERO, BlockType::Sand), (Coord3::new(1, 0, 0), BlockType::Sand)]);
+        // struktura na granicy chunkow
+        let origin = Coord3::new(49, 300, 0);
+        assert_eq!(paste_structure(&storage, &generator, &structure, origin, Rotation::None).unwrap(), 2);
+        assert_eq!(storage.list_chunks().unwrap().len(), 2);
+        let world: MemoryWorld = load_region(&storage, &Region::new(origin, origin+Coord3::new(1, 0, 0))).unwrap();
+        assert_eq!(world.get_voxel(origin), BlockType::Sand);
+        assert_eq!(world.get_voxel(origin+Coord3::new(1, 0, 0)), BlockType::Sand);
+        assert_eq!(paste_structure(&storage, &generator, &structure, origin, Rotation::None).unwrap(), 0);
+    }
+}